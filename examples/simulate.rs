@@ -0,0 +1,199 @@
+//! Host-side simulation of a swapping strategy, printing every operation and the resulting slot contents.
+//!
+//! Usage: `cargo run --example simulate -- [swap-scootch|swap-sabs] [PAGES] [SCRATCH_PAGES]`
+//!
+//! Each page of the primary image is shown as `a<index>`, each page of the secondary image as `b<index>`,
+//! and an erased page as `--`.
+
+use std::{env, fmt::Write, num::NonZeroU16, process};
+
+use bootlick::{
+    CopyOperation, Device, DeviceWithPrimarySlot, DeviceWithScratch, Error, MemoryLocation, Slot,
+    Step,
+    strategies::{FromRequest, Strategy, swap_sabs, swap_scootch},
+};
+
+const PRIMARY: Slot = Slot(0);
+const SECONDARY: Slot = Slot(1);
+const SCRATCH: Slot = Slot(2);
+
+/// Contents of a page: the image and page index it originates from, or `None` when erased.
+type Tag = Option<(char, u16)>;
+
+/// Device holding a primary and secondary image and a scratch slot in RAM, tracking the wear of every page.
+struct SimulatedDevice {
+    slots: [Vec<Tag>; 3],
+    wear: [Vec<usize>; 3],
+}
+
+impl SimulatedDevice {
+    fn new(page_count: NonZeroU16, scratch_page_count: NonZeroU16) -> Self {
+        let image = |name| {
+            (0..page_count.get())
+                .map(|page| Some((name, page)))
+                .collect()
+        };
+        let pages = page_count.get() as usize;
+        let scratch_pages = scratch_page_count.get() as usize;
+
+        Self {
+            slots: [image('a'), image('b'), vec![None; scratch_pages]],
+            wear: [vec![0; pages], vec![0; pages], vec![0; scratch_pages]],
+        }
+    }
+
+    fn page(&mut self, location: MemoryLocation) -> Result<(&mut Tag, &mut usize), Error> {
+        let slot = location.slot.0 as usize;
+        let page = location.page.as_u16() as usize;
+
+        match (self.slots.get_mut(slot), self.wear.get_mut(slot)) {
+            (Some(tags), Some(wear)) => Ok((
+                tags.get_mut(page).ok_or(Error::OutOfRange)?,
+                wear.get_mut(page).ok_or(Error::OutOfRange)?,
+            )),
+            _ => Err(Error::OutOfRange),
+        }
+    }
+
+    fn print_slots(&self) {
+        for (name, tags) in ["primary", "secondary", "scratch"].iter().zip(&self.slots) {
+            let mut line = String::new();
+            for tag in tags {
+                match tag {
+                    Some((image, page)) => write!(line, " {image}{page}").unwrap(),
+                    None => line.push_str(" --"),
+                }
+            }
+            println!("    {name:>9}:{line}");
+        }
+    }
+
+    fn print_wear(&self) {
+        for (name, wear) in ["primary", "secondary", "scratch"].iter().zip(&self.wear) {
+            let max = wear.iter().max().copied().unwrap_or(0);
+            let total: usize = wear.iter().sum();
+            println!("    {name:>9}: {wear:?} (max {max}, total {total})");
+        }
+    }
+}
+
+impl Device for SimulatedDevice {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), Error> {
+        let tag = *self.page(operation.from)?.0;
+        let (to, wear) = self.page(operation.to)?;
+        *to = tag;
+        *wear += 1;
+        Ok(())
+    }
+
+    fn boot(self, _slot: Slot) -> ! {
+        unimplemented!("the simulation never boots")
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        NonZeroU16::new(self.slots[0].len() as u16).unwrap()
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        self.slots
+            .get(slot.0 as usize)
+            .map_or(0, |tags| tags.len() as u16)
+    }
+}
+
+impl DeviceWithScratch for SimulatedDevice {
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        NonZeroU16::new(self.slots[2].len() as u16).unwrap()
+    }
+
+    fn get_scratch(&self) -> Slot {
+        SCRATCH
+    }
+}
+
+impl DeviceWithPrimarySlot for SimulatedDevice {
+    fn get_primary(&self) -> Slot {
+        PRIMARY
+    }
+}
+
+/// Execute all steps of the strategy one by one, printing the operations of each step.
+fn simulate(device: &mut SimulatedDevice, strategy: &impl Strategy) -> Result<(), Error> {
+    strategy.verify_layout(device)?;
+
+    for step in Step::range(Step::default(), strategy.last_step()) {
+        println!("step {step} ({}):", strategy.phase_name(step));
+
+        for operation in strategy.plan(step) {
+            println!(
+                "  copy {}:{} -> {}:{}",
+                operation.from.slot.0, operation.from.page, operation.to.slot.0, operation.to.page
+            );
+            embassy_futures::block_on(device.copy(operation))?;
+        }
+
+        device.print_slots();
+    }
+
+    Ok(())
+}
+
+fn usage() -> ! {
+    eprintln!("usage: simulate [swap-scootch|swap-sabs] [PAGES] [SCRATCH_PAGES]");
+    process::exit(2);
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let name = args.next().unwrap_or_else(|| "swap-scootch".into());
+    if !["swap-scootch", "swap-sabs"].contains(&name.as_str()) {
+        usage();
+    }
+    let mut count = |default| match args.next() {
+        Some(arg) => arg.parse().unwrap_or_else(|_| usage()),
+        None => NonZeroU16::new(default).unwrap(),
+    };
+    let page_count = count(4);
+    let scratch_page_count = count(1);
+
+    let mut device = SimulatedDevice::new(page_count, scratch_page_count);
+    println!("initial:");
+    device.print_slots();
+
+    let result = match name.as_str() {
+        "swap-scootch" => {
+            let request = swap_scootch::Request {
+                slot_secondary: SECONDARY,
+            };
+            let strategy = swap_scootch::SwapScootch::from_request(&device, request);
+            simulate(&mut device, &strategy)
+        }
+        "swap-sabs" => {
+            let request = swap_sabs::Request {
+                slot_secondary: SECONDARY,
+            };
+            let strategy = swap_sabs::SwapSABS::from_request(&device, request);
+            simulate(&mut device, &strategy)
+        }
+        _ => usage(),
+    };
+
+    if let Err(error) = result {
+        eprintln!("simulation failed: {error}");
+        process::exit(1);
+    }
+
+    println!("wear:");
+    device.print_wear();
+
+    let image = |name| (0..page_count.get()).map(move |page| Some((name, page)));
+    assert!(
+        device.slots[0].iter().copied().eq(image('b')),
+        "primary should hold the secondary image"
+    );
+    assert!(
+        device.slots[1].iter().copied().eq(image('a')),
+        "secondary should hold the primary image"
+    );
+    println!("images swapped");
+}
@@ -6,9 +6,14 @@ mod fake;
 mod partitions;
 
 use bootlick::{
-    state::{simple::SimpleStateStorage, State, StateStorage},
-    strategies::swap_scootch::{self, SwapScootch},
-    Device, DeviceWithPrimarySlot, DeviceWithScratch, MemoryLocation, Slot,
+    adapters::BlockingToAsync,
+    boot::{boot_entry, cortex_m::SimpleCortexM},
+    flow::BootFlow,
+    prelude::*,
+    runner::StrategyRunner,
+    state::simple::SimpleStateStorage,
+    strategies::prelude::*,
+    DeviceWithEntry, SlotMap, SlotRegion,
 };
 use embassy_embedded_hal::shared_bus::asynch::spi::SpiDevice;
 use embassy_executor::Spawner;
@@ -26,67 +31,40 @@ use crate::{
 
 use {defmt_rtt as _, panic_halt as _};
 
-struct AsyncFlashAdapter<T>(T);
-
-impl<T: embedded_storage::nor_flash::ErrorType> embedded_storage_async::nor_flash::ErrorType
-    for AsyncFlashAdapter<T>
-{
-    type Error = T::Error;
-}
-
-impl<T: embedded_storage::nor_flash::ReadNorFlash> embedded_storage_async::nor_flash::ReadNorFlash
-    for AsyncFlashAdapter<T>
-{
-    const READ_SIZE: usize = T::READ_SIZE;
-
-    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
-        self.0.read(offset, bytes)
-    }
-
-    fn capacity(&self) -> usize {
-        self.0.capacity()
-    }
-}
-
-impl<T: embedded_storage::nor_flash::NorFlash> embedded_storage_async::nor_flash::NorFlash
-    for AsyncFlashAdapter<T>
-{
-    const WRITE_SIZE: usize = T::WRITE_SIZE;
-    const ERASE_SIZE: usize = T::ERASE_SIZE;
-
-    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
-        self.0.erase(from, to)
-    }
-
-    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
-        self.0.write(offset, bytes)
-    }
-}
+type ExternalPartition<'a> = Partition<
+    'a,
+    W25<
+        w25::Q,
+        SpiDevice<'static, NoopRawMutex, Spi<'static, Async>, Output<'static>>,
+        Nothing,
+        Nothing,
+    >,
+    RW,
+    NoopRawMutex,
+>;
 
 struct ThisDevice<'a> {
     slot_primary: Partition<
         'a,
-        AsyncFlashAdapter<embassy_stm32::flash::Flash<'static, Blocking>>,
-        RW,
-        NoopRawMutex,
-    >,
-    slot_scratch: Partition<
-        'a,
-        W25<
-            w25::Q,
-            SpiDevice<'static, NoopRawMutex, Spi<'static, Async>, Output<'static>>,
-            Nothing,
-            Nothing,
-        >,
+        BlockingToAsync<embassy_stm32::flash::Flash<'static, Blocking>>,
         RW,
         NoopRawMutex,
     >,
+    slot_secondary: ExternalPartition<'a>,
+    slot_scratch: ExternalPartition<'a>,
 }
 
 const SLOT_PRIMARY: Slot = Slot(0);
 const SLOT_SECONDARY: Slot = Slot(1);
 const SLOT_SCRATCH: Slot = Slot(2);
 
+/// Internal flash is mapped at 0x0800_0000, with the primary slot at offset 0x8000.
+const SLOT_MAP: SlotMap<1> = SlotMap::new([SlotRegion {
+    slot: SLOT_PRIMARY,
+    base: 0x0800_8000,
+    len: core::num::NonZeroU32::new(0x18000).unwrap(),
+}]);
+
 impl ThisDevice<'_> {
     async fn erase_page(&mut self, loc: MemoryLocation) {
         match loc.slot {}
@@ -100,20 +78,40 @@ impl Device for ThisDevice<'_> {
 
     fn boot(self, slot: Slot) -> ! {
         defmt::info!("Boot into {}", slot);
-        loop {
-            cortex_m::asm::wfe();
-        }
+        // Safety: the strategy has completed, hence the slot holds the requested image.
+        unsafe { boot_entry::<SimpleCortexM>(&self, slot) }
     }
 
     fn page_count(&self) -> core::num::NonZeroU16 {
         use embedded_storage_async::nor_flash::{NorFlash, ReadNorFlash};
         core::num::NonZeroU16::new(
             (self.slot_primary.capacity()
-                / AsyncFlashAdapter::<embassy_stm32::flash::Flash<'static, Blocking>>::ERASE_SIZE)
+                / BlockingToAsync::<embassy_stm32::flash::Flash<'static, Blocking>>::ERASE_SIZE)
                 as u16,
         )
         .unwrap()
     }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        use embedded_storage_async::nor_flash::{NorFlash, ReadNorFlash};
+        let capacity = match slot {
+            SLOT_PRIMARY => self.slot_primary.capacity(),
+            SLOT_SECONDARY => self.slot_secondary.capacity(),
+            SLOT_SCRATCH => self.slot_scratch.capacity(),
+            _ => 0,
+        };
+        (capacity / BlockingToAsync::<embassy_stm32::flash::Flash<'static, Blocking>>::ERASE_SIZE)
+            as u16
+    }
+}
+
+impl DeviceWithEntry for ThisDevice<'_> {
+    fn entry_address(&self, slot: Slot) -> *const u32 {
+        match SLOT_MAP.address_of(slot) {
+            Some(address) => address as *const u32,
+            None => unreachable!("only the primary slot is executable"),
+        }
+    }
 }
 
 impl DeviceWithPrimarySlot for ThisDevice<'_> {
@@ -127,7 +125,7 @@ impl DeviceWithScratch for ThisDevice<'_> {
         use embedded_storage_async::nor_flash::{NorFlash, ReadNorFlash};
         core::num::NonZeroU16::new(
             (self.slot_scratch.capacity()
-                / AsyncFlashAdapter::<embassy_stm32::flash::Flash<'static, Blocking>>::ERASE_SIZE)
+                / BlockingToAsync::<embassy_stm32::flash::Flash<'static, Blocking>>::ERASE_SIZE)
                 as u16,
         )
         .unwrap()
@@ -151,7 +149,7 @@ async fn main(spawner: Spawner) -> ! {
 
     defmt::info!("device: {}", ext_flash.device_id().await.unwrap());
 
-    let mut int_flash: PartitionManager<_> = PartitionManager::new(AsyncFlashAdapter(int_flash));
+    let mut int_flash: PartitionManager<_> = PartitionManager::new(BlockingToAsync(int_flash));
     let mut ext_flash: PartitionManager<_> = PartitionManager::new(ext_flash);
 
     let InternalStorageMap {
@@ -164,21 +162,22 @@ async fn main(spawner: Spawner) -> ! {
         mut slot_secundary,
     } = ext_flash.map(ExternalStorageConfig::new());
 
-    let mut state_storage = SimpleStateStorage::new(bl_state);
+    let state_storage = SimpleStateStorage::new(bl_state);
 
-    let state: State<swap_scootch::Request> = state_storage.fetch().await.unwrap();
     let device = ThisDevice {
         slot_primary,
+        slot_secondary: slot_secundary,
         slot_scratch: bl_swap,
     };
 
-    if let Some(request) = state.request {
-        let strategy = SwapScootch::new(&device, request.strategy);
+    device.check_capacity(SLOT_PRIMARY).unwrap();
+    device.check_capacity(SLOT_SECONDARY).unwrap();
+    device.check_scratch_capacity().unwrap();
 
-        todo!()
-    } else {
-        defmt::info!("No request active, boot to primary!");
-        let primary = device.get_primary();
-        device.boot(primary)
+    // Installs a requested image, or reverts it if it did not confirm itself, before booting the primary slot.
+    let flow = BootFlow::new(StrategyRunner::new(device, state_storage));
+    match flow.finalize::<SwapScootch>().await {
+        Ok(never) => match never {},
+        Err(_) => panic!("failed to boot"),
     }
 }
@@ -0,0 +1,124 @@
+//! Adapters to bridge memory drivers into the traits expected by the bootloader.
+
+/// Wraps a blocking `embedded_storage` NOR flash, such that it can be used as an `embedded_storage_async` NOR flash.
+///
+/// All operations complete immediately, as the underlying flash blocks until done.
+pub struct BlockingToAsync<T>(pub T);
+
+impl<T: embedded_storage::nor_flash::ErrorType> embedded_storage_async::nor_flash::ErrorType
+    for BlockingToAsync<T>
+{
+    type Error = T::Error;
+}
+
+impl<T: embedded_storage::nor_flash::ReadNorFlash> embedded_storage_async::nor_flash::ReadNorFlash
+    for BlockingToAsync<T>
+{
+    const READ_SIZE: usize = T::READ_SIZE;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.read(offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+}
+
+impl<T: embedded_storage::nor_flash::NorFlash> embedded_storage_async::nor_flash::NorFlash
+    for BlockingToAsync<T>
+{
+    const WRITE_SIZE: usize = T::WRITE_SIZE;
+    const ERASE_SIZE: usize = T::ERASE_SIZE;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.0.erase(from, to)
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.0.write(offset, bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash};
+
+    use super::*;
+
+    /// RAM backed blocking NOR flash.
+    struct BlockingFlash([u8; 64]);
+
+    impl ErrorType for BlockingFlash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for BlockingFlash {
+        const READ_SIZE: usize = 2;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            bytes.copy_from_slice(&self.0[offset..offset + bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    impl NorFlash for BlockingFlash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = 16;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            self.0[from as usize..to as usize].fill(0xFF);
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            if !offset.is_multiple_of(Self::WRITE_SIZE as u32) {
+                return Err(NorFlashErrorKind::NotAligned);
+            }
+
+            let offset = offset as usize;
+            self.0[offset..offset + bytes.len()].copy_from_slice(bytes);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn forwards() {
+        use embedded_storage_async::nor_flash::{NorFlash, ReadNorFlash};
+
+        assert_eq!(
+            <BlockingToAsync<BlockingFlash> as ReadNorFlash>::READ_SIZE,
+            2
+        );
+        assert_eq!(<BlockingToAsync<BlockingFlash> as NorFlash>::WRITE_SIZE, 4);
+        assert_eq!(<BlockingToAsync<BlockingFlash> as NorFlash>::ERASE_SIZE, 16);
+
+        let mut flash = BlockingToAsync(BlockingFlash([0x00; 64]));
+        assert_eq!(ReadNorFlash::capacity(&flash), 64);
+
+        embassy_futures::block_on(async {
+            NorFlash::erase(&mut flash, 16, 32).await.unwrap();
+            NorFlash::write(&mut flash, 20, &[1, 2, 3, 4])
+                .await
+                .unwrap();
+            assert_eq!(
+                NorFlash::write(&mut flash, 22, &[1, 2, 3, 4]).await,
+                Err(NorFlashErrorKind::NotAligned)
+            );
+
+            let mut bytes = [0u8; 8];
+            ReadNorFlash::read(&mut flash, 16, &mut bytes)
+                .await
+                .unwrap();
+            assert_eq!(bytes, [0xFF, 0xFF, 0xFF, 0xFF, 1, 2, 3, 4]);
+        });
+
+        assert_eq!(flash.0.0[..16], [0x00; 16]);
+        assert_eq!(flash.0.0[32..], [0x00; 32]);
+    }
+}
@@ -1,10 +1,95 @@
+use cortex_m::peripheral::{NVIC, SCB, SYST};
+
 use crate::boot::Boot;
 
 /// Simple bootload mechanism for Cortex-M without support for TrustZone.
+///
+/// Before jumping, [`prepare`](Boot::prepare) returns the interrupt state of the core to how it is after reset:
+/// * the SysTick timer is stopped, and its pending exception is cleared;
+/// * all external interrupts are disabled in the NVIC, and their pending bits are cleared.
+///
+/// Interrupts are masked whilst doing so, and unmasked once no interrupt can fire anymore, as `PRIMASK` is clear after reset.
+///
+/// Not reset are all other peripherals, such as clocks, GPIO or DMA, and core state like the MPU, the FPU or `VTOR`.
+/// These are left as configured by the bootloader, hence either leave them untouched or reset them before booting.
 pub struct SimpleCortexM;
 
+/// Number of NVIC registers covering the interrupt lines, for up to 496 external interrupts.
+const NVIC_WORDS: usize = 16;
+
+/// `ICSR.PENDSTCLR`, clearing a pending SysTick exception.
+const ICSR_PENDSTCLR: u32 = 1 << 25;
+
+/// Register write to hand the core over to the next image.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Write {
+    /// Stop the SysTick counter and its exception.
+    StopSysTick,
+    /// Clear a SysTick exception that became pending before it was stopped.
+    ClearSysTick,
+    /// Disable all interrupt lines of the NVIC word.
+    DisableInterrupts(usize),
+    /// Clear the pending bits of all interrupt lines of the NVIC word.
+    ClearInterrupts(usize),
+}
+
+/// Writes to perform in order, such that nothing is left to raise an interrupt once they are done.
+///
+/// Sources are silenced before their pending bits are cleared, as they could become pending again otherwise.
+fn handover_writes() -> impl Iterator<Item = Write> {
+    [Write::StopSysTick, Write::ClearSysTick]
+        .into_iter()
+        .chain((0..NVIC_WORDS).map(Write::DisableInterrupts))
+        .chain((0..NVIC_WORDS).map(Write::ClearInterrupts))
+}
+
 impl Boot for SimpleCortexM {
+    unsafe fn prepare() {
+        cortex_m::interrupt::disable();
+
+        for write in handover_writes() {
+            // Safety: the bootloader is about to be left, hence no longer relies on these peripherals.
+            unsafe {
+                match write {
+                    Write::StopSysTick => (*SYST::PTR).csr.write(0),
+                    Write::ClearSysTick => (*SCB::PTR).icsr.write(ICSR_PENDSTCLR),
+                    Write::DisableInterrupts(word) => (*NVIC::PTR).icer[word].write(u32::MAX),
+                    Write::ClearInterrupts(word) => (*NVIC::PTR).icpr[word].write(u32::MAX),
+                }
+            }
+        }
+
+        // Make sure the writes took effect before interrupts are unmasked.
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+
+        // Safety: all interrupt sources have been silenced.
+        unsafe { cortex_m::interrupt::enable() };
+    }
+
     unsafe fn boot(addr: *const u32) -> ! {
         unsafe { cortex_m::asm::bootload(addr) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn handover_writes_silence_before_clearing() {
+        let writes: Vec<Write> = handover_writes().collect();
+
+        let position = |write| writes.iter().position(|w| *w == write).unwrap();
+        assert!(position(Write::StopSysTick) < position(Write::ClearSysTick));
+
+        for word in 0..NVIC_WORDS {
+            assert!(
+                position(Write::DisableInterrupts(word)) < position(Write::ClearInterrupts(word))
+            );
+        }
+        assert_eq!(writes.len(), 2 + 2 * NVIC_WORDS);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::{DeviceWithEntry, Slot};
+
 #[cfg(feature = "cortex_m")]
 pub mod cortex_m;
 
@@ -5,6 +7,14 @@ pub mod cortex_m;
 ///
 /// Optionally could drop TrustZone privileges or mask memory access.
 pub trait Boot {
+    /// Hand over the core to the next image, for example by silencing interrupts the bootloader has set up.
+    ///
+    /// Called by [`boot_entry`] right before [`boot`](Self::boot). Defaults to doing nothing.
+    ///
+    /// # Safety
+    /// Only call right before booting, as the bootloader can no longer rely on the state that is reset.
+    unsafe fn prepare() {}
+
     /// Address to the start of the image slot body, typically pointing to the vector table.
     ///
     /// # Safety
@@ -12,3 +22,14 @@ pub trait Boot {
     /// If not the behaviour is undefined.
     unsafe fn boot(addr: *const u32) -> !;
 }
+
+/// Boot the image in `slot` of `device` using the bootload mechanism `B`.
+///
+/// # Safety
+/// Ensure that the slot contains a valid image, as its entry address is jumped to without any further checks.
+pub unsafe fn boot_entry<B: Boot>(device: &impl DeviceWithEntry, slot: Slot) -> ! {
+    unsafe {
+        B::prepare();
+        B::boot(device.entry_address(slot))
+    }
+}
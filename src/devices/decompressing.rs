@@ -0,0 +1,264 @@
+//! Device decorator decompressing a compressed image on the fly, for over-the-air updates over limited bandwidth.
+
+use core::num::{NonZeroU16, NonZeroU32};
+
+use crate::{
+    CopyOperation, Device, DeviceWithPhysicalPages, DeviceWithPrimarySlot, DeviceWithRead,
+    DeviceWithScratch, DeviceWithWrite, Error, MemoryLocation, Page, Slot,
+};
+
+/// Streaming decompression algorithm, for example heatshrink or deflate.
+pub trait Decompressor {
+    /// Start decompressing a new stream, discarding any state of the previous stream.
+    fn reset(&mut self);
+
+    /// Decompress the stream from `input` into `output`, returning the number of bytes consumed and produced.
+    ///
+    /// `input` continues where the previously consumed bytes left off, and is empty once the compressed slot is exhausted.
+    /// Neither consuming nor producing any bytes denotes the end of the stream.
+    fn decompress(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), Error>;
+}
+
+/// Device that decompresses every copy from the compressed slot, and passes all other copies on to the inner device.
+///
+/// Page `n` of the compressed slot denotes page `n` of the decompressed image, as planned by
+/// [`DecompressCopy`](crate::strategies::decompress_copy::DecompressCopy). Copying the pages in order continues the stream,
+/// whereas copying any other page, for example after power loss, decompresses the stream from the start up to that page.
+/// Pages past the end of the stream are filled with `0xFF`.
+///
+/// The compressed stream is read in chunks of `BUF` bytes, re-reading whatever the decompressor did not consume.
+/// `BUF` must be a multiple of the write size of the inner device.
+pub struct DecompressingDevice<D, Z, const BUF: usize = 64> {
+    inner: D,
+    decompressor: Z,
+    slot_compressed: Slot,
+    /// Number of bytes of the compressed slot consumed by the decompressor.
+    consumed: u32,
+    /// Page of the decompressed image the stream continues with, if the stream is intact.
+    position: Option<u16>,
+}
+
+impl<D, Z, const BUF: usize> DecompressingDevice<D, Z, BUF> {
+    pub fn new(inner: D, decompressor: Z, slot_compressed: Slot) -> Self {
+        Self {
+            inner,
+            decompressor,
+            slot_compressed,
+            consumed: 0,
+            position: None,
+        }
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<
+    D: DeviceWithRead + DeviceWithWrite + DeviceWithPhysicalPages,
+    Z: Decompressor,
+    const BUF: usize,
+> DecompressingDevice<D, Z, BUF>
+{
+    /// Fill `output` with the next bytes of the stream.
+    async fn fill(&mut self, output: &mut [u8]) -> Result<(), Error> {
+        let capacity =
+            self.inner.capacity_pages(self.slot_compressed) as u32 * self.inner.page_size().get();
+        let start = MemoryLocation {
+            slot: self.slot_compressed,
+            page: Page(0),
+        };
+        let mut input = [0u8; BUF];
+        let mut produced = 0;
+
+        while produced < output.len() {
+            let len = usize::min(BUF, capacity.saturating_sub(self.consumed) as usize);
+            self.inner
+                .read(start, self.consumed, &mut input[..len])
+                .await?;
+
+            let (consumed, written) = self
+                .decompressor
+                .decompress(&input[..len], &mut output[produced..])?;
+            if consumed > len || written > output.len() - produced {
+                return Err(Error::Misconfigured);
+            }
+            if consumed == 0 && written == 0 {
+                break;
+            }
+
+            self.consumed += consumed as u32;
+            produced += written;
+        }
+
+        output[produced..].fill(0xFF);
+        Ok(())
+    }
+
+    /// Restart the stream, and skip ahead to the start of `page` of the decompressed image.
+    async fn rewind(&mut self, page: u16) -> Result<(), Error> {
+        self.decompressor.reset();
+        self.consumed = 0;
+
+        let skip = page as u32 * self.inner.page_size().get();
+        let mut discard = [0u8; BUF];
+        for chunk in (0..skip).step_by(BUF) {
+            let len = usize::min(BUF, (skip - chunk) as usize);
+            self.fill(&mut discard[..len]).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn decompress(&mut self, operation: CopyOperation) -> Result<(), Error> {
+        let page = operation.from.page.0;
+        if self.position != Some(page) {
+            self.rewind(page).await?;
+        }
+
+        self.inner.erase(operation.to).await?;
+
+        let page_size = self.inner.page_size().get();
+        let mut buffer = [0u8; BUF];
+        for chunk in (0..page_size).step_by(BUF) {
+            let len = usize::min(BUF, (page_size - chunk) as usize);
+            self.fill(&mut buffer[..len]).await?;
+            self.inner
+                .write(operation.to, chunk, &buffer[..len])
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<
+    D: DeviceWithRead + DeviceWithWrite + DeviceWithPhysicalPages,
+    Z: Decompressor,
+    const BUF: usize,
+> Device for DecompressingDevice<D, Z, BUF>
+{
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), Error> {
+        if operation.from.slot != self.slot_compressed {
+            return self.inner.copy(operation).await;
+        }
+
+        if operation.from.page.0 >= self.inner.page_count().get()
+            || operation.to.page.0 >= self.inner.capacity_pages(operation.to.slot)
+        {
+            return Err(Error::OutOfRange);
+        }
+
+        // A failing copy leaves the stream at an unknown position, hence the next copy starts over.
+        self.position = None;
+        self.decompress(operation).await?;
+        self.position = Some(operation.from.page.0 + 1);
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush().await
+    }
+
+    async fn unlock(&mut self) -> Result<(), Error> {
+        self.inner.unlock().await
+    }
+
+    async fn lock(&mut self) -> Result<(), Error> {
+        self.inner.lock().await
+    }
+
+    async fn shutdown(&mut self) -> Result<(), Error> {
+        self.inner.shutdown().await
+    }
+
+    fn boot(self, slot: Slot) -> ! {
+        self.inner.boot(slot)
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        self.inner.page_count()
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        self.inner.capacity_pages(slot)
+    }
+
+    fn slot_count(&self) -> u8 {
+        self.inner.slot_count()
+    }
+
+    fn is_executable(&self, slot: Slot) -> bool {
+        self.inner.is_executable(slot)
+    }
+}
+
+impl<
+    D: DeviceWithRead + DeviceWithWrite + DeviceWithPhysicalPages,
+    Z: Decompressor,
+    const BUF: usize,
+> DeviceWithRead for DecompressingDevice<D, Z, BUF>
+{
+    async fn read(
+        &mut self,
+        location: MemoryLocation,
+        offset: u32,
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        self.inner.read(location, offset, buffer).await
+    }
+}
+
+impl<
+    D: DeviceWithRead + DeviceWithWrite + DeviceWithPhysicalPages,
+    Z: Decompressor,
+    const BUF: usize,
+> DeviceWithPhysicalPages for DecompressingDevice<D, Z, BUF>
+{
+    fn page_size(&self) -> NonZeroU32 {
+        self.inner.page_size()
+    }
+
+    fn physical_page_size(&self, slot: Slot) -> NonZeroU32 {
+        self.inner.physical_page_size(slot)
+    }
+}
+
+impl<
+    D: DeviceWithRead + DeviceWithWrite + DeviceWithPhysicalPages + DeviceWithPrimarySlot,
+    Z: Decompressor,
+    const BUF: usize,
+> DeviceWithPrimarySlot for DecompressingDevice<D, Z, BUF>
+{
+    fn get_primary(&self) -> Slot {
+        self.inner.get_primary()
+    }
+}
+
+impl<
+    D: DeviceWithRead + DeviceWithWrite + DeviceWithPhysicalPages + DeviceWithScratch,
+    Z: Decompressor,
+    const BUF: usize,
+> DeviceWithScratch for DecompressingDevice<D, Z, BUF>
+{
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        self.inner.scratch_page_count()
+    }
+
+    fn get_scratch(&self) -> Slot {
+        self.inner.get_scratch()
+    }
+
+    fn scratch_base(&self) -> Page {
+        self.inner.scratch_base()
+    }
+
+    fn scratch_is_durable(&self) -> bool {
+        self.inner.scratch_is_durable()
+    }
+}
@@ -0,0 +1,6 @@
+//! Ready-made devices on top of common memory abstractions.
+
+pub mod decompressing;
+pub mod nor_flash;
+pub mod skip_identical;
+pub mod verifying;
@@ -0,0 +1,605 @@
+//! Device on top of a set of `embedded_storage_async` NOR flash partitions, one for each slot.
+
+use core::num::{NonZeroU16, NonZeroU32};
+
+use embedded_storage_async::nor_flash::NorFlash;
+
+use crate::{
+    CopyOperation, Device, DeviceWithErase, DeviceWithExplicitErase, DeviceWithPhysicalPages,
+    DeviceWithPrimarySlot, DeviceWithRead, DeviceWithScratch, DeviceWithWrite, Error,
+    MemoryLocation, Slot,
+};
+
+/// Device of which each slot is a NOR flash partition, with `Slot(n)` being `partitions[n]`.
+///
+/// A copy erases the destination page, after which it is written in chunks staged in a stack buffer of `BUF` bytes.
+/// Erasing before reading is safe, as pages consist of whole erase blocks: two distinct pages never share an erase block,
+/// not even neighbouring pages of the same slot, and copying a page onto itself is skipped altogether.
+/// A larger buffer takes fewer flash transactions per page at the cost of stack usage.
+///
+/// `BUF` must be a multiple of both the `READ_SIZE` and the `WRITE_SIZE` of the partitions. It need not divide the page size,
+/// as the page size is a multiple of the `ERASE_SIZE` and thus the final partial chunk is still aligned.
+///
+/// Every access is checked against the length of its slot, and fails with [`Error::OutOfRange`] before touching the flash
+/// when it would reach past the end of the slot, which could otherwise write into the next partition.
+///
+/// [Writes](DeviceWithWrite::write) must start at a multiple of the `WRITE_SIZE`, but may be of any length:
+/// the final partial write unit is padded with `0xFF`, which leaves the erased flash beyond the data untouched.
+pub struct NorFlashDevice<P, const SLOTS: usize, const BUF: usize = 256> {
+    partitions: [P; SLOTS],
+    page_size: u32,
+    page_count: NonZeroU16,
+    slot_primary: Slot,
+    slot_scratch: Slot,
+    scratch_page_count: NonZeroU16,
+    boot: fn(Slot) -> !,
+}
+
+impl<P: NorFlash, const SLOTS: usize, const BUF: usize> NorFlashDevice<P, SLOTS, BUF> {
+    /// Create a device with pages of `page_size` bytes, which must be a multiple of the erase size of the partitions.
+    ///
+    /// The image size is derived from the primary partition, and booting is delegated to `boot`.
+    pub fn new(
+        partitions: [P; SLOTS],
+        page_size: u32,
+        slot_primary: Slot,
+        slot_scratch: Slot,
+        boot: fn(Slot) -> !,
+    ) -> Result<Self, Error> {
+        if page_size == 0
+            || !(page_size as usize).is_multiple_of(P::ERASE_SIZE)
+            || BUF == 0
+            || !BUF.is_multiple_of(P::WRITE_SIZE)
+            || !BUF.is_multiple_of(P::READ_SIZE)
+        {
+            return Err(Error::Misconfigured);
+        }
+
+        let pages = |slot: Slot| {
+            let capacity = partitions
+                .get(slot.0 as usize)
+                .map_or(0, |partition| partition.capacity());
+            NonZeroU16::new((capacity / page_size as usize) as u16).ok_or(Error::Misconfigured)
+        };
+
+        let page_count = pages(slot_primary)?;
+        let scratch_page_count = pages(slot_scratch)?;
+
+        Ok(Self {
+            partitions,
+            page_size,
+            page_count,
+            slot_primary,
+            slot_scratch,
+            scratch_page_count,
+            boot,
+        })
+    }
+
+    pub fn partitions(&self) -> &[P; SLOTS] {
+        &self.partitions
+    }
+
+    fn partition(&mut self, slot: Slot) -> Result<&mut P, Error> {
+        self.partitions
+            .get_mut(slot.0 as usize)
+            .ok_or(Error::Misconfigured)
+    }
+
+    /// Offset into the partition of `len` bytes at `offset` from the page at `location`, if they lie within the slot.
+    fn offset(&self, location: MemoryLocation, offset: u32, len: usize) -> Result<u32, Error> {
+        let capacity_pages = self.capacity_pages(location.slot);
+        if location.page.0 >= capacity_pages {
+            return Err(Error::OutOfRange);
+        }
+
+        let capacity = (capacity_pages as u32)
+            .checked_mul(self.page_size)
+            .ok_or(Error::OutOfRange)?;
+        let start = (location.page.0 as u32)
+            .checked_mul(self.page_size)
+            .and_then(|start| start.checked_add(offset))
+            .ok_or(Error::OutOfRange)?;
+        let end = u32::try_from(len)
+            .ok()
+            .and_then(|len| start.checked_add(len));
+        if end.is_none_or(|end| end > capacity) {
+            return Err(Error::OutOfRange);
+        }
+
+        Ok(start)
+    }
+}
+
+impl<P: NorFlash, const SLOTS: usize, const BUF: usize> Device for NorFlashDevice<P, SLOTS, BUF> {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), Error> {
+        self.offset(operation.from, 0, self.page_size as usize)?;
+        self.offset(operation.to, 0, self.page_size as usize)?;
+
+        if operation.is_in_place() {
+            return Ok(());
+        }
+
+        self.erase(operation.to).await?;
+        self.copy_erased(operation).await
+    }
+
+    fn boot(self, slot: Slot) -> ! {
+        (self.boot)(slot)
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        self.page_count
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        self.partitions.get(slot.0 as usize).map_or(0, |partition| {
+            (partition.capacity() / self.page_size as usize) as u16
+        })
+    }
+
+    fn slot_count(&self) -> u8 {
+        SLOTS as u8
+    }
+}
+
+impl<P: NorFlash, const SLOTS: usize, const BUF: usize> DeviceWithErase
+    for NorFlashDevice<P, SLOTS, BUF>
+{
+    async fn erase(&mut self, location: MemoryLocation) -> Result<(), Error> {
+        let from = self.offset(location, 0, self.page_size as usize)?;
+        let to = from + self.page_size;
+
+        self.partition(location.slot)?
+            .erase(from, to)
+            .await
+            .map_err(|_| Error::Storage)
+    }
+}
+
+impl<P: NorFlash, const SLOTS: usize, const BUF: usize> DeviceWithExplicitErase
+    for NorFlashDevice<P, SLOTS, BUF>
+{
+    async fn copy_erased(&mut self, operation: CopyOperation) -> Result<(), Error> {
+        let from = self.offset(operation.from, 0, self.page_size as usize)?;
+        let to = self.offset(operation.to, 0, self.page_size as usize)?;
+        let mut buffer = [0u8; BUF];
+
+        for chunk in (0..self.page_size).step_by(BUF) {
+            let len = usize::min(BUF, (self.page_size - chunk) as usize);
+            let buffer = &mut buffer[..len];
+
+            self.partition(operation.from.slot)?
+                .read(from + chunk, buffer)
+                .await
+                .map_err(|_| Error::Storage)?;
+            self.partition(operation.to.slot)?
+                .write(to + chunk, buffer)
+                .await
+                .map_err(|_| Error::Storage)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<P: NorFlash, const SLOTS: usize, const BUF: usize> DeviceWithPhysicalPages
+    for NorFlashDevice<P, SLOTS, BUF>
+{
+    fn page_size(&self) -> NonZeroU32 {
+        // Checked to be non-zero on construction.
+        NonZeroU32::new(self.page_size).unwrap()
+    }
+
+    fn physical_page_size(&self, _slot: Slot) -> NonZeroU32 {
+        NonZeroU32::new(P::ERASE_SIZE as u32).unwrap()
+    }
+}
+
+impl<P: NorFlash, const SLOTS: usize, const BUF: usize> DeviceWithRead
+    for NorFlashDevice<P, SLOTS, BUF>
+{
+    async fn read(
+        &mut self,
+        location: MemoryLocation,
+        offset: u32,
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        let offset = self.offset(location, offset, buffer.len())?;
+
+        self.partition(location.slot)?
+            .read(offset, buffer)
+            .await
+            .map_err(|_| Error::Storage)
+    }
+}
+
+impl<P: NorFlash, const SLOTS: usize, const BUF: usize> DeviceWithWrite
+    for NorFlashDevice<P, SLOTS, BUF>
+{
+    async fn write(
+        &mut self,
+        location: MemoryLocation,
+        offset: u32,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let offset = self.offset(location, offset, data.len())?;
+        let (data, tail) = data.split_at(data.len() - data.len() % P::WRITE_SIZE);
+        let partition = self.partition(location.slot)?;
+
+        if !data.is_empty() {
+            partition
+                .write(offset, data)
+                .await
+                .map_err(|_| Error::Storage)?;
+        }
+
+        if !tail.is_empty() {
+            // `BUF` is a non-zero multiple of the write size, and thus fits a whole write unit.
+            let mut buffer = [0xFF; BUF];
+            buffer[..tail.len()].copy_from_slice(tail);
+            partition
+                .write(offset + data.len() as u32, &buffer[..P::WRITE_SIZE])
+                .await
+                .map_err(|_| Error::Storage)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<P: NorFlash, const SLOTS: usize, const BUF: usize> DeviceWithScratch
+    for NorFlashDevice<P, SLOTS, BUF>
+{
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        self.scratch_page_count
+    }
+
+    fn get_scratch(&self) -> Slot {
+        self.slot_scratch
+    }
+}
+
+impl<P: NorFlash, const SLOTS: usize, const BUF: usize> DeviceWithPrimarySlot
+    for NorFlashDevice<P, SLOTS, BUF>
+{
+    fn get_primary(&self) -> Slot {
+        self.slot_primary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Page,
+        mock::{nvm::MockFlash, run_strategy_to_completion},
+        strategies::swap_scootch::{Request, SwapScootch},
+    };
+
+    type Flash = MockFlash<4, 64>;
+
+    const PAGE_SIZE: u32 = 128;
+    const PRIMARY: Slot = Slot(0);
+    const SECONDARY: Slot = Slot(1);
+    const SCRATCH: Slot = Slot(2);
+
+    fn no_boot(_slot: Slot) -> ! {
+        unimplemented!()
+    }
+
+    /// Flash with three pages for both images, and a single scratch page, filled with `fill` for every page.
+    fn device(fill: impl Fn(Slot, u32) -> u8) -> NorFlashDevice<Flash, 3> {
+        let mut partitions = [Flash::new(6), Flash::new(6), Flash::new(2)];
+        for (slot, partition) in partitions.iter_mut().enumerate() {
+            for (offset, byte) in partition.data.iter_mut().enumerate() {
+                *byte = fill(Slot(slot as u8), offset as u32 / PAGE_SIZE);
+            }
+        }
+
+        NorFlashDevice::new(partitions, PAGE_SIZE, PRIMARY, SCRATCH, no_boot).unwrap()
+    }
+
+    fn image(slot: Slot, page: u32) -> u8 {
+        slot.0 << 4 | page as u8
+    }
+
+    fn page(device: &NorFlashDevice<Flash, 3>, slot: Slot, page: u32) -> &[u8] {
+        let offset = (page * PAGE_SIZE) as usize;
+        &device.partitions()[slot.0 as usize].data[offset..offset + PAGE_SIZE as usize]
+    }
+
+    #[test]
+    fn layout() {
+        let device = device(image);
+
+        assert_eq!(device.page_count().get(), 3);
+        assert_eq!(device.scratch_page_count().get(), 1);
+        assert_eq!(device.capacity_pages(SECONDARY), 3);
+        assert_eq!(device.capacity_pages(Slot(3)), 0);
+        assert_eq!(device.erases_per_page(PRIMARY), 2);
+
+        assert!(
+            NorFlashDevice::<_, 1>::new([Flash::new(1)], 96, PRIMARY, PRIMARY, no_boot).is_err(),
+            "page size must be a multiple of the erase size"
+        );
+        assert!(
+            NorFlashDevice::<_, 1>::new([Flash::new(1)], PAGE_SIZE, PRIMARY, PRIMARY, no_boot)
+                .is_err(),
+            "partition must fit at least a page"
+        );
+        assert!(
+            NorFlashDevice::<_, 1, 6>::new([Flash::new(2)], PAGE_SIZE, PRIMARY, PRIMARY, no_boot)
+                .is_err(),
+            "buffer must be a multiple of the write size"
+        );
+    }
+
+    #[test]
+    fn copy_pages() {
+        let mut device = device(image);
+
+        embassy_futures::block_on(async {
+            for page in 0..3 {
+                device
+                    .copy(CopyOperation {
+                        from: MemoryLocation {
+                            slot: SECONDARY,
+                            page: Page(page),
+                        },
+                        to: MemoryLocation {
+                            slot: PRIMARY,
+                            page: Page(2 - page),
+                        },
+                    })
+                    .await
+                    .unwrap();
+            }
+        });
+
+        for page in 0..3 {
+            assert!(
+                self::page(&device, PRIMARY, page)
+                    .iter()
+                    .all(|byte| *byte == image(SECONDARY, 2 - page))
+            );
+        }
+    }
+
+    #[test]
+    fn copy_in_place() {
+        let mut device = device(image);
+        let location = MemoryLocation {
+            slot: PRIMARY,
+            page: Page(1),
+        };
+
+        let result = embassy_futures::block_on(device.copy(CopyOperation {
+            from: location,
+            to: location,
+        }));
+
+        assert_eq!(result, Ok(()));
+        assert!(
+            self::page(&device, PRIMARY, 1)
+                .iter()
+                .all(|byte| *byte == image(PRIMARY, 1))
+        );
+    }
+
+    #[test]
+    fn copy_within_slot() {
+        // Scootch a page down within the primary slot, of which both pages are adjacent in the same partition.
+        let mut device = device(image);
+
+        embassy_futures::block_on(device.copy(CopyOperation {
+            from: MemoryLocation {
+                slot: PRIMARY,
+                page: Page(1),
+            },
+            to: MemoryLocation {
+                slot: PRIMARY,
+                page: Page(0),
+            },
+        }))
+        .unwrap();
+
+        for page in [0, 1] {
+            assert!(
+                self::page(&device, PRIMARY, page)
+                    .iter()
+                    .all(|byte| *byte == image(PRIMARY, 1))
+            );
+        }
+    }
+
+    #[test]
+    fn copy_out_of_range() {
+        let mut device = device(image);
+
+        // The scratch partition holds a single page, of which the second page would run into the next partition.
+        let result = embassy_futures::block_on(device.copy(CopyOperation {
+            from: MemoryLocation {
+                slot: PRIMARY,
+                page: Page(0),
+            },
+            to: MemoryLocation {
+                slot: SCRATCH,
+                page: Page(1),
+            },
+        }));
+
+        assert_eq!(result, Err(Error::OutOfRange));
+        assert!(
+            self::page(&device, SCRATCH, 0)
+                .iter()
+                .all(|byte| *byte == image(SCRATCH, 0))
+        );
+    }
+
+    #[test]
+    fn access_out_of_range() {
+        let mut device = device(image);
+        let past_end = MemoryLocation {
+            slot: PRIMARY,
+            page: Page(3),
+        };
+        let last = MemoryLocation {
+            slot: SCRATCH,
+            page: Page(0),
+        };
+
+        embassy_futures::block_on(async {
+            // Even copying a page onto itself is refused past the end of the slot.
+            assert_eq!(
+                device
+                    .copy(CopyOperation {
+                        from: past_end,
+                        to: past_end,
+                    })
+                    .await,
+                Err(Error::OutOfRange)
+            );
+            assert_eq!(device.erase(past_end).await, Err(Error::OutOfRange));
+            assert_eq!(
+                device.read(past_end, 0, &mut [0u8; 4]).await,
+                Err(Error::OutOfRange)
+            );
+            // Running past the end of the last page of the slot would write into the next partition.
+            assert_eq!(
+                device.write(last, PAGE_SIZE - 4, &[0u8; 8]).await,
+                Err(Error::OutOfRange)
+            );
+            assert_eq!(
+                device.write(last, u32::MAX, &[0u8; 4]).await,
+                Err(Error::OutOfRange)
+            );
+        });
+
+        assert!(
+            self::page(&device, SCRATCH, 0)
+                .iter()
+                .all(|byte| *byte == image(SCRATCH, 0))
+        );
+    }
+
+    #[test]
+    fn padded_write() {
+        let partitions = [MockFlash::<8, 64>::new(2), MockFlash::<8, 64>::new(2)];
+        let mut device =
+            NorFlashDevice::<_, 2, 24>::new(partitions, PAGE_SIZE, PRIMARY, PRIMARY, no_boot)
+                .unwrap();
+        let location = MemoryLocation {
+            slot: SECONDARY,
+            page: Page(0),
+        };
+
+        // Neither the data nor the chunks of a copy are a multiple of the write size of 8 bytes.
+        let data: [u8; 13] = core::array::from_fn(|index| index as u8);
+        embassy_futures::block_on(async {
+            device.erase(location).await.unwrap();
+            device.write(location, 8, &data).await.unwrap();
+            device
+                .copy(CopyOperation {
+                    from: location,
+                    to: MemoryLocation {
+                        slot: PRIMARY,
+                        page: Page(0),
+                    },
+                })
+                .await
+                .unwrap();
+        });
+
+        for partition in device.partitions() {
+            assert_eq!(partition.data[..8], [0xFF; 8]);
+            assert_eq!(partition.data[8..21], data);
+            assert!(partition.data[21..].iter().all(|byte| *byte == 0xFF));
+        }
+    }
+
+    #[test]
+    fn contract() {
+        let mut device = device(image);
+        embassy_futures::block_on(crate::testkit::assert_device_contract(
+            &mut device,
+            SECONDARY,
+            PRIMARY,
+        ));
+    }
+
+    #[test]
+    fn swap() {
+        let mut device = device(image);
+        let strategy = SwapScootch::new(
+            &device,
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        run_strategy_to_completion(&mut device, &strategy);
+
+        for page in 0..3 {
+            assert!(
+                self::page(&device, PRIMARY, page)
+                    .iter()
+                    .all(|byte| *byte == image(SECONDARY, page))
+            );
+            assert!(
+                self::page(&device, SECONDARY, page)
+                    .iter()
+                    .all(|byte| *byte == image(PRIMARY, page))
+            );
+        }
+    }
+
+    #[test]
+    fn storage_error() {
+        let mut device = device(image);
+        device.partitions[1].failing_accesses = 1;
+
+        let result = embassy_futures::block_on(device.copy(CopyOperation {
+            from: MemoryLocation {
+                slot: SECONDARY,
+                page: Page(0),
+            },
+            to: MemoryLocation {
+                slot: PRIMARY,
+                page: Page(0),
+            },
+        }));
+
+        assert_eq!(result, Err(Error::Storage));
+    }
+
+    #[test]
+    fn small_buffer() {
+        // A buffer that neither equals nor divides the page size, to exercise the chunk loop and the final partial chunk.
+        let mut partitions = [Flash::new(2), Flash::new(2)];
+        for (offset, byte) in partitions[1].data.iter_mut().enumerate() {
+            *byte = offset as u8;
+        }
+
+        let mut device =
+            NorFlashDevice::<_, 2, 12>::new(partitions, PAGE_SIZE, PRIMARY, SECONDARY, no_boot)
+                .unwrap();
+
+        embassy_futures::block_on(device.copy(CopyOperation {
+            from: MemoryLocation {
+                slot: SECONDARY,
+                page: Page(0),
+            },
+            to: MemoryLocation {
+                slot: PRIMARY,
+                page: Page(0),
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            device.partitions()[0].data[..PAGE_SIZE as usize],
+            device.partitions()[1].data[..PAGE_SIZE as usize]
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Device decorator skipping copies of pages that already hold the content of their source, for example when flashing a similar build.
+
+use core::num::{NonZeroU16, NonZeroU32};
+
+use crate::{
+    CopyOperation, Device, DeviceWithPhysicalPages, DeviceWithPrimarySlot, DeviceWithRead,
+    DeviceWithScratch, Error, MemoryLocation, Page, Slot,
+};
+
+/// Device that compares the destination page to its source before every copy, and skips the copy if they are identical.
+///
+/// Saves the erase of every page that would not change, at the cost of reading both pages. Skipping is power-safe,
+/// as the destination holds the content the copy would have written regardless of when the copy is executed.
+/// Both pages are read in chunks staged in two stack buffers of `BUF` bytes.
+pub struct SkipIdenticalDevice<D, const BUF: usize = 64> {
+    inner: D,
+}
+
+impl<D, const BUF: usize> SkipIdenticalDevice<D, BUF> {
+    pub fn new(inner: D) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D: DeviceWithRead + DeviceWithPhysicalPages, const BUF: usize> SkipIdenticalDevice<D, BUF> {
+    async fn is_identical(&mut self, operation: CopyOperation) -> Result<bool, Error> {
+        let page_size = self.inner.page_size().get();
+        let mut source = [0u8; BUF];
+        let mut destination = [0u8; BUF];
+
+        for chunk in (0..page_size).step_by(BUF) {
+            let len = usize::min(BUF, (page_size - chunk) as usize);
+
+            self.inner
+                .read(operation.from, chunk, &mut source[..len])
+                .await?;
+            self.inner
+                .read(operation.to, chunk, &mut destination[..len])
+                .await?;
+
+            if source[..len] != destination[..len] {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl<D: DeviceWithRead + DeviceWithPhysicalPages, const BUF: usize> Device
+    for SkipIdenticalDevice<D, BUF>
+{
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), Error> {
+        if self.is_identical(operation).await? {
+            return Ok(());
+        }
+        self.inner.copy(operation).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush().await
+    }
+
+    async fn unlock(&mut self) -> Result<(), Error> {
+        self.inner.unlock().await
+    }
+
+    async fn lock(&mut self) -> Result<(), Error> {
+        self.inner.lock().await
+    }
+
+    async fn shutdown(&mut self) -> Result<(), Error> {
+        self.inner.shutdown().await
+    }
+
+    fn boot(self, slot: Slot) -> ! {
+        self.inner.boot(slot)
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        self.inner.page_count()
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        self.inner.capacity_pages(slot)
+    }
+
+    fn slot_count(&self) -> u8 {
+        self.inner.slot_count()
+    }
+
+    fn is_executable(&self, slot: Slot) -> bool {
+        self.inner.is_executable(slot)
+    }
+}
+
+impl<D: DeviceWithRead + DeviceWithPhysicalPages, const BUF: usize> DeviceWithRead
+    for SkipIdenticalDevice<D, BUF>
+{
+    async fn read(
+        &mut self,
+        location: MemoryLocation,
+        offset: u32,
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        self.inner.read(location, offset, buffer).await
+    }
+}
+
+impl<D: DeviceWithRead + DeviceWithPhysicalPages, const BUF: usize> DeviceWithPhysicalPages
+    for SkipIdenticalDevice<D, BUF>
+{
+    fn page_size(&self) -> NonZeroU32 {
+        self.inner.page_size()
+    }
+
+    fn physical_page_size(&self, slot: Slot) -> NonZeroU32 {
+        self.inner.physical_page_size(slot)
+    }
+}
+
+impl<D: DeviceWithRead + DeviceWithPhysicalPages + DeviceWithScratch, const BUF: usize>
+    DeviceWithScratch for SkipIdenticalDevice<D, BUF>
+{
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        self.inner.scratch_page_count()
+    }
+
+    fn get_scratch(&self) -> Slot {
+        self.inner.get_scratch()
+    }
+
+    fn scratch_base(&self) -> Page {
+        self.inner.scratch_base()
+    }
+
+    fn scratch_is_durable(&self) -> bool {
+        self.inner.scratch_is_durable()
+    }
+}
+
+impl<D: DeviceWithRead + DeviceWithPhysicalPages + DeviceWithPrimarySlot, const BUF: usize>
+    DeviceWithPrimarySlot for SkipIdenticalDevice<D, BUF>
+{
+    fn get_primary(&self) -> Slot {
+        self.inner.get_primary()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock::{
+            generic::{GenericMock, PRIMARY, SECONDARY},
+            run_strategy_to_completion,
+        },
+        strategies::copy::{Copy, Request},
+    };
+
+    #[test]
+    fn identical_page() {
+        let mut inner = GenericMock::new(3, 1);
+        inner.primary[0] = inner.secondary[0];
+        let mut device = SkipIdenticalDevice::<_, 4>::new(inner);
+
+        let strategy = Copy::new(
+            &device,
+            Request {
+                slot_secondary: SECONDARY,
+                slot_backup: None,
+                image_pages: None,
+            },
+        );
+        run_strategy_to_completion(&mut device, &strategy);
+
+        let inner = device.inner();
+        assert_eq!(inner.primary, GenericMock::image_b(3));
+        for (page, wear) in [(0, 0), (1, 1), (2, 1)] {
+            let location = MemoryLocation {
+                slot: PRIMARY,
+                page: Page(page),
+            };
+            assert_eq!(inner.wear.wear_at(location), wear);
+        }
+    }
+}
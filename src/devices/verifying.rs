@@ -0,0 +1,203 @@
+//! Device decorator reading back every copied page, for example during bring-up of a new memory.
+
+use core::num::{NonZeroU16, NonZeroU32};
+
+use crate::{
+    CopyOperation, Device, DeviceWithPhysicalPages, DeviceWithPrimarySlot, DeviceWithRead,
+    DeviceWithScratch, Error, MemoryLocation, Page, Slot,
+};
+
+/// Device that compares the destination page to its source after every copy, failing with [`Error::VerificationFailed`] on mismatch.
+///
+/// Both pages are read in chunks staged in two stack buffers of `BUF` bytes, doubling the reads for every copy.
+pub struct ReadbackVerifyingDevice<D, const BUF: usize = 64> {
+    inner: D,
+}
+
+impl<D, const BUF: usize> ReadbackVerifyingDevice<D, BUF> {
+    pub fn new(inner: D) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D: DeviceWithRead + DeviceWithPhysicalPages, const BUF: usize>
+    ReadbackVerifyingDevice<D, BUF>
+{
+    async fn verify(&mut self, operation: CopyOperation) -> Result<(), Error> {
+        let page_size = self.inner.page_size().get();
+        let mut expected = [0u8; BUF];
+        let mut actual = [0u8; BUF];
+
+        for chunk in (0..page_size).step_by(BUF) {
+            let len = usize::min(BUF, (page_size - chunk) as usize);
+
+            self.inner
+                .read(operation.from, chunk, &mut expected[..len])
+                .await?;
+            self.inner
+                .read(operation.to, chunk, &mut actual[..len])
+                .await?;
+
+            if expected[..len] != actual[..len] {
+                return Err(Error::VerificationFailed);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<D: DeviceWithRead + DeviceWithPhysicalPages, const BUF: usize> Device
+    for ReadbackVerifyingDevice<D, BUF>
+{
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), Error> {
+        self.inner.copy(operation).await?;
+        self.verify(operation).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush().await
+    }
+
+    async fn unlock(&mut self) -> Result<(), Error> {
+        self.inner.unlock().await
+    }
+
+    async fn lock(&mut self) -> Result<(), Error> {
+        self.inner.lock().await
+    }
+
+    async fn shutdown(&mut self) -> Result<(), Error> {
+        self.inner.shutdown().await
+    }
+
+    fn boot(self, slot: Slot) -> ! {
+        self.inner.boot(slot)
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        self.inner.page_count()
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        self.inner.capacity_pages(slot)
+    }
+
+    fn slot_count(&self) -> u8 {
+        self.inner.slot_count()
+    }
+
+    fn is_executable(&self, slot: Slot) -> bool {
+        self.inner.is_executable(slot)
+    }
+}
+
+impl<D: DeviceWithRead + DeviceWithPhysicalPages, const BUF: usize> DeviceWithRead
+    for ReadbackVerifyingDevice<D, BUF>
+{
+    async fn read(
+        &mut self,
+        location: MemoryLocation,
+        offset: u32,
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        self.inner.read(location, offset, buffer).await
+    }
+}
+
+impl<D: DeviceWithRead + DeviceWithPhysicalPages, const BUF: usize> DeviceWithPhysicalPages
+    for ReadbackVerifyingDevice<D, BUF>
+{
+    fn page_size(&self) -> NonZeroU32 {
+        self.inner.page_size()
+    }
+
+    fn physical_page_size(&self, slot: Slot) -> NonZeroU32 {
+        self.inner.physical_page_size(slot)
+    }
+}
+
+impl<D: DeviceWithRead + DeviceWithPhysicalPages + DeviceWithScratch, const BUF: usize>
+    DeviceWithScratch for ReadbackVerifyingDevice<D, BUF>
+{
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        self.inner.scratch_page_count()
+    }
+
+    fn get_scratch(&self) -> Slot {
+        self.inner.get_scratch()
+    }
+
+    fn scratch_base(&self) -> Page {
+        self.inner.scratch_base()
+    }
+
+    fn scratch_is_durable(&self) -> bool {
+        self.inner.scratch_is_durable()
+    }
+}
+
+impl<D: DeviceWithRead + DeviceWithPhysicalPages + DeviceWithPrimarySlot, const BUF: usize>
+    DeviceWithPrimarySlot for ReadbackVerifyingDevice<D, BUF>
+{
+    fn get_primary(&self) -> Slot {
+        self.inner.get_primary()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{devices::nor_flash::NorFlashDevice, mock::nvm::MockFlash};
+
+    type Flash = MockFlash<4, 64>;
+
+    const PRIMARY: Slot = Slot(0);
+    const SECONDARY: Slot = Slot(1);
+
+    fn no_boot(_slot: Slot) -> ! {
+        unimplemented!()
+    }
+
+    fn copy(page: u16) -> CopyOperation {
+        CopyOperation {
+            from: MemoryLocation {
+                slot: SECONDARY,
+                page: Page(page),
+            },
+            to: MemoryLocation {
+                slot: PRIMARY,
+                page: Page(page),
+            },
+        }
+    }
+
+    #[test]
+    fn corrupt_write() {
+        let mut partitions = [Flash::new(2), Flash::new(2)];
+        partitions[1].data.fill(0x42);
+        // The write of the first page is corrupted, which only a read back reveals.
+        partitions[0].corrupt_writes = 1;
+
+        let inner =
+            NorFlashDevice::<_, 2>::new(partitions, 64, PRIMARY, SECONDARY, no_boot).unwrap();
+        let mut device = ReadbackVerifyingDevice::<_, 16>::new(inner);
+
+        embassy_futures::block_on(async {
+            assert_eq!(device.copy(copy(0)).await, Err(Error::VerificationFailed));
+            assert_eq!(device.copy(copy(1)).await, Ok(()));
+        });
+
+        let data = &device.inner().partitions()[0].data;
+        assert_eq!(data[0], 0xFF);
+        assert!(data[1..].iter().all(|byte| *byte == 0x42));
+    }
+}
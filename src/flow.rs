@@ -0,0 +1,749 @@
+//! Lifecycle of an update, from its request up to the confirmation or reversal of the new image.
+//!
+//! An update passes through the following phases, of which the bootloader drives all but the confirmation:
+//!
+//! ```text
+//! Requested → Swapping → Trialing → Idle (confirmed by the application)
+//!                            ↓
+//!                          Failed → Returning → Returned → Idle
+//! ```
+//!
+//! The new image is tried by booting it once. If it does not [`confirm`](State::confirm) itself before the next boot,
+//! for example because it crashed or a watchdog reset the device, the trial has failed and the previous image is restored.
+
+use core::convert::Infallible;
+
+use crate::{
+    DeviceWithPrimarySlot, Error, Slot,
+    image::ImageCheck,
+    runner::{RunnerError, RunnerObserver, StrategyRunner},
+    state::{State, StateStorage},
+    strategies::{FromRequest, Strategy},
+};
+
+/// Phase of an update, see the [module documentation](self).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Phase {
+    /// No update is in progress, either because none was requested or because the new image was confirmed.
+    Idle,
+    /// An update was requested, but nothing has been copied yet.
+    Requested,
+    /// The new image is being installed.
+    Swapping,
+    /// The new image has been installed, and is booted to be tried.
+    Trialing,
+    /// The new image was not confirmed, and its installation is to be reverted.
+    Failed,
+    /// The previous image is being restored.
+    Returning,
+    /// The previous image has been restored.
+    Returned,
+}
+
+impl Phase {
+    /// Phase of the update in `state`, with `strategy` being the reverted strategy whilst reverting.
+    pub fn of<S>(state: &State<S>, strategy: &impl Strategy) -> Phase {
+        let Some(request) = state.request.as_ref() else {
+            return Phase::Idle;
+        };
+
+        let started = request.step > Default::default();
+        let finished = request.step >= strategy.last_step();
+
+        match (request.revert, started, finished) {
+            (false, _, true) => Phase::Trialing,
+            (false, false, false) => Phase::Requested,
+            (false, true, false) => Phase::Swapping,
+            (true, _, true) => Phase::Returned,
+            (true, false, false) => Phase::Failed,
+            (true, true, false) => Phase::Returning,
+        }
+    }
+}
+
+/// Bootloader driving updates through their [`Phase`]s, installing new images into the primary slot.
+///
+/// Owns the runner, and thereby the persistence of the state. Strategies of which the last step is the first step,
+/// like [`Xip`](crate::strategies::xip::Xip), are not supported, as their request is indistinguishable from a failed trial.
+pub struct BootFlow<D, SS, W = fn(), O = crate::runner::NoopObserver, C = ()> {
+    runner: StrategyRunner<D, SS, W, O>,
+    check: C,
+    /// Slot to boot as resolved by the last [`resume`](Self::resume), or `None` for the primary slot.
+    boot_slot: Option<Slot>,
+}
+
+impl<D: DeviceWithPrimarySlot, SS, W: FnMut(), O: RunnerObserver> BootFlow<D, SS, W, O> {
+    pub fn new(runner: StrategyRunner<D, SS, W, O>) -> Self {
+        Self {
+            runner,
+            check: (),
+            boot_slot: None,
+        }
+    }
+}
+
+impl<D: DeviceWithPrimarySlot, SS, W: FnMut(), O: RunnerObserver, C: ImageCheck<D>>
+    BootFlow<D, SS, W, O, C>
+{
+    /// Check the images of every update using `check`, replacing any previously set checks.
+    pub fn with_check<C2: ImageCheck<D>>(self, check: C2) -> BootFlow<D, SS, W, O, C2> {
+        BootFlow {
+            runner: self.runner,
+            check,
+            boot_slot: self.boot_slot,
+        }
+    }
+
+    pub fn runner(&self) -> &StrategyRunner<D, SS, W, O> {
+        &self.runner
+    }
+
+    pub fn runner_mut(&mut self) -> &mut StrategyRunner<D, SS, W, O> {
+        &mut self.runner
+    }
+
+    /// Fetch the state and drive the update in it, if any, up to the point where the primary slot is to be booted.
+    ///
+    /// Returns the phase the update is in when booting:
+    /// * [`Phase::Idle`] when there is no update, and the primary image is booted as usual.
+    ///   A request that does not fit the device, for example one referring to a slot that does not exist because the state
+    ///   was corrupted, is discarded before anything is copied. So is a request of which the new image is refused by the
+    ///   [`before_install`](ImageCheck::before_install) check, for example because it is older than the minimum version,
+    ///   or that would exceed the [wear budget](StrategyRunner::with_wear_budget);
+    /// * [`Phase::Trialing`] when the new image has been installed, and is to be tried;
+    /// * [`Phase::Returned`] when the new image failed its trial, and the previous image has been restored.
+    ///   An installed image refused by the [`before_trial`](ImageCheck::before_trial) check fails its trial right away;
+    ///   if the strategy can not be reverted, the check's error is returned instead, such that the image is not booted;
+    /// * [`Phase::Failed`] when the new image failed its trial, but the strategy can not be reverted.
+    ///   The state is left as is, and it is up to the caller whether to boot the new image regardless.
+    ///
+    /// [`boot`](Self::boot) then boots the [`boot_slot`](Strategy::boot_slot) of the strategy for the phase returned:
+    /// that of the request whilst trialing or failed, and that of the reverted strategy once returned.
+    /// Both the request and the reverted strategy are [activated](FromRequest::activate) once they have been run.
+    pub async fn resume<T>(&mut self) -> Result<Phase, RunnerError<SS::Error>>
+    where
+        T: FromRequest<D>,
+        T::Request: Clone,
+        SS: StateStorage<T::Request>,
+    {
+        let mut state = self
+            .runner
+            .storage_mut()
+            .fetch()
+            .await
+            .map_err(RunnerError::State)?;
+        self.boot_slot = None;
+
+        let Some(request) = state.request.as_ref() else {
+            return Ok(Phase::Idle);
+        };
+        let revert = request.revert;
+        let strategy: T = self.runner.strategy(request.strategy.clone());
+
+        // Refuse to act on a request that does not fit the device, rather than copying outside of its slots.
+        if strategy.verify_layout(self.runner.device()).is_err() {
+            return self.discard(&mut state).await;
+        }
+
+        // The new image is booted whilst trialing, and regardless once it failed but can not be reverted.
+        let request_slot = strategy.boot_slot();
+        let strategy = if revert {
+            let Some(strategy) = strategy.revert() else {
+                self.boot_slot = request_slot;
+                return Ok(Phase::Failed);
+            };
+            strategy
+        } else {
+            let phase = Phase::of(&state, &strategy);
+            if phase == Phase::Requested
+                && let Some(slot) = strategy.image_slot()
+                && self
+                    .check
+                    .before_install(self.runner.device_mut(), slot, state.min_version)
+                    .await
+                    .is_err()
+            {
+                return self.discard(&mut state).await;
+            }
+
+            match phase {
+                Phase::Requested | Phase::Swapping => {
+                    match self.runner.run(&mut state, &strategy).await {
+                        // Refused before anything was copied.
+                        Err(RunnerError::Device(Error::WearExceeded)) => {
+                            return self.discard(&mut state).await;
+                        }
+                        result => result?,
+                    };
+                    strategy.activate(self.runner.device_mut());
+
+                    let slot = request_slot.unwrap_or_else(|| self.runner.device().get_primary());
+                    match self
+                        .check
+                        .before_trial(self.runner.device_mut(), slot)
+                        .await
+                    {
+                        Ok(()) => {
+                            self.boot_slot = request_slot;
+                            return Ok(Phase::Trialing);
+                        }
+                        // The installed image is refused, thus it fails its trial without being booted.
+                        Err(error) => match self.runner.revert(&mut state, strategy).await? {
+                            Some(strategy) => strategy,
+                            None => return Err(RunnerError::Device(error)),
+                        },
+                    }
+                }
+                // The new image was booted before, but has not been confirmed since.
+                _ => match self.runner.revert(&mut state, strategy).await? {
+                    Some(strategy) => strategy,
+                    None => {
+                        self.boot_slot = request_slot;
+                        return Ok(Phase::Failed);
+                    }
+                },
+            }
+        };
+
+        // The reversal copies from another slot, such as the backup.
+        if strategy.verify_layout(self.runner.device()).is_err() {
+            return self.discard(&mut state).await;
+        }
+        self.runner.run(&mut state, &strategy).await?;
+        strategy.activate(self.runner.device_mut());
+
+        // The previous image was confirmed before the update, and thus needs no trial.
+        state.clear();
+        self.runner
+            .storage_mut()
+            .store(&state)
+            .await
+            .map_err(RunnerError::State)?;
+
+        self.boot_slot = strategy.boot_slot();
+        Ok(Phase::Returned)
+    }
+
+    /// Forget the request in `state`, such that the primary image is booted.
+    async fn discard<S>(&mut self, state: &mut State<S>) -> Result<Phase, RunnerError<SS::Error>>
+    where
+        SS: StateStorage<S>,
+    {
+        state.clear();
+        self.runner
+            .storage_mut()
+            .store(state)
+            .await
+            .map_err(RunnerError::State)?;
+
+        Ok(Phase::Idle)
+    }
+
+    /// Shut the device down and boot the slot resolved by [`resume`](Self::resume), which is the primary slot unless the
+    /// strategy specifies its [`boot_slot`](Strategy::boot_slot).
+    ///
+    /// Only returns if the device failed to shut down, in which case it has not booted.
+    pub async fn boot(self) -> Result<Infallible, Error> {
+        let slot = self
+            .boot_slot
+            .unwrap_or_else(|| self.runner.device().get_primary());
+        self.runner.boot(slot).await
+    }
+
+    /// [`Resume`](Self::resume) the update, if any, and [`boot`](Self::boot) the resulting slot, for every boot of the device.
+    ///
+    /// A newly installed image is recorded as trialing before it is booted, such that the next boot reverts it
+    /// unless it [confirmed](State::confirm) itself. An update that failed but can not be reverted boots the new image regardless.
+    ///
+    /// Only returns if the update or the shutdown failed, in which case the device has not booted.
+    pub async fn finalize<T>(mut self) -> Result<Infallible, RunnerError<SS::Error>>
+    where
+        T: FromRequest<D>,
+        T::Request: Clone,
+        SS: StateStorage<T::Request>,
+    {
+        self.resume::<T>().await?;
+        Ok(self.boot().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Slot,
+        mock::tri_slot::{ALPHA, BETA, IMAGE_A, IMAGE_B, MockDevice},
+        state::mem::MemStateStorage,
+        strategies::copy::{self, Copy},
+    };
+
+    fn flow() -> BootFlow<MockDevice, MemStateStorage<copy::Request>> {
+        let mut storage = MemStateStorage::new();
+        embassy_futures::block_on(storage.store(&State::new_request(copy::Request {
+            slot_secondary: BETA,
+            slot_backup: Some(ALPHA),
+            image_pages: None,
+        })))
+        .unwrap();
+
+        BootFlow::new(StrategyRunner::new(MockDevice::new(), storage))
+    }
+
+    #[test]
+    fn confirmed() {
+        let mut flow = flow();
+
+        embassy_futures::block_on(async {
+            assert_eq!(flow.resume::<Copy>().await, Ok(Phase::Trialing));
+            assert_eq!(flow.runner().device().primary, IMAGE_B);
+
+            // The new image confirms itself.
+            let storage = flow.runner_mut().storage_mut();
+            let mut state = storage.fetch().await.unwrap();
+            state.confirm(1);
+            storage.store(&state).await.unwrap();
+
+            assert_eq!(flow.resume::<Copy>().await, Ok(Phase::Idle));
+            assert_eq!(flow.runner().device().primary, IMAGE_B);
+        });
+    }
+
+    #[test]
+    fn failed_trial() {
+        let mut flow = flow();
+
+        embassy_futures::block_on(async {
+            assert_eq!(flow.resume::<Copy>().await, Ok(Phase::Trialing));
+            assert_eq!(flow.runner().device().primary, IMAGE_B);
+
+            // The new image did not confirm itself before the next boot.
+            assert_eq!(flow.resume::<Copy>().await, Ok(Phase::Returned));
+            assert_eq!(flow.runner().device().primary, IMAGE_A);
+            assert!(flow.runner().storage().step().is_none());
+
+            assert_eq!(flow.resume::<Copy>().await, Ok(Phase::Idle));
+        });
+    }
+
+    /// Device of which booting resets it, unwinding with the device such that it can be booted again.
+    struct ResettingDevice(MockDevice);
+
+    struct Reset(MockDevice, Slot);
+
+    impl crate::Device for ResettingDevice {
+        async fn copy(&mut self, operation: crate::CopyOperation) -> Result<(), Error> {
+            self.0.copy(operation).await
+        }
+
+        fn boot(self, slot: Slot) -> ! {
+            std::panic::panic_any(Reset(self.0, slot))
+        }
+
+        fn page_count(&self) -> core::num::NonZeroU16 {
+            self.0.page_count()
+        }
+
+        fn capacity_pages(&self, slot: Slot) -> u16 {
+            self.0.capacity_pages(slot)
+        }
+    }
+
+    impl DeviceWithPrimarySlot for ResettingDevice {
+        fn get_primary(&self) -> Slot {
+            self.0.get_primary()
+        }
+    }
+
+    /// Boot the device once using [`BootFlow::finalize`], returning the device after the reset and the booted slot.
+    fn cold_boot<T: FromRequest<ResettingDevice, Request = copy::Request>>(
+        device: MockDevice,
+        storage: &mut MemStateStorage<copy::Request>,
+    ) -> Reset {
+        let flow = BootFlow::new(StrategyRunner::new(ResettingDevice(device), storage));
+        let unwound = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            embassy_futures::block_on(flow.finalize::<T>())
+        }));
+
+        *unwound.unwrap_err().downcast::<Reset>().ok().unwrap()
+    }
+
+    #[test]
+    fn finalize_failed_trial() {
+        use crate::mock::tri_slot::PRIMARY;
+
+        let mut storage = MemStateStorage::new();
+        embassy_futures::block_on(storage.store(&State::new_request(copy::Request {
+            slot_secondary: BETA,
+            slot_backup: Some(ALPHA),
+            image_pages: None,
+        })))
+        .unwrap();
+
+        let Reset(device, slot) = cold_boot::<Copy>(MockDevice::new(), &mut storage);
+        assert_eq!(slot, PRIMARY);
+        assert_eq!(device.primary, IMAGE_B);
+        assert!(storage.state().unwrap().is_update_pending());
+
+        // The new image crashed before confirming itself.
+        let Reset(device, slot) = cold_boot::<Copy>(device, &mut storage);
+        assert_eq!(slot, PRIMARY);
+        assert_eq!(device.primary, IMAGE_A);
+        assert!(storage.state().unwrap().request.is_none());
+    }
+
+    /// Copy that boots the new image from the secondary slot, and falls back to the primary slot once reverted.
+    struct FromSecondary {
+        inner: Copy,
+        reverted: bool,
+    }
+
+    impl Strategy for FromSecondary {
+        type Plan<'a> = copy::CopyPlan;
+
+        fn last_step(&self) -> crate::Step {
+            self.inner.last_step()
+        }
+
+        fn plan(&self, step: crate::Step) -> copy::CopyPlan {
+            self.inner.plan(step)
+        }
+
+        fn image_slot(&self) -> Option<Slot> {
+            self.inner.image_slot()
+        }
+
+        fn boot_slot(&self) -> Option<Slot> {
+            (!self.reverted).then_some(BETA)
+        }
+
+        fn revert(self) -> Option<Self> {
+            Some(Self {
+                inner: self.inner.revert()?,
+                reverted: true,
+            })
+        }
+    }
+
+    impl<D: DeviceWithPrimarySlot> FromRequest<D> for FromSecondary {
+        type Request = copy::Request;
+
+        fn from_request(device: &D, request: copy::Request) -> Self {
+            Self {
+                inner: Copy::new(device, request),
+                reverted: false,
+            }
+        }
+    }
+
+    #[test]
+    fn boot_slot() {
+        use crate::mock::tri_slot::PRIMARY;
+
+        let mut storage = MemStateStorage::new();
+        embassy_futures::block_on(storage.store(&State::new_request(copy::Request {
+            slot_secondary: BETA,
+            slot_backup: Some(ALPHA),
+            image_pages: None,
+        })))
+        .unwrap();
+
+        let Reset(device, slot) = cold_boot::<FromSecondary>(MockDevice::new(), &mut storage);
+        assert_eq!(slot, BETA);
+
+        // Once reverted, the slot of the reverted strategy is booted.
+        let Reset(device, slot) = cold_boot::<FromSecondary>(device, &mut storage);
+        assert_eq!(slot, PRIMARY);
+
+        // Without a request, the primary slot is booted.
+        let Reset(_, slot) = cold_boot::<FromSecondary>(device, &mut storage);
+        assert_eq!(slot, PRIMARY);
+    }
+
+    #[test]
+    fn downgrade() {
+        let mut flow = flow();
+
+        embassy_futures::block_on(async {
+            assert_eq!(flow.resume::<Copy>().await, Ok(Phase::Trialing));
+
+            let storage = flow.runner_mut().storage_mut();
+            let mut state = storage.fetch().await.unwrap();
+            state.confirm(2);
+
+            // Return to the previous release in the backup slot on purpose.
+            state.request_downgrade(
+                1,
+                copy::Request {
+                    slot_secondary: ALPHA,
+                    slot_backup: Some(BETA),
+                    image_pages: None,
+                },
+            );
+            assert!(state.is_update_pending());
+            assert!(!state.is_reverting());
+            assert_eq!(state.min_version, 1);
+            storage.store(&state).await.unwrap();
+
+            assert_eq!(flow.resume::<Copy>().await, Ok(Phase::Trialing));
+            assert_eq!(flow.runner().device().primary, IMAGE_A);
+
+            // The downgrade is tried like any update, rather than being a failed one.
+            let state = flow.runner_mut().storage_mut().fetch().await.unwrap();
+            assert!(state.is_update_pending());
+            assert!(!state.is_reverting());
+
+            // Failing its trial reverts the downgrade, and with it the minimum version.
+            assert_eq!(flow.resume::<Copy>().await, Ok(Phase::Returned));
+            assert_eq!(flow.runner().device().primary, IMAGE_B);
+            let state = flow.runner_mut().storage_mut().fetch().await.unwrap();
+            assert_eq!(state.min_version, 2);
+        });
+    }
+
+    #[test]
+    fn invalid_slot() {
+        let mut storage = MemStateStorage::new();
+        embassy_futures::block_on(storage.store(&State::new_request(copy::Request {
+            slot_secondary: Slot(9),
+            slot_backup: Some(ALPHA),
+            image_pages: None,
+        })))
+        .unwrap();
+        let mut flow = BootFlow::new(StrategyRunner::new(MockDevice::new(), storage));
+
+        embassy_futures::block_on(async {
+            assert_eq!(flow.resume::<Copy>().await, Ok(Phase::Idle));
+            assert_eq!(flow.runner().device().primary, IMAGE_A);
+            assert!(flow.runner().storage().step().is_none());
+        });
+    }
+
+    #[test]
+    fn wear_budget() {
+        use crate::runner::WearBudget;
+
+        let mut storage = MemStateStorage::new();
+        embassy_futures::block_on(storage.store(&State::new_request(copy::Request {
+            slot_secondary: BETA,
+            slot_backup: Some(ALPHA),
+            image_pages: None,
+        })))
+        .unwrap();
+
+        // Copying the image erases each of the three pages of the primary slot.
+        let runner = StrategyRunner::new(MockDevice::new(), storage)
+            .with_wear_budget(WearBudget { max_erases: 2 }, |_| 0);
+        let mut flow = BootFlow::new(runner);
+
+        embassy_futures::block_on(async {
+            assert_eq!(flow.resume::<Copy>().await, Ok(Phase::Idle));
+            assert_eq!(flow.runner().device().primary, IMAGE_A);
+            assert!(flow.runner().storage().step().is_none());
+        });
+    }
+
+    #[test]
+    fn refused_image() {
+        use crate::image::DigestCheck;
+
+        embassy_futures::block_on(async {
+            let mut refusing = flow().with_check(DigestCheck { expected: IMAGE_A });
+            assert_eq!(refusing.resume::<Copy>().await, Ok(Phase::Returned));
+            assert_eq!(refusing.runner().device().primary, IMAGE_A);
+            assert!(refusing.runner().storage().step().is_none());
+
+            let mut accepting = flow().with_check(DigestCheck { expected: IMAGE_B });
+            assert_eq!(accepting.resume::<Copy>().await, Ok(Phase::Trialing));
+            assert_eq!(accepting.runner().device().primary, IMAGE_B);
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "secure-boot")]
+    fn signature() {
+        use std::{vec, vec::Vec};
+
+        use crate::{
+            devices::nor_flash::NorFlashDevice,
+            image::{ImageHeader, SIGNATURE_SIZE, SignatureCheck, SignatureVerifier},
+            mock::nvm::MockFlash,
+            strategies::swap_scootch::{self, SwapScootch},
+        };
+
+        const PRIMARY: Slot = Slot(0);
+        const SECONDARY: Slot = Slot(1);
+        const SCRATCH: Slot = Slot(2);
+        const KEY: u8 = 0x5A;
+
+        /// Stub scheme of which the signature repeats the sum of the message plus the key.
+        #[derive(Default)]
+        struct SumVerifier {
+            sum: u8,
+        }
+
+        impl SignatureVerifier for SumVerifier {
+            type PublicKey = u8;
+
+            fn update(&mut self, data: &[u8]) {
+                self.sum = data
+                    .iter()
+                    .fold(self.sum, |sum, byte| sum.wrapping_add(*byte));
+            }
+
+            fn finish(&mut self, key: &u8, signature: &[u8; SIGNATURE_SIZE]) -> bool {
+                let expected = core::mem::take(&mut self.sum).wrapping_add(*key);
+                signature.iter().all(|byte| *byte == expected)
+            }
+        }
+
+        fn no_boot(_slot: Slot) -> ! {
+            unimplemented!()
+        }
+
+        /// Image of two pages filled with `fill`, signed by `key`.
+        fn image(fill: u8, key: u8) -> Vec<u8> {
+            let header = ImageHeader {
+                magic: ImageHeader::MAGIC,
+                version: 1,
+                length: 128,
+                crc: 0,
+            };
+
+            let mut image = vec![fill; 128];
+            image[..ImageHeader::SIZE].copy_from_slice(&header.to_bytes());
+
+            let mut verifier = SumVerifier::default();
+            verifier.update(&image[..ImageHeader::SIZE]);
+            verifier.update(&image[ImageHeader::SIZE + SIGNATURE_SIZE..]);
+            image[ImageHeader::SIZE..][..SIGNATURE_SIZE].fill(verifier.sum.wrapping_add(key));
+            image
+        }
+
+        let flow = |secondary_key: u8| {
+            let mut partitions = [
+                MockFlash::<4, 64>::new(2),
+                MockFlash::new(2),
+                MockFlash::new(1),
+            ];
+            partitions[0].data = image(0xAA, KEY);
+            partitions[1].data = image(0xBB, secondary_key);
+
+            let device =
+                NorFlashDevice::<_, 3>::new(partitions, 64, PRIMARY, SCRATCH, no_boot).unwrap();
+            let mut storage = MemStateStorage::new();
+            embassy_futures::block_on(storage.store(&State::new_request(swap_scootch::Request {
+                slot_secondary: SECONDARY,
+            })))
+            .unwrap();
+
+            BootFlow::new(StrategyRunner::new(device, storage)).with_check(SignatureCheck {
+                key: KEY,
+                verifier: SumVerifier::default(),
+            })
+        };
+
+        embassy_futures::block_on(async {
+            // Signed by another key, as for example a tampered image would be.
+            let mut tampered = flow(KEY ^ 1);
+            assert_eq!(tampered.resume::<SwapScootch>().await, Ok(Phase::Returned));
+            assert_eq!(
+                tampered.runner().device().partitions()[0].data,
+                image(0xAA, KEY)
+            );
+            assert!(tampered.runner().storage().step().is_none());
+
+            let mut signed = flow(KEY);
+            assert_eq!(signed.resume::<SwapScootch>().await, Ok(Phase::Trialing));
+            assert_eq!(
+                signed.runner().device().partitions()[0].data,
+                image(0xBB, KEY)
+            );
+        });
+    }
+
+    #[test]
+    fn rollback_protection() {
+        use crate::{
+            devices::nor_flash::NorFlashDevice,
+            image::{VersionCheck, read_version},
+            mock::nvm::MockFlash,
+            strategies::swap_scootch::{self, SwapScootch},
+        };
+
+        const PRIMARY: Slot = Slot(0);
+        const SECONDARY: Slot = Slot(1);
+        const SCRATCH: Slot = Slot(2);
+        const VERSION_OFFSET: u32 = 0x1C;
+
+        fn no_boot(_slot: Slot) -> ! {
+            unimplemented!()
+        }
+
+        let flow = |version: u32| {
+            let mut partitions = [
+                MockFlash::<4, 64>::new(2),
+                MockFlash::new(2),
+                MockFlash::new(1),
+            ];
+            partitions[1].data[VERSION_OFFSET as usize..][..4]
+                .copy_from_slice(&version.to_le_bytes());
+
+            let device =
+                NorFlashDevice::<_, 3>::new(partitions, 64, PRIMARY, SCRATCH, no_boot).unwrap();
+            let mut state = State::new_request(swap_scootch::Request {
+                slot_secondary: SECONDARY,
+            });
+            state.min_version = 5;
+
+            let mut storage = MemStateStorage::new();
+            embassy_futures::block_on(storage.store(&state)).unwrap();
+            BootFlow::new(StrategyRunner::new(device, storage)).with_check(VersionCheck {
+                offset: VERSION_OFFSET,
+            })
+        };
+
+        embassy_futures::block_on(async {
+            // An older image is refused before anything is swapped.
+            let mut older = flow(3);
+            assert_eq!(older.resume::<SwapScootch>().await, Ok(Phase::Idle));
+            assert!(older.runner().storage().step().is_none());
+            let device = older.runner_mut().device_mut();
+            assert_eq!(
+                read_version(device, PRIMARY, VERSION_OFFSET).await,
+                Ok(0xFFFF_FFFF)
+            );
+
+            // A newer image is swapped in to be tried.
+            let mut newer = flow(7);
+            assert_eq!(newer.resume::<SwapScootch>().await, Ok(Phase::Trialing));
+            let device = newer.runner_mut().device_mut();
+            assert_eq!(read_version(device, PRIMARY, VERSION_OFFSET).await, Ok(7));
+        });
+    }
+
+    #[test]
+    fn phases() {
+        let device = MockDevice::new();
+        let request = copy::Request {
+            slot_secondary: BETA,
+            slot_backup: Some(ALPHA),
+            image_pages: None,
+        };
+        let strategy = Copy::new(&device, request.clone());
+
+        let mut state = State::default();
+        assert_eq!(Phase::of(&state, &strategy), Phase::Idle);
+
+        state.set_request(request);
+        assert_eq!(Phase::of(&state, &strategy), Phase::Requested);
+
+        state.advance_step();
+        assert_eq!(Phase::of(&state, &strategy), Phase::Trialing);
+
+        state.begin_revert();
+        assert_eq!(Phase::of(&state, &strategy), Phase::Failed);
+
+        state.advance_step();
+        assert_eq!(Phase::of(&state, &strategy), Phase::Returned);
+    }
+}
@@ -0,0 +1,393 @@
+//! Inspection of the images residing in slots.
+
+use core::num::NonZeroU32;
+
+use crate::{DeviceWithDigest, DeviceWithRead, Error, MemoryLocation, Page, Slot};
+
+/// Read the version of the image in `slot`, stored as a little endian `u32` at `offset` bytes from the start of the slot.
+///
+/// Where the version resides depends on the image format, and is thus up to the integrator.
+/// For Cortex-M images an unused entry of the vector table, or a header preceding it, is a good candidate.
+pub async fn read_version(
+    device: &mut impl DeviceWithRead,
+    slot: Slot,
+    offset: u32,
+) -> Result<u32, Error> {
+    let mut buffer = [0u8; 4];
+    device
+        .read(
+            MemoryLocation {
+                slot,
+                page: Page(0),
+            },
+            offset,
+            &mut buffer,
+        )
+        .await?;
+
+    Ok(u32::from_le_bytes(buffer))
+}
+
+/// Checks of the images involved in an update, run by the [`BootFlow`](crate::flow::BootFlow).
+///
+/// All methods default to accepting the image, such that an implementation only needs to handle the checks it requires.
+/// Checks are combined by passing them as a tuple.
+#[allow(async_fn_in_trait)]
+pub trait ImageCheck<D> {
+    /// Check the new image in `slot` before anything is installed, given the minimum version of the state.
+    ///
+    /// On failure the request is discarded, leaving the current image in place.
+    async fn before_install(
+        &mut self,
+        _device: &mut D,
+        _slot: Slot,
+        _min_version: u32,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Check the installed image in `slot` before it is booted to be tried.
+    ///
+    /// On failure the update is reverted as if the image failed its trial.
+    async fn before_trial(&mut self, _device: &mut D, _slot: Slot) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<D> ImageCheck<D> for () {}
+
+impl<D, A: ImageCheck<D>, B: ImageCheck<D>> ImageCheck<D> for (A, B) {
+    async fn before_install(
+        &mut self,
+        device: &mut D,
+        slot: Slot,
+        min_version: u32,
+    ) -> Result<(), Error> {
+        self.0.before_install(device, slot, min_version).await?;
+        self.1.before_install(device, slot, min_version).await
+    }
+
+    async fn before_trial(&mut self, device: &mut D, slot: Slot) -> Result<(), Error> {
+        self.0.before_trial(device, slot).await?;
+        self.1.before_trial(device, slot).await
+    }
+}
+
+/// Refuse to install an image older than the minimum version with [`Error::Rollback`].
+///
+/// The version is read as described in [`read_version`], at `offset` bytes from the start of the slot.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VersionCheck {
+    pub offset: u32,
+}
+
+impl<D: DeviceWithRead> ImageCheck<D> for VersionCheck {
+    async fn before_install(
+        &mut self,
+        device: &mut D,
+        slot: Slot,
+        min_version: u32,
+    ) -> Result<(), Error> {
+        if read_version(device, slot, self.offset).await? < min_version {
+            return Err(Error::Rollback);
+        }
+        Ok(())
+    }
+}
+
+/// Refuse to boot an installed image of which the [digest](DeviceWithDigest::digest) differs from `expected`,
+/// with [`Error::InvalidImage`].
+///
+/// The expected digest accompanies the update, for example in a manifest that was authenticated before requesting it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DigestCheck<G> {
+    pub expected: G,
+}
+
+impl<D: DeviceWithDigest> ImageCheck<D> for DigestCheck<D::Digest> {
+    async fn before_trial(&mut self, device: &mut D, slot: Slot) -> Result<(), Error> {
+        if device.digest(slot).await? != self.expected {
+            return Err(Error::InvalidImage);
+        }
+        Ok(())
+    }
+}
+
+/// Incremental hash algorithm, for example SHA-256 in software or by a hardware accelerator.
+pub trait Digester {
+    /// Digest as produced by the algorithm, for example `[u8; 32]` for SHA-256.
+    type Digest;
+
+    /// Feed the next bytes of the data to hash.
+    fn update(&mut self, data: &[u8]);
+
+    /// Digest of all bytes fed since the previous digest, starting over afterwards.
+    fn finalize_reset(&mut self) -> Self::Digest;
+}
+
+/// Digest of the first `len` bytes of `slot`, read in chunks of `BUF` bytes.
+///
+/// Useful to implement [`DeviceWithDigest`](crate::DeviceWithDigest) in software, typically for the `length` of the [`ImageHeader`].
+pub async fn digest<H: Digester, const BUF: usize>(
+    device: &mut impl DeviceWithRead,
+    slot: Slot,
+    len: u32,
+    digester: &mut H,
+) -> Result<H::Digest, Error> {
+    let start = MemoryLocation {
+        slot,
+        page: Page(0),
+    };
+    let mut buffer = [0u8; BUF];
+
+    for chunk in (0..len).step_by(BUF) {
+        let buffer = &mut buffer[..usize::min(BUF, (len - chunk) as usize)];
+        device.read(start, chunk, buffer).await?;
+        digester.update(buffer);
+    }
+
+    Ok(digester.finalize_reset())
+}
+
+/// Header at the start of a slot, describing the image following it.
+///
+/// Stored as four little endian `u32` fields in the order `magic`, `version`, `length` and `crc`,
+/// amounting to [`ImageHeader::SIZE`] bytes. The header is part of the image, and thus moved along with it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ImageHeader {
+    /// Always [`ImageHeader::MAGIC`] for a valid image.
+    pub magic: u32,
+    /// Version of the image, for example as checked for rollback protection.
+    pub version: u32,
+    /// Length in bytes of the image, including this header.
+    pub length: u32,
+    /// Checksum of the image, of which the algorithm is up to the integrator.
+    pub crc: u32,
+}
+
+impl ImageHeader {
+    /// Magic marking a valid header, being `"BLCK"` in ASCII.
+    pub const MAGIC: u32 = u32::from_le_bytes(*b"BLCK");
+
+    /// Size in bytes of the header.
+    pub const SIZE: usize = 16;
+
+    /// Number of pages of `page_size` bytes occupied by the image.
+    pub const fn pages(&self, page_size: NonZeroU32) -> u32 {
+        self.length.div_ceil(page_size.get())
+    }
+
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        let field = |index: usize| {
+            u32::from_le_bytes([
+                bytes[index * 4],
+                bytes[index * 4 + 1],
+                bytes[index * 4 + 2],
+                bytes[index * 4 + 3],
+            ])
+        };
+
+        Self {
+            magic: field(0),
+            version: field(1),
+            length: field(2),
+            crc: field(3),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        for (chunk, field) in
+            bytes
+                .chunks_exact_mut(4)
+                .zip([self.magic, self.version, self.length, self.crc])
+        {
+            chunk.copy_from_slice(&field.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// Read the [`ImageHeader`] at the start of `slot`, failing with [`Error::InvalidImage`] if it lacks the magic.
+pub async fn read_header(
+    device: &mut impl DeviceWithRead,
+    slot: Slot,
+) -> Result<ImageHeader, Error> {
+    let mut buffer = [0u8; ImageHeader::SIZE];
+    device
+        .read(
+            MemoryLocation {
+                slot,
+                page: Page(0),
+            },
+            0,
+            &mut buffer,
+        )
+        .await?;
+
+    let header = ImageHeader::from_bytes(buffer);
+    if header.magic != ImageHeader::MAGIC {
+        return Err(Error::InvalidImage);
+    }
+    Ok(header)
+}
+
+/// Size in bytes of the signature of a signed image, as for example produced by Ed25519.
+#[cfg(feature = "secure-boot")]
+pub const SIGNATURE_SIZE: usize = 64;
+
+/// Signature scheme verifying a message fed incrementally, for example Ed25519 in software or by a hardware accelerator.
+#[cfg(feature = "secure-boot")]
+pub trait SignatureVerifier {
+    /// Public key of the signer.
+    type PublicKey;
+
+    /// Feed the next bytes of the signed message.
+    fn update(&mut self, data: &[u8]);
+
+    /// Whether `signature` by `key` is valid for all bytes fed since the previous verification, starting over afterwards.
+    fn finish(&mut self, key: &Self::PublicKey, signature: &[u8; SIGNATURE_SIZE]) -> bool;
+}
+
+/// Verify the signature of the image in `slot` by `key`, failing with [`Error::InvalidImage`] if it does not hold.
+///
+/// A signed image has its signature of [`SIGNATURE_SIZE`] bytes directly following the [`ImageHeader`], and is signed over
+/// its header and the remainder of the image after the signature, up to its `length`. The image is read in chunks of `BUF` bytes.
+#[cfg(feature = "secure-boot")]
+pub async fn verify_signature<V: SignatureVerifier, const BUF: usize>(
+    device: &mut impl DeviceWithRead,
+    slot: Slot,
+    key: &V::PublicKey,
+    verifier: &mut V,
+) -> Result<(), Error> {
+    let header = read_header(device, slot).await?;
+    let body = (ImageHeader::SIZE + SIGNATURE_SIZE) as u32;
+    if header.length < body {
+        return Err(Error::InvalidImage);
+    }
+
+    let start = MemoryLocation {
+        slot,
+        page: Page(0),
+    };
+    let mut signature = [0u8; SIGNATURE_SIZE];
+    device
+        .read(start, ImageHeader::SIZE as u32, &mut signature)
+        .await?;
+
+    verifier.update(&header.to_bytes());
+    let mut buffer = [0u8; BUF];
+    for chunk in (body..header.length).step_by(BUF) {
+        let buffer = &mut buffer[..usize::min(BUF, (header.length - chunk) as usize)];
+        device.read(start, chunk, buffer).await?;
+        verifier.update(buffer);
+    }
+
+    if !verifier.finish(key, &signature) {
+        return Err(Error::InvalidImage);
+    }
+    Ok(())
+}
+
+/// Refuse to boot an installed image that is not signed by `key`, as verified by [`verify_signature`].
+#[cfg(feature = "secure-boot")]
+pub struct SignatureCheck<V: SignatureVerifier> {
+    pub key: V::PublicKey,
+    pub verifier: V,
+}
+
+#[cfg(feature = "secure-boot")]
+impl<D: DeviceWithRead, V: SignatureVerifier> ImageCheck<D> for SignatureCheck<V> {
+    async fn before_trial(&mut self, device: &mut D, slot: Slot) -> Result<(), Error> {
+        verify_signature::<V, 64>(device, slot, &self.key, &mut self.verifier).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Device, DeviceWithPhysicalPages, devices::nor_flash::NorFlashDevice, mock::nvm::MockFlash,
+    };
+
+    const PRIMARY: Slot = Slot(0);
+    const SECONDARY: Slot = Slot(1);
+
+    fn no_boot(_slot: Slot) -> ! {
+        unimplemented!()
+    }
+
+    #[test]
+    fn header() {
+        let header = ImageHeader {
+            magic: ImageHeader::MAGIC,
+            version: 3,
+            length: 100,
+            crc: 0xDEAD_BEEF,
+        };
+
+        let mut partitions = [MockFlash::<4, 64>::new(2), MockFlash::new(2)];
+        partitions[1].data[..ImageHeader::SIZE].copy_from_slice(&header.to_bytes());
+
+        let mut device =
+            NorFlashDevice::<_, 2>::new(partitions, 64, PRIMARY, SECONDARY, no_boot).unwrap();
+
+        embassy_futures::block_on(async {
+            assert_eq!(read_header(&mut device, SECONDARY).await, Ok(header));
+            assert_eq!(header.pages(device.page_size()), 2);
+            assert_eq!(read_version(&mut device, SECONDARY, 4).await, Ok(3));
+
+            // An erased slot holds no image.
+            assert_eq!(
+                read_header(&mut device, PRIMARY).await,
+                Err(Error::InvalidImage)
+            );
+        });
+    }
+
+    impl Digester for sha2::Sha256 {
+        type Digest = [u8; 32];
+
+        fn update(&mut self, data: &[u8]) {
+            sha2::Digest::update(self, data);
+        }
+
+        fn finalize_reset(&mut self) -> [u8; 32] {
+            sha2::Digest::finalize_reset(self).into()
+        }
+    }
+
+    type FlashDevice = NorFlashDevice<MockFlash<4, 64>, 2>;
+
+    impl crate::DeviceWithDigest for FlashDevice {
+        type Digest = [u8; 32];
+
+        async fn digest(&mut self, slot: Slot) -> Result<[u8; 32], Error> {
+            let len = self.page_count().get() as u32 * self.page_size().get();
+            digest::<_, 48>(self, slot, len, &mut sha2::Sha256::default()).await
+        }
+    }
+
+    #[test]
+    fn sha256() {
+        use sha2::Digest as _;
+
+        let mut partitions = [MockFlash::<4, 64>::new(2), MockFlash::new(2)];
+        for (offset, byte) in partitions[1].data.iter_mut().enumerate() {
+            *byte = offset as u8;
+        }
+        let expected: [u8; 32] = sha2::Sha256::digest(&partitions[1].data).into();
+
+        let mut device: FlashDevice =
+            NorFlashDevice::new(partitions, 64, PRIMARY, SECONDARY, no_boot).unwrap();
+        let mut check = DigestCheck { expected };
+
+        embassy_futures::block_on(async {
+            assert_eq!(check.before_trial(&mut device, SECONDARY).await, Ok(()));
+            assert_eq!(
+                check.before_trial(&mut device, PRIMARY).await,
+                Err(Error::InvalidImage)
+            );
+        });
+    }
+}
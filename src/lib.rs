@@ -1,12 +1,24 @@
 //! Toolkit for building your own bootloader, tailored to your needs.
+//!
+//! All value types, like [`Slot`], [`Page`], [`Step`] and [`CopyOperation`], as well as the strategies and their requests
+//! are `Send` and `Sync`, such that they can be handed to tasks on other cores or shared behind a mutex.
 #![no_std]
 
-use core::num::NonZeroU16;
+use core::num::{NonZeroU16, NonZeroU32};
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "embedded-storage")]
+pub mod adapters;
 pub mod boot;
+pub mod devices;
+pub mod flow;
+pub mod image;
+pub mod prelude;
+pub mod runner;
 pub mod state;
 pub mod strategies;
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
 
 #[cfg(test)]
 extern crate std;
@@ -14,21 +26,134 @@ extern crate std;
 #[cfg(test)]
 mod mock;
 
-#[derive(Debug)]
-pub struct Error;
+/// Errors that can occur whilst operating the bootloader.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The memory layout of the device does not match its configuration.
+    Misconfigured,
+    /// The underlying memory failed to execute an operation.
+    Storage,
+    /// A value, for example a slot index, is out of range for the device.
+    OutOfRange,
+    /// The image is older than the minimum version, and installing it would roll back the device.
+    Rollback,
+    /// A copied page did not read back identical to its source.
+    VerificationFailed,
+    /// The slot does not hold a valid image, for example because its header lacks the magic.
+    InvalidImage,
+    /// The bootloader state could not be stored or fetched, for example because it is corrupted or does not fit.
+    State,
+    /// Executing the strategy would wear a slot beyond its budget of erases.
+    WearExceeded,
+}
+
+impl Error {
+    const fn message(&self) -> &'static str {
+        match self {
+            Error::Misconfigured => "memory layout does not match the configuration",
+            Error::Storage => "memory operation failed",
+            Error::OutOfRange => "value out of range for the device",
+            Error::Rollback => "image is older than the minimum version",
+            Error::VerificationFailed => "copied page does not match its source",
+            Error::InvalidImage => "slot does not hold a valid image",
+            Error::State => "state could not be stored or fetched",
+            Error::WearExceeded => "slot would exceed its wear budget",
+        }
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Error {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=str}", self.message())
+    }
+}
 
 /// Representation of a concrete device with image slots, supporting copying of pages.
 #[allow(async_fn_in_trait)]
 pub trait Device {
     /// Copy a page from one memory to another.
+    ///
+    /// **Warning:** strategies such as [`SwapScootch`](strategies::swap_scootch::SwapScootch) copy between neighbouring
+    /// pages of the same slot. If both pages share an erase block, erasing `to` before reading `from` destroys the source.
+    /// Either keep every page aligned to whole erase blocks, or stage `from` in RAM before erasing `to`.
     async fn copy(&mut self, operation: CopyOperation) -> Result<(), Error>;
 
+    /// Commit all buffered writes to the underlying memory.
+    ///
+    /// Called after the last copy of a strategy has been executed, but before its last step is recorded and the device boots.
+    /// Devices that do not buffer writes can rely on the default, which does nothing.
+    async fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Prepare the memory for erasing and writing, for example by clearing its write protection.
+    ///
+    /// Called once before the first copy of a strategy. The runner brackets the strategy as
+    /// `unlock`, every `copy`, `flush`, `lock`, and only then `shutdown` and `boot`.
+    /// Devices of which the memory is always writable can rely on the default, which does nothing.
+    async fn unlock(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Protect the memory against erasing and writing again, undoing `unlock`.
+    ///
+    /// Called once after the last copy and `flush` of a strategy, and also when the strategy fails after `unlock`.
+    /// Devices of which the memory is always writable can rely on the default, which does nothing.
+    async fn lock(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Tear down peripherals, for example stopping SPI transfers and disabling DMA, in preparation of booting.
+    ///
+    /// Called after the last step has been recorded, immediately before `boot`.
+    /// Devices without peripherals to tear down can rely on the default, which does nothing.
+    async fn shutdown(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Boot a specific memory slot.
     fn boot(self, slot: Slot) -> !;
 
     /// All image slots should have the same memory size.
     /// Note that these are `Page` in the bootloader sense, which is decoupled from the underlying memory storage.
     fn page_count(&self) -> NonZeroU16;
+
+    /// Number of pages physically backing a slot, or `0` if the slot does not exist.
+    fn capacity_pages(&self, slot: Slot) -> u16;
+
+    /// Number of slots, being `Slot(0)` up to but not including `Slot(slot_count)`.
+    ///
+    /// Used to validate slot indices, for example with [`Slot::try_new`].
+    /// Defaults to the number of consecutive slots from `Slot(0)` onwards that have any capacity.
+    fn slot_count(&self) -> u8 {
+        (0..u8::MAX)
+            .take_while(|index| self.capacity_pages(Slot(*index)) > 0)
+            .count() as u8
+    }
+
+    /// Whether code can be executed in place from `slot`, for example because it resides in internal flash or memory mapped QSPI flash.
+    ///
+    /// Typically only holds for the primary slot. Defaults to `false`, such that images are copied rather than executed in place.
+    fn is_executable(&self, _slot: Slot) -> bool {
+        false
+    }
+
+    /// Check that a slot is physically large enough to hold an image of `page_count` pages.
+    ///
+    /// Catches partition table mistakes before they silently produce wrong `Page` indices.
+    fn check_capacity(&self, slot: Slot) -> Result<(), Error> {
+        if self.capacity_pages(slot) < self.page_count().get() {
+            return Err(Error::Misconfigured);
+        }
+        Ok(())
+    }
 }
 
 /// A device that has a scratch memory which can be used to swap images.
@@ -36,7 +161,35 @@ pub trait DeviceWithScratch: Device {
     /// Number of pages available in the scratch memory.
     fn scratch_page_count(&self) -> NonZeroU16;
 
+    /// Slot holding the scratch memory.
+    ///
+    /// Must differ from every image slot, as strategies overwrite the scratch slot freely.
     fn get_scratch(&self) -> Slot;
+
+    /// First page of the scratch slot that strategies may use, for scratch memories that do not start at the first page.
+    fn scratch_base(&self) -> Page {
+        Page(0)
+    }
+
+    /// Whether the scratch memory endures far more erases than the image slots, for example FRAM.
+    ///
+    /// A hint for [`Swap`](strategies::swap::Swap) to concentrate the wear on the scratch rather than on the primary slot.
+    fn scratch_is_durable(&self) -> bool {
+        false
+    }
+
+    /// Check that the scratch slot is physically large enough to hold `scratch_page_count` pages from `scratch_base`.
+    fn check_scratch_capacity(&self) -> Result<(), Error> {
+        let required = self
+            .scratch_base()
+            .offset(self.scratch_page_count().get())
+            .ok_or(Error::Misconfigured)?;
+
+        if self.capacity_pages(self.get_scratch()) < required.0 {
+            return Err(Error::Misconfigured);
+        }
+        Ok(())
+    }
 }
 
 /// A device that has a primary image slot for which images can be booted.
@@ -44,15 +197,181 @@ pub trait DeviceWithPrimarySlot: Device {
     fn get_primary(&self) -> Slot;
 }
 
+/// A device of which the primary slot is selected by the hardware, for example by a bank swap register read by the boot ROM.
+///
+/// Changing the primary slot does not move any data, and replaces copying images around.
+pub trait DeviceWithBankSwap: DeviceWithPrimarySlot {
+    /// Select the slot that is to be the primary slot from now on.
+    fn set_primary(&mut self, slot: Slot);
+}
+
+/// A device of which the contents of slots can be read, for example to inspect an image header.
+#[allow(async_fn_in_trait)]
+pub trait DeviceWithRead: Device {
+    /// Read `buffer.len()` bytes at `offset` bytes from the start of `location`.
+    ///
+    /// The read may extend into subsequent pages of the same slot.
+    async fn read(
+        &mut self,
+        location: MemoryLocation,
+        offset: u32,
+        buffer: &mut [u8],
+    ) -> Result<(), Error>;
+}
+
+/// A device that can compute a cryptographic digest of a slot, for example to verify an image before booting it.
+///
+/// The algorithm is up to the device, such that it can use a hardware accelerator, or hash in software using [`image::digest`].
+#[allow(async_fn_in_trait)]
+pub trait DeviceWithDigest: Device {
+    /// Digest as produced by the algorithm, for example `[u8; 32]` for SHA-256.
+    type Digest: PartialEq;
+
+    /// Digest of the image in `slot`.
+    async fn digest(&mut self, slot: Slot) -> Result<Self::Digest, Error>;
+}
+
+/// A device that can erase pages, without copying anything into them.
+#[allow(async_fn_in_trait)]
+pub trait DeviceWithErase: Device {
+    /// Erase a single page, leaving it blank.
+    async fn erase(&mut self, location: MemoryLocation) -> Result<(), Error>;
+
+    /// Erase every page of a slot, for example for a factory reset.
+    async fn erase_slot(&mut self, slot: Slot) -> Result<(), Error> {
+        for page in 0..self.capacity_pages(slot) {
+            self.erase(MemoryLocation {
+                slot,
+                page: Page(page),
+            })
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// A device that can copy onto a page without erasing it, as the page has been erased separately.
+///
+/// Required by [`StrategyRunner::run_with_explicit_erase`](runner::StrategyRunner::run_with_explicit_erase), such that
+/// devices of which [`Device::copy`] always erases are refused rather than erasing every destination twice.
+#[allow(async_fn_in_trait)]
+pub trait DeviceWithExplicitErase: DeviceWithErase {
+    /// Copy a page onto a page that has been erased by [`erase`](DeviceWithErase::erase).
+    async fn copy_erased(&mut self, operation: CopyOperation) -> Result<(), Error>;
+}
+
+/// A device of which erased pages can be written directly, rather than only be copied into.
+#[allow(async_fn_in_trait)]
+pub trait DeviceWithWrite: DeviceWithErase {
+    /// Write `data` at `offset` bytes from the start of `location`, which must have been erased.
+    ///
+    /// The write may extend into subsequent pages of the same slot, provided these have been erased as well.
+    async fn write(
+        &mut self,
+        location: MemoryLocation,
+        offset: u32,
+        data: &[u8],
+    ) -> Result<(), Error>;
+}
+
+/// A device that can erase a page in the background, whilst copying other pages.
+///
+/// Used by [`StrategyRunner::run_with_erase_ahead`](runner::StrategyRunner::run_with_erase_ahead) to erase the destination
+/// of the next copy whilst the current copy is executed, for memories of which erasing is slow and can overlap with other bus activity.
+#[allow(async_fn_in_trait)]
+pub trait DeviceWithEraseAhead: Device {
+    /// Start erasing a page, without waiting for the erase to complete.
+    ///
+    /// At most one erase is pending at a time, and the page is neither read nor written until [`finish_erase`](Self::finish_erase).
+    async fn begin_erase(&mut self, location: MemoryLocation) -> Result<(), Error>;
+
+    /// Wait for the pending erase, if any, to complete.
+    async fn finish_erase(&mut self) -> Result<(), Error>;
+}
+
+/// A device of which the slots reside in memories with possibly differing physical page sizes.
+///
+/// See [`Page`] for how the bootloader page relates to the physical pages.
+pub trait DeviceWithPhysicalPages: Device {
+    /// Size in bytes of a bootloader `Page`.
+    fn page_size(&self) -> NonZeroU32;
+
+    /// Size in bytes of a physical page of the memory in which `slot` resides.
+    fn physical_page_size(&self, slot: Slot) -> NonZeroU32;
+
+    /// Number of physical pages erased when copying a bootloader page into `slot`.
+    fn erases_per_page(&self, slot: Slot) -> u32 {
+        self.page_size().get() / self.physical_page_size(slot).get()
+    }
+
+    /// Check that the bootloader page size is a multiple of the physical page size of `slot`.
+    fn check_page_size(&self, slot: Slot) -> Result<(), Error> {
+        if !self
+            .page_size()
+            .get()
+            .is_multiple_of(self.physical_page_size(slot).get())
+        {
+            return Err(Error::Misconfigured);
+        }
+        Ok(())
+    }
+
+    /// Check that the [`capacity_pages`](Device::capacity_pages) of `slot` fit in the `capacity` bytes of its backing memory,
+    /// for example the size of its partition.
+    ///
+    /// Catches pages counted in the wrong memory, which [`check_capacity`](Device::check_capacity) can not as it only
+    /// compares page counts. Together they ensure that `page_count() * page_size()` bytes fit in an image slot.
+    fn check_backing_capacity(&self, slot: Slot, capacity: u64) -> Result<(), Error> {
+        let pages = self.capacity_pages(slot) as u64;
+        if pages * self.page_size().get() as u64 > capacity {
+            return Err(Error::Misconfigured);
+        }
+        Ok(())
+    }
+}
+
+/// A device of which the slots do not necessarily start at the first page of their underlying memory.
+///
+/// Strategies keep planning with zero-based `Page` indices, and the device translates these in `copy`
+/// using the layout of each slot.
+pub trait DeviceWithSlotLayout: Device {
+    /// Placement of a slot within its memory, or `None` if the slot does not exist.
+    fn slot_layout(&self, slot: Slot) -> Option<SlotLayout>;
+}
+
+/// A device of which the slots are memory mapped, such that an image can be jumped to.
+pub trait DeviceWithEntry: Device {
+    /// Address of the start of the image in `slot`, typically pointing to its vector table.
+    ///
+    /// Can be passed to [`Boot::boot`](boot::Boot::boot), for example using [`boot::boot_entry`].
+    fn entry_address(&self, slot: Slot) -> *const u32;
+}
+
 /// Marker trait to indicate that the device can boot from all image slots.
 pub trait DeviceSupportsXip: Device {}
 
 /// Image slot with regards to the bootloader.
 ///
 /// Memory layout describes in which memory and at what location each slot resides.
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 pub struct Slot(pub u8);
 
+impl Slot {
+    pub const fn new(index: u8) -> Self {
+        Slot(index)
+    }
+
+    /// Slot for an index from an untrusted source, for example a DFU command, for a device with `slot_count` slots.
+    ///
+    /// Typically `slot_count` is [`Device::slot_count`].
+    pub const fn try_new(index: u8, slot_count: u8) -> Result<Self, Error> {
+        if index >= slot_count {
+            return Err(Error::OutOfRange);
+        }
+        Ok(Slot(index))
+    }
+}
+
 /// Page number with regards to the bootloader.
 ///
 /// If the underlying memory has a disparate page size,
@@ -62,27 +381,540 @@ pub struct Slot(pub u8);
 ///
 /// For example: with a 1K page size for primary memory and 4K page size for secondary memory,
 /// `Page(0)` is 4K large and covers 4 physical pages in primary memory.
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct Page(pub(crate) u16);
 
+impl Page {
+    pub const fn new(index: u16) -> Self {
+        Page(index)
+    }
+
+    pub const fn as_u16(self) -> u16 {
+        self.0
+    }
+
+    /// Page `delta` pages further, or `None` if that overflows.
+    pub const fn offset(self, delta: u16) -> Option<Page> {
+        match self.0.checked_add(delta) {
+            Some(index) => Some(Page(index)),
+            None => None,
+        }
+    }
+
+    /// Page `delta` pages further, saturating at the last representable page.
+    ///
+    /// For planning where the bounds of the strategy rule out overflow; use [`offset`](Self::offset) for untrusted input.
+    pub const fn saturating_add(self, delta: u16) -> Page {
+        Page(self.0.saturating_add(delta))
+    }
+}
+
+impl core::fmt::Display for Page {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Page {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=u16}", self.0)
+    }
+}
+
+/// Placement of a slot within its underlying memory.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SlotLayout {
+    /// Page in the underlying memory at which the slot starts.
+    pub base: Page,
+    /// Number of pages in the slot.
+    pub len: NonZeroU16,
+}
+
+impl SlotLayout {
+    /// Translate a page relative to the slot into a page relative to the underlying memory.
+    ///
+    /// Returns `None` if the page does not fall within the slot.
+    pub const fn translate(&self, page: Page) -> Option<Page> {
+        if page.0 >= self.len.get() {
+            return None;
+        }
+
+        self.base.offset(page.0)
+    }
+
+    /// Address of the start of the slot, for a memory mapped at `memory_base` with pages of `page_size` bytes.
+    pub const fn entry_address(&self, memory_base: usize, page_size: usize) -> *const u32 {
+        (memory_base + self.base.0 as usize * page_size) as *const u32
+    }
+}
+
+/// Physical address range of a memory mapped slot.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SlotRegion {
+    pub slot: Slot,
+    /// Address at which the slot starts.
+    pub base: u32,
+    /// Size of the slot in bytes.
+    pub len: NonZeroU32,
+}
+
+/// Addresses of up to `N` memory mapped slots, for example to implement [`DeviceWithEntry`].
+///
+/// Slots that are not memory mapped, for example those in external flash, are simply left out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SlotMap<const N: usize> {
+    regions: [SlotRegion; N],
+}
+
+impl<const N: usize> SlotMap<N> {
+    pub const fn new(regions: [SlotRegion; N]) -> Self {
+        Self { regions }
+    }
+
+    /// Region of `slot`, or `None` if the slot is not mapped.
+    pub const fn region(&self, slot: Slot) -> Option<&SlotRegion> {
+        let mut i = 0;
+        while i < N {
+            if self.regions[i].slot.0 == slot.0 {
+                return Some(&self.regions[i]);
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// Address at which `slot` starts, or `None` if the slot is not mapped.
+    pub const fn address_of(&self, slot: Slot) -> Option<u32> {
+        match self.region(slot) {
+            Some(region) => Some(region.base),
+            None => None,
+        }
+    }
+}
+
 /// Step number of a specific strategy that has to be or has been executed.
 ///
 /// What operation this step entails can be extracted from the strategy.
 /// Step numbers are strictly monotonical.
 /// Every step can be interrupted at any time, and after a step has been executed this has to be recorded in the persistant state.
 /// If the step is executed, but not yet recorded in the persistant state, it must be valid to execute the step again.
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
-pub struct Step(pub(crate) u16);
+///
+/// The default step is the first step of any strategy.
+/// Steps are wider than pages, as strategies can take several steps for every page of an image.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Serialize, Deserialize)]
+pub struct Step(pub(crate) u32);
+
+impl Step {
+    /// Iterate over all steps from `start` up to, but not including, `end`.
+    pub fn range(start: Step, end: Step) -> impl Iterator<Item = Step> + Clone {
+        (start.0..end.0).map(Step)
+    }
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+    /// The step following this step.
+    pub const fn next(self) -> Step {
+        Step(self.0 + 1)
+    }
+
+    /// Step `steps` steps earlier, saturating at the first step.
+    pub const fn saturating_sub(self, steps: u32) -> Step {
+        Step(self.0.saturating_sub(steps))
+    }
+
+    pub const fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+impl core::fmt::Display for Step {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Step {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=u32}", self.0)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct MemoryLocation {
     pub slot: Slot,
     pub page: Page,
 }
 
+impl MemoryLocation {
+    /// Location in the same slot, `delta` pages further.
+    ///
+    /// Saturates at the last representable page, which lies beyond the end of every slot, such that a strategy planning
+    /// outside of its slots is reported by [`verify_layout`](strategies::Strategy::verify_layout) rather than wrapping around.
+    pub const fn with_page_offset(self, delta: u16) -> MemoryLocation {
+        MemoryLocation {
+            slot: self.slot,
+            page: self.page.saturating_add(delta),
+        }
+    }
+}
+
 /// Perform an erase of `to` (if necessary) and copy `from` to `to`, leaving `from` intact.
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct CopyOperation {
     pub from: MemoryLocation,
     pub to: MemoryLocation,
 }
+
+/// Erase of a single page, leaving it blank.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EraseOperation {
+    pub location: MemoryLocation,
+}
+
+impl CopyOperation {
+    /// Whether the page is copied onto itself, which devices should treat as a no-op.
+    ///
+    /// Pages never partially overlap, thus this is the only copy of which the source is affected by the destination.
+    /// Naively erasing the destination first would otherwise destroy the page.
+    pub fn is_in_place(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+/// Single operation on the memory of a device, of which copies do not imply erasing their destination.
+///
+/// See [`Strategy::plan_ops`](strategies::Strategy::plan_ops).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Operation {
+    /// Erase a page, before it is written by the next copy.
+    Erase(EraseOperation),
+    /// Copy a page onto an erased page.
+    Copy(CopyOperation),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Checked at compile time, such that adding a field that is not `Send` or `Sync` breaks the build.
+    const _: fn() = || {
+        use crate::{
+            runner::{RunStats, WearBudget},
+            state::{CompletionLedger, Request, State},
+            strategies::{StrategyError, prelude::*},
+        };
+
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<Error>();
+        assert_send_sync::<Slot>();
+        assert_send_sync::<Page>();
+        assert_send_sync::<Step>();
+        assert_send_sync::<SlotLayout>();
+        assert_send_sync::<SlotRegion>();
+        assert_send_sync::<SlotMap<4>>();
+        assert_send_sync::<MemoryLocation>();
+        assert_send_sync::<CopyOperation>();
+        assert_send_sync::<Operation>();
+        assert_send_sync::<EraseOperation>();
+        assert_send_sync::<StrategyError>();
+        assert_send_sync::<CompletionLedger>();
+        assert_send_sync::<Request<AnyRequest>>();
+        assert_send_sync::<State<AnyRequest>>();
+        assert_send_sync::<RunStats>();
+        assert_send_sync::<WearBudget>();
+
+        assert_send_sync::<AnyStrategy>();
+        assert_send_sync::<CopyStrategy>();
+        assert_send_sync::<CopyWithBackup>();
+        assert_send_sync::<DecompressCopy>();
+        assert_send_sync::<DeltaCopy>();
+        assert_send_sync::<Swap>();
+        assert_send_sync::<SwapBanks>();
+        assert_send_sync::<SwapSABS>();
+        assert_send_sync::<SwapScootch>();
+        assert_send_sync::<WearLeveling<SwapScootch>>();
+        assert_send_sync::<Xip>();
+        assert_send_sync::<XipOrCopy>();
+        assert_send_sync::<XipThenCopy>();
+        assert_send_sync::<XipThenCopyRequest>();
+        assert_send_sync::<SwapScootchRequest>();
+        assert_send_sync::<CopyRequest>();
+    };
+
+    #[test]
+    fn error_display() {
+        use std::{collections::BTreeSet, string::ToString};
+
+        let errors = [
+            Error::Misconfigured,
+            Error::Storage,
+            Error::OutOfRange,
+            Error::Rollback,
+            Error::VerificationFailed,
+            Error::InvalidImage,
+            Error::State,
+            Error::WearExceeded,
+        ];
+        let messages: BTreeSet<_> = errors.iter().map(ToString::to_string).collect();
+
+        assert_eq!(messages.len(), errors.len());
+        assert!(messages.iter().all(|message| !message.is_empty()));
+    }
+
+    #[test]
+    fn index_display() {
+        use std::string::ToString;
+
+        assert_eq!(Step(5).to_string(), "5");
+        assert_eq!(Page(12).to_string(), "12");
+    }
+
+    #[test]
+    fn check_capacity() {
+        use crate::mock::single_scratch::{MockDevice, PRIMARY, SECONDARY};
+
+        let device = MockDevice::new();
+        assert_eq!(device.check_capacity(PRIMARY), Ok(()));
+        assert_eq!(device.check_capacity(SECONDARY), Ok(()));
+        assert_eq!(device.check_scratch_capacity(), Ok(()));
+        assert_eq!(device.check_capacity(Slot(3)), Err(Error::Misconfigured));
+    }
+
+    #[test]
+    fn check_page_size() {
+        use crate::mock::{
+            physical_pages::PhysicalPagesDevice,
+            single_scratch::{MockDevice, PRIMARY, SCRATCH, SECONDARY},
+        };
+
+        // A 1K page size for primary memory and 4K page size for secondary memory, as in the `Page` docs.
+        let physical = [(PRIMARY, 1024), (SECONDARY, 4096), (SCRATCH, 4096)];
+
+        let device = PhysicalPagesDevice::new(MockDevice::new(), 4096, &physical);
+        for slot in [PRIMARY, SECONDARY, SCRATCH] {
+            assert_eq!(device.check_page_size(slot), Ok(()));
+        }
+        assert_eq!(device.erases_per_page(PRIMARY), 4);
+        assert_eq!(device.erases_per_page(SECONDARY), 1);
+
+        let device = PhysicalPagesDevice::new(MockDevice::new(), 2048, &physical);
+        assert_eq!(device.check_page_size(PRIMARY), Ok(()));
+        assert_eq!(device.check_page_size(SECONDARY), Err(Error::Misconfigured));
+    }
+
+    #[test]
+    fn check_capacity_misconfigured() {
+        use crate::mock::misconfigured::{MockDevice, PRIMARY, SECONDARY};
+
+        let device = MockDevice::new();
+        assert_eq!(device.check_capacity(PRIMARY), Ok(()));
+        assert_eq!(device.check_capacity(SECONDARY), Err(Error::Misconfigured));
+    }
+
+    #[test]
+    fn check_backing_capacity() {
+        use crate::mock::{
+            physical_pages::PhysicalPagesDevice,
+            single_scratch::{MockDevice, PRIMARY, SCRATCH},
+        };
+
+        let device = PhysicalPagesDevice::new(MockDevice::new(), 1024, &[]);
+        let image = device.page_count().get() as u64 * 1024;
+        assert_eq!(device.check_backing_capacity(PRIMARY, image), Ok(()));
+        assert_eq!(device.check_backing_capacity(SCRATCH, 1024), Ok(()));
+        // The pages were counted in a memory larger than the partition backing the slot.
+        assert_eq!(
+            device.check_backing_capacity(PRIMARY, image - 1),
+            Err(Error::Misconfigured)
+        );
+    }
+
+    #[test]
+    fn erase_slot() {
+        use crate::mock::single_scratch::{IMAGE_A, MockDevice, PRIMARY, SCRATCH, SECONDARY};
+
+        let mut device = MockDevice::new();
+        embassy_futures::block_on(device.erase_slot(SECONDARY)).unwrap();
+
+        assert_eq!(device.primary, IMAGE_A);
+        assert_eq!(device.secondary, [0xFF; 3]);
+
+        for page in 0..device.page_count().get() {
+            let location = |slot| MemoryLocation {
+                slot,
+                page: Page(page),
+            };
+            assert_eq!(device.wear.wear_at(location(PRIMARY)), 0);
+            assert_eq!(device.wear.wear_at(location(SECONDARY)), 1);
+        }
+        assert!(device.wear.check_slot(SCRATCH, 0));
+    }
+
+    #[test]
+    fn step_range() {
+        use crate::{
+            mock::single_scratch::{MockDevice, SECONDARY},
+            strategies::{
+                Strategy,
+                swap_scootch::{Request, SwapScootch},
+            },
+        };
+
+        let strategy = SwapScootch::new(
+            &MockDevice::new(),
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        let mut manual = std::vec::Vec::new();
+        for step_i in 0..strategy.last_step().0 {
+            manual.push(Step(step_i));
+        }
+
+        let steps: std::vec::Vec<_> = Step::range(Step::default(), strategy.last_step()).collect();
+        assert_eq!(steps, manual);
+        assert_eq!(steps.last().unwrap().next(), strategy.last_step());
+        assert_eq!(strategy.last_step().as_u32(), 9);
+        assert_eq!(Step::range(Step(3), Step(3)).count(), 0);
+    }
+
+    #[test]
+    fn slot_try_new() {
+        assert_eq!(Slot::try_new(0, 3), Ok(Slot::new(0)));
+        assert_eq!(Slot::try_new(2, 3), Ok(Slot::new(2)));
+        assert_eq!(Slot::try_new(3, 3), Err(Error::OutOfRange));
+        assert_eq!(Slot::try_new(7, 3), Err(Error::OutOfRange));
+        assert_eq!(Slot::try_new(0, 0), Err(Error::OutOfRange));
+    }
+
+    #[test]
+    fn slot_count() {
+        use crate::mock::{generic::GenericMock, misconfigured, tri_slot};
+
+        assert_eq!(GenericMock::new(3, 1).slot_count(), 3);
+        assert_eq!(tri_slot::MockDevice::new().slot_count(), 3);
+        assert_eq!(misconfigured::MockDevice::new().slot_count(), 2);
+        assert_eq!(
+            Slot::try_new(3, GenericMock::new(3, 1).slot_count()),
+            Err(Error::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn slot_map() {
+        const MAP: SlotMap<2> = SlotMap::new([
+            SlotRegion {
+                slot: Slot(0),
+                base: 0x0800_8000,
+                len: NonZeroU32::new(0x18000).unwrap(),
+            },
+            SlotRegion {
+                slot: Slot(2),
+                base: 0x9000_0000,
+                len: NonZeroU32::new(0x1000).unwrap(),
+            },
+        ]);
+
+        assert_eq!(MAP.address_of(Slot(0)), Some(0x0800_8000));
+        assert_eq!(MAP.address_of(Slot(2)), Some(0x9000_0000));
+        assert_eq!(MAP.address_of(Slot(1)), None);
+        assert_eq!(
+            MAP.region(Slot(2)).map(|region| region.len.get()),
+            Some(0x1000)
+        );
+    }
+
+    #[test]
+    fn location_hash() {
+        use std::collections::HashMap;
+
+        let location = |slot, page| MemoryLocation {
+            slot: Slot(slot),
+            page: Page(page),
+        };
+
+        let mut wear = HashMap::new();
+        for (slot, page) in [(0, 1), (1, 0), (0, 1)] {
+            *wear.entry(location(slot, page)).or_insert(0) += 1;
+        }
+
+        assert_eq!(wear.len(), 2);
+        assert_eq!(wear[&location(0, 1)], 2);
+        assert_eq!(wear[&location(1, 0)], 1);
+    }
+
+    #[test]
+    fn page_offset() {
+        assert_eq!(Page(3).offset(0), Some(Page(3)));
+        assert_eq!(Page(3).offset(4), Some(Page(7)));
+        assert_eq!(Page(u16::MAX - 1).offset(1), Some(Page(u16::MAX)));
+        assert_eq!(Page(u16::MAX).offset(1), None);
+
+        let location = MemoryLocation {
+            slot: Slot(1),
+            page: Page(2),
+        };
+        assert_eq!(
+            location.with_page_offset(3),
+            MemoryLocation {
+                slot: Slot(1),
+                page: Page(5),
+            }
+        );
+    }
+
+    #[test]
+    fn saturating() {
+        assert_eq!(Page(3).saturating_add(4), Page(7));
+        assert_eq!(Page(u16::MAX - 1).saturating_add(1), Page(u16::MAX));
+        assert_eq!(Page(u16::MAX - 1).saturating_add(2), Page(u16::MAX));
+
+        assert_eq!(Step(7).saturating_sub(4), Step(3));
+        assert_eq!(Step(4).saturating_sub(4), Step(0));
+        assert_eq!(Step(3).saturating_sub(4), Step(0));
+    }
+
+    #[test]
+    fn page_offset_overflow() {
+        let location = MemoryLocation {
+            slot: Slot(1),
+            page: Page(u16::MAX),
+        };
+        assert_eq!(location.with_page_offset(1), location);
+    }
+
+    #[test]
+    fn slot_layout_translate() {
+        let layout = SlotLayout {
+            base: Page(4),
+            len: NonZeroU16::new(3).unwrap(),
+        };
+
+        assert_eq!(layout.translate(Page(0)), Some(Page(4)));
+        assert_eq!(layout.translate(Page(2)), Some(Page(6)));
+        assert_eq!(layout.translate(Page(3)), None);
+
+        let layout = SlotLayout {
+            base: Page(u16::MAX),
+            len: NonZeroU16::new(2).unwrap(),
+        };
+        assert_eq!(layout.translate(Page(0)), Some(Page(u16::MAX)));
+        assert_eq!(layout.translate(Page(1)), None);
+    }
+
+    #[test]
+    fn slot_layout_entry_address() {
+        let layout = SlotLayout {
+            base: Page(16),
+            len: NonZeroU16::new(48).unwrap(),
+        };
+
+        assert_eq!(
+            layout.entry_address(0x0800_0000, 0x800),
+            0x0800_8000 as *const u32
+        );
+        assert_eq!(layout.entry_address(0, 0x1000), 0x0001_0000 as *const u32);
+    }
+}
@@ -0,0 +1,83 @@
+use core::num::NonZeroU16;
+
+use crate::{
+    CopyOperation, Device, DeviceWithBankSwap, DeviceWithPrimarySlot, MemoryLocation, Slot,
+    mock::WearTracker,
+};
+
+const PAGE_COUNT: NonZeroU16 = NonZeroU16::new(3).unwrap();
+
+/// Dual bank device, of which the primary slot can be selected without moving data.
+pub struct MockDevice {
+    pub alpha: [u8; PAGE_COUNT.get() as usize],
+    pub beta: [u8; PAGE_COUNT.get() as usize],
+    pub primary: Slot,
+    pub wear: WearTracker,
+}
+
+pub const IMAGE_A: [u8; PAGE_COUNT.get() as usize] = [0x01, 0x02, 0x03];
+pub const IMAGE_B: [u8; PAGE_COUNT.get() as usize] = [0x04, 0x05, 0x06];
+
+pub const ALPHA: Slot = Slot(0);
+pub const BETA: Slot = Slot(1);
+
+impl MockDevice {
+    pub const fn new() -> MockDevice {
+        MockDevice {
+            alpha: IMAGE_A,
+            beta: IMAGE_B,
+            primary: ALPHA,
+            wear: WearTracker::new(),
+        }
+    }
+
+    fn get_mut(&mut self, addr: MemoryLocation) -> &mut u8 {
+        match addr.slot {
+            ALPHA => self.alpha.as_mut_slice(),
+            BETA => self.beta.as_mut_slice(),
+            _ => unimplemented!(),
+        }
+        .get_mut(addr.page.0 as usize)
+        .unwrap()
+    }
+}
+
+impl Device for MockDevice {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), crate::Error> {
+        let value = *self.get_mut(operation.from);
+        *self.get_mut(operation.to) = value;
+
+        self.wear.read(operation.from);
+        self.wear.increase(operation.to);
+
+        Ok(())
+    }
+
+    fn boot(self, _slot: Slot) -> ! {
+        unimplemented!()
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        PAGE_COUNT
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        (match slot {
+            ALPHA => self.alpha.len(),
+            BETA => self.beta.len(),
+            _ => 0,
+        }) as u16
+    }
+}
+
+impl DeviceWithPrimarySlot for MockDevice {
+    fn get_primary(&self) -> Slot {
+        self.primary
+    }
+}
+
+impl DeviceWithBankSwap for MockDevice {
+    fn set_primary(&mut self, slot: Slot) {
+        self.primary = slot;
+    }
+}
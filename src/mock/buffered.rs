@@ -0,0 +1,111 @@
+use core::num::NonZeroU16;
+
+use crate::{CopyOperation, Device, DeviceWithPrimarySlot, DeviceWithScratch, Page, Slot};
+
+/// Device decorator that buffers writes until they are flushed, and requires a shutdown before booting.
+///
+/// Copies are only accepted whilst unlocked, and the device must be locked again, after flushing, before booting.
+pub struct BufferedDevice<D> {
+    pub inner: D,
+    /// Number of copies that have not been flushed yet.
+    pub unflushed: usize,
+    pub flushes: usize,
+    /// Whether the peripherals have been torn down.
+    pub shut_down: bool,
+    /// Whether the memory is unlocked for writing.
+    pub unlocked: bool,
+    pub locks: usize,
+}
+
+impl<D> BufferedDevice<D> {
+    pub const fn new(inner: D) -> Self {
+        Self {
+            inner,
+            unflushed: 0,
+            flushes: 0,
+            shut_down: false,
+            unlocked: false,
+            locks: 0,
+        }
+    }
+}
+
+impl<D: Device> Device for BufferedDevice<D> {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), crate::Error> {
+        assert!(self.unlocked, "copying whilst locked");
+        self.inner.copy(operation).await?;
+        self.unflushed += 1;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), crate::Error> {
+        self.unflushed = 0;
+        self.flushes += 1;
+        Ok(())
+    }
+
+    async fn unlock(&mut self) -> Result<(), crate::Error> {
+        assert!(!self.unlocked, "unlocking twice");
+        self.unlocked = true;
+        Ok(())
+    }
+
+    async fn lock(&mut self) -> Result<(), crate::Error> {
+        assert!(self.unlocked, "locking without unlocking");
+        self.unlocked = false;
+        self.locks += 1;
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<(), crate::Error> {
+        self.shut_down = true;
+        Ok(())
+    }
+
+    fn boot(self, slot: Slot) -> ! {
+        assert_eq!(self.unflushed, 0, "booting with unflushed writes");
+        assert!(self.shut_down, "booting without shutting down");
+        assert!(!self.unlocked, "booting whilst unlocked");
+        self.inner.boot(slot)
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        self.inner.page_count()
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        self.inner.capacity_pages(slot)
+    }
+
+    fn slot_count(&self) -> u8 {
+        self.inner.slot_count()
+    }
+
+    fn is_executable(&self, slot: Slot) -> bool {
+        self.inner.is_executable(slot)
+    }
+}
+
+impl<D: DeviceWithScratch> DeviceWithScratch for BufferedDevice<D> {
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        self.inner.scratch_page_count()
+    }
+
+    fn get_scratch(&self) -> Slot {
+        self.inner.get_scratch()
+    }
+
+    fn scratch_base(&self) -> Page {
+        self.inner.scratch_base()
+    }
+
+    fn scratch_is_durable(&self) -> bool {
+        self.inner.scratch_is_durable()
+    }
+}
+
+impl<D: DeviceWithPrimarySlot> DeviceWithPrimarySlot for BufferedDevice<D> {
+    fn get_primary(&self) -> Slot {
+        self.inner.get_primary()
+    }
+}
@@ -0,0 +1,88 @@
+use core::num::NonZeroU16;
+
+use crate::{
+    CopyOperation, Device, DeviceWithErase, DeviceWithEraseAhead, DeviceWithPrimarySlot,
+    MemoryLocation, Slot,
+};
+
+/// Device decorator erasing pages in the background, counting the copies overlapping an erase.
+pub struct EraseAheadDevice<D> {
+    pub inner: D,
+    pending: Option<MemoryLocation>,
+    /// Number of copies executed whilst an erase was pending.
+    pub overlaps: usize,
+    /// Number of pages erased ahead.
+    pub erased_ahead: usize,
+}
+
+impl<D> EraseAheadDevice<D> {
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            pending: None,
+            overlaps: 0,
+            erased_ahead: 0,
+        }
+    }
+}
+
+impl<D: DeviceWithErase> Device for EraseAheadDevice<D> {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), crate::Error> {
+        if let Some(pending) = self.pending {
+            assert!(
+                pending != operation.from && pending != operation.to,
+                "copy touches a page being erased"
+            );
+            self.overlaps += 1;
+        }
+
+        self.inner.copy(operation).await
+    }
+
+    async fn flush(&mut self) -> Result<(), crate::Error> {
+        assert!(self.pending.is_none(), "flushed whilst erasing");
+        self.inner.flush().await
+    }
+
+    fn boot(self, slot: Slot) -> ! {
+        self.inner.boot(slot)
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        self.inner.page_count()
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        self.inner.capacity_pages(slot)
+    }
+
+    fn slot_count(&self) -> u8 {
+        self.inner.slot_count()
+    }
+
+    fn is_executable(&self, slot: Slot) -> bool {
+        self.inner.is_executable(slot)
+    }
+}
+
+impl<D: DeviceWithErase> DeviceWithEraseAhead for EraseAheadDevice<D> {
+    async fn begin_erase(&mut self, location: MemoryLocation) -> Result<(), crate::Error> {
+        assert!(self.pending.is_none(), "erase already pending");
+        self.pending = Some(location);
+        Ok(())
+    }
+
+    async fn finish_erase(&mut self) -> Result<(), crate::Error> {
+        if let Some(location) = self.pending.take() {
+            self.inner.erase(location).await?;
+            self.erased_ahead += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<D: DeviceWithErase + DeviceWithPrimarySlot> DeviceWithPrimarySlot for EraseAheadDevice<D> {
+    fn get_primary(&self) -> Slot {
+        self.inner.get_primary()
+    }
+}
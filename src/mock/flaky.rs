@@ -0,0 +1,96 @@
+use core::num::NonZeroU16;
+use std::collections::BTreeSet;
+
+use crate::{CopyOperation, Device, DeviceWithPrimarySlot, DeviceWithScratch, Page, Slot};
+
+/// Device decorator that fails specific copy attempts, leaving the destination untouched.
+pub struct FlakyDevice<D> {
+    pub inner: D,
+    /// Indices of the copy attempts that fail, counting from zero.
+    pub failing_attempts: BTreeSet<usize>,
+    /// Number of copy attempts so far, including failed attempts.
+    pub attempts: usize,
+}
+
+impl<D> FlakyDevice<D> {
+    pub fn new(inner: D, failing_attempts: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            inner,
+            failing_attempts: failing_attempts.into_iter().collect(),
+            attempts: 0,
+        }
+    }
+}
+
+impl<D: Device> Device for FlakyDevice<D> {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), crate::Error> {
+        let attempt = self.attempts;
+        self.attempts += 1;
+
+        if self.failing_attempts.contains(&attempt) {
+            return Err(crate::Error::Storage);
+        }
+
+        self.inner.copy(operation).await
+    }
+
+    async fn flush(&mut self) -> Result<(), crate::Error> {
+        self.inner.flush().await
+    }
+
+    async fn unlock(&mut self) -> Result<(), crate::Error> {
+        self.inner.unlock().await
+    }
+
+    async fn lock(&mut self) -> Result<(), crate::Error> {
+        self.inner.lock().await
+    }
+
+    async fn shutdown(&mut self) -> Result<(), crate::Error> {
+        self.inner.shutdown().await
+    }
+
+    fn boot(self, slot: Slot) -> ! {
+        self.inner.boot(slot)
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        self.inner.page_count()
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        self.inner.capacity_pages(slot)
+    }
+
+    fn slot_count(&self) -> u8 {
+        self.inner.slot_count()
+    }
+
+    fn is_executable(&self, slot: Slot) -> bool {
+        self.inner.is_executable(slot)
+    }
+}
+
+impl<D: DeviceWithScratch> DeviceWithScratch for FlakyDevice<D> {
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        self.inner.scratch_page_count()
+    }
+
+    fn get_scratch(&self) -> Slot {
+        self.inner.get_scratch()
+    }
+
+    fn scratch_base(&self) -> Page {
+        self.inner.scratch_base()
+    }
+
+    fn scratch_is_durable(&self) -> bool {
+        self.inner.scratch_is_durable()
+    }
+}
+
+impl<D: DeviceWithPrimarySlot> DeviceWithPrimarySlot for FlakyDevice<D> {
+    fn get_primary(&self) -> Slot {
+        self.inner.get_primary()
+    }
+}
@@ -0,0 +1,173 @@
+use core::num::{NonZeroU16, NonZeroU32};
+use std::{collections::BTreeSet, vec, vec::Vec};
+
+use crate::{
+    CopyOperation, Device, DeviceWithPhysicalPages, DeviceWithPrimarySlot, DeviceWithRead,
+    DeviceWithScratch, Error, MemoryLocation, Page, Slot, mock::WearTracker,
+};
+
+/// Device with a configurable amount of pages, of which each page of the images holds a unique tag.
+pub struct GenericMock {
+    pub primary: Vec<u32>,
+    pub secondary: Vec<u32>,
+    pub scratch: Vec<u32>,
+    /// Number of scratch pages preceding the pages available to strategies.
+    ///
+    /// Set beyond the scratch slot to mimic a misconfigured base, for which all pages of the scratch slot are reported.
+    pub scratch_base: u16,
+    /// Whether the scratch is reported as [durable](DeviceWithScratch::scratch_is_durable).
+    pub durable_scratch: bool,
+    pub wear: WearTracker,
+}
+
+pub const PRIMARY: Slot = Slot(0);
+pub const SECONDARY: Slot = Slot(1);
+pub const SCRATCH: Slot = Slot(2);
+
+const BLANK: u32 = u32::MAX;
+
+impl GenericMock {
+    pub fn new(page_count: u16, scratch_page_count: u16) -> Self {
+        Self {
+            primary: Self::image_a(page_count),
+            secondary: Self::image_b(page_count),
+            scratch: vec![BLANK; scratch_page_count as usize],
+            scratch_base: 0,
+            durable_scratch: false,
+            wear: WearTracker::new(),
+        }
+    }
+
+    /// Precede the scratch pages by `scratch_base` pages which are not to be touched.
+    pub fn with_scratch_base(mut self, scratch_base: u16) -> Self {
+        self.scratch
+            .splice(0..0, vec![BLANK; scratch_base as usize]);
+        self.scratch_base = scratch_base;
+        self
+    }
+
+    pub fn image_a(page_count: u16) -> Vec<u32> {
+        (0..page_count as u32).collect()
+    }
+
+    pub fn image_b(page_count: u16) -> Vec<u32> {
+        (0..page_count as u32).map(|page| 0x1_0000 + page).collect()
+    }
+
+    /// Whether every page of both original images is still stored somewhere on the device.
+    pub fn holds_all_images(&self) -> bool {
+        let page_count = self.primary.len() as u16;
+        let stored: BTreeSet<u32> = self
+            .primary
+            .iter()
+            .chain(&self.secondary)
+            .chain(&self.scratch)
+            .copied()
+            .collect();
+
+        Self::image_a(page_count)
+            .iter()
+            .chain(&Self::image_b(page_count))
+            .all(|tag| stored.contains(tag))
+    }
+
+    fn get_mut(&mut self, addr: MemoryLocation) -> &mut u32 {
+        match addr.slot {
+            PRIMARY => self.primary.as_mut_slice(),
+            SECONDARY => self.secondary.as_mut_slice(),
+            SCRATCH => self.scratch.as_mut_slice(),
+            _ => unimplemented!(),
+        }
+        .get_mut(addr.page.0 as usize)
+        .unwrap()
+    }
+}
+
+impl Device for GenericMock {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), crate::Error> {
+        let value = *self.get_mut(operation.from);
+        *self.get_mut(operation.to) = value;
+
+        self.wear.read(operation.from);
+        self.wear.increase(operation.to);
+
+        Ok(())
+    }
+
+    fn boot(self, _slot: Slot) -> ! {
+        unimplemented!()
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        NonZeroU16::new(self.primary.len() as u16).unwrap()
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        (match slot {
+            PRIMARY => self.primary.len(),
+            SECONDARY => self.secondary.len(),
+            SCRATCH => self.scratch.len(),
+            _ => 0,
+        }) as u16
+    }
+}
+
+impl DeviceWithScratch for GenericMock {
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        let pages = self.scratch.len() as u16;
+        NonZeroU16::new(pages.checked_sub(self.scratch_base).unwrap_or(pages)).unwrap()
+    }
+
+    fn get_scratch(&self) -> Slot {
+        SCRATCH
+    }
+
+    fn scratch_base(&self) -> Page {
+        Page(self.scratch_base)
+    }
+
+    fn scratch_is_durable(&self) -> bool {
+        self.durable_scratch
+    }
+}
+
+impl DeviceWithPrimarySlot for GenericMock {
+    fn get_primary(&self) -> Slot {
+        PRIMARY
+    }
+}
+
+impl DeviceWithPhysicalPages for GenericMock {
+    fn page_size(&self) -> NonZeroU32 {
+        // Each page consists of the bytes of its tag, see `read`.
+        NonZeroU32::new(4).unwrap()
+    }
+
+    fn physical_page_size(&self, _slot: Slot) -> NonZeroU32 {
+        self.page_size()
+    }
+}
+
+impl DeviceWithRead for GenericMock {
+    async fn read(
+        &mut self,
+        location: MemoryLocation,
+        offset: u32,
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        // Each page consists of the little endian bytes of its tag.
+        for (index, byte) in (offset as usize..).zip(buffer) {
+            let page = location
+                .page
+                .offset((index / 4) as u16)
+                .filter(|page| page.0 < self.capacity_pages(location.slot))
+                .ok_or(Error::OutOfRange)?;
+            let tag = *self.get_mut(MemoryLocation {
+                slot: location.slot,
+                page,
+            });
+            *byte = tag.to_le_bytes()[index % 4];
+        }
+        Ok(())
+    }
+}
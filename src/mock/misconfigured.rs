@@ -0,0 +1,71 @@
+use core::num::NonZeroU16;
+
+use crate::{
+    CopyOperation, Device, DeviceWithPrimarySlot, MemoryLocation, Slot, mock::WearTracker,
+};
+
+const PAGE_COUNT: NonZeroU16 = NonZeroU16::new(3).unwrap();
+
+/// Device of which the secondary slot is declared to be as large as the primary slot, but is actually smaller.
+pub struct MockDevice {
+    pub primary: [u8; PAGE_COUNT.get() as usize],
+    pub secondary: [u8; PAGE_COUNT.get() as usize - 1],
+    pub wear: WearTracker,
+}
+
+pub const PRIMARY: Slot = Slot(0);
+pub const SECONDARY: Slot = Slot(1);
+
+impl MockDevice {
+    pub const fn new() -> MockDevice {
+        MockDevice {
+            primary: [0x01, 0x02, 0x03],
+            secondary: [0x04, 0x05],
+            wear: WearTracker::new(),
+        }
+    }
+
+    fn get_mut(&mut self, addr: MemoryLocation) -> &mut u8 {
+        match addr.slot {
+            PRIMARY => self.primary.as_mut_slice(),
+            SECONDARY => self.secondary.as_mut_slice(),
+            _ => unimplemented!(),
+        }
+        .get_mut(addr.page.0 as usize)
+        .unwrap()
+    }
+}
+
+impl Device for MockDevice {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), crate::Error> {
+        let value = *self.get_mut(operation.from);
+        *self.get_mut(operation.to) = value;
+
+        self.wear.read(operation.from);
+        self.wear.increase(operation.to);
+
+        Ok(())
+    }
+
+    fn boot(self, _slot: Slot) -> ! {
+        unimplemented!()
+    }
+
+    fn page_count(&self) -> core::num::NonZeroU16 {
+        PAGE_COUNT
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        (match slot {
+            PRIMARY => self.primary.len(),
+            SECONDARY => self.secondary.len(),
+            _ => 0,
+        }) as u16
+    }
+}
+
+impl DeviceWithPrimarySlot for MockDevice {
+    fn get_primary(&self) -> Slot {
+        PRIMARY
+    }
+}
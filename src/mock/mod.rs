@@ -1,32 +1,151 @@
+pub mod bank_swap;
+pub mod buffered;
+pub mod erase_ahead;
+pub mod flaky;
+pub mod generic;
+pub mod misconfigured;
 pub mod multi_scratch;
+pub mod nvm;
+pub mod offset_slots;
+pub mod physical_pages;
+pub mod recording;
+pub mod single_page;
 pub mod single_scratch;
 pub mod tri_slot;
+pub mod yielding;
 
 use std::collections::BTreeMap;
 
-use crate::{MemoryLocation, Slot};
+use crate::{Device, MemoryLocation, Slot, Step, strategies::Strategy};
 
+/// Tracks how often each page has been written, and how often it has been read.
 #[derive(Debug)]
-pub struct WearTracker(BTreeMap<MemoryLocation, usize>);
+pub struct WearTracker {
+    writes: BTreeMap<MemoryLocation, usize>,
+    reads: BTreeMap<MemoryLocation, usize>,
+}
 
 impl WearTracker {
     pub const fn new() -> Self {
-        WearTracker(BTreeMap::new())
+        WearTracker {
+            writes: BTreeMap::new(),
+            reads: BTreeMap::new(),
+        }
     }
 
     pub fn increase(&mut self, addr: MemoryLocation) {
-        if let Some(wear) = self.0.get_mut(&addr) {
-            *wear += 1;
-        } else {
-            self.0.insert(addr, 1);
+        *self.writes.entry(addr).or_default() += 1;
+    }
+
+    /// Record a write of `pages` consecutive pages from `start`, for example the destination of a range copy.
+    pub fn increase_range(&mut self, start: MemoryLocation, pages: u16) {
+        for page in 0..pages {
+            self.increase(start.with_page_offset(page));
         }
     }
 
+    /// Record a read of a page, for example the source of a copy.
+    pub fn read(&mut self, addr: MemoryLocation) {
+        *self.reads.entry(addr).or_default() += 1;
+    }
+
+    /// Number of times a specific page has been written.
+    pub fn wear_at(&self, addr: MemoryLocation) -> usize {
+        self.writes.get(&addr).copied().unwrap_or(0)
+    }
+
+    /// Number of times a specific page has been read.
+    pub fn reads_at(&self, addr: MemoryLocation) -> usize {
+        self.reads.get(&addr).copied().unwrap_or(0)
+    }
+
     /// Check wear on all pages of slot for worst wear.
     pub fn check_slot(&self, slot: Slot, wear_level: usize) -> bool {
-        self.0
+        self.writes
             .iter()
             .filter(|(addr, _)| addr.slot == slot)
             .all(|(_, v)| *v <= wear_level)
     }
 }
+
+/// Moment at which power is lost whilst executing a strategy.
+#[derive(Clone, Copy, Debug)]
+pub struct PowerLoss {
+    /// Step being executed, which will not be recorded.
+    pub step: Step,
+    /// Number of operations of the step that were executed before power was lost.
+    pub operations: usize,
+}
+
+/// Execute all steps of a strategy, as the bootloader would.
+pub fn run_strategy_to_completion(device: &mut impl Device, strategy: &impl Strategy) {
+    run_strategy_with_power_loss(device, strategy, None);
+}
+
+/// Execute all steps of a strategy, losing power once at the given moment.
+///
+/// After power loss the strategy is resumed from the last recorded step, re-executing the interrupted step
+/// and any steps before it since the last safe point.
+pub fn run_strategy_with_power_loss(
+    device: &mut impl Device,
+    strategy: &impl Strategy,
+    mut power_loss: Option<PowerLoss>,
+) {
+    // Mimics the step as persisted in the bootloader state.
+    let mut recorded = Step::default();
+
+    let mut step = recorded;
+
+    while recorded < strategy.last_step() {
+        let interrupt = power_loss.take_if(|power_loss| power_loss.step == step);
+
+        for (i, operation) in strategy.plan(step).enumerate() {
+            if interrupt.is_some_and(|power_loss| power_loss.operations == i) {
+                break;
+            }
+
+            embassy_futures::block_on(async {
+                device.copy(operation).await.unwrap();
+            })
+        }
+
+        if interrupt.is_some() {
+            step = recorded;
+            continue;
+        }
+
+        step = step.next();
+        if step == strategy.last_step() || strategy.is_safe_point(step) {
+            recorded = step;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Page;
+
+    #[test]
+    fn increase_range() {
+        let start = MemoryLocation {
+            slot: Slot(1),
+            page: Page(2),
+        };
+
+        let mut ranged = WearTracker::new();
+        ranged.increase_range(start, 3);
+        ranged.increase_range(start, 1);
+
+        let mut single = WearTracker::new();
+        for page in [2, 3, 4, 2] {
+            single.increase(MemoryLocation {
+                slot: Slot(1),
+                page: Page(page),
+            });
+        }
+
+        assert_eq!(ranged.writes, single.writes);
+        assert_eq!(ranged.wear_at(start), 2);
+    }
+}
@@ -1,8 +1,8 @@
 use core::num::NonZeroU16;
 
 use crate::{
-    CopyOperation, Device, DeviceWithPrimarySlot, DeviceWithScratch, MemoryLocation, Slot,
-    mock::WearTracker,
+    CopyOperation, Device, DeviceWithErase, DeviceWithPrimarySlot, DeviceWithScratch,
+    MemoryLocation, Slot, mock::WearTracker,
 };
 
 const PAGE_COUNT: NonZeroU16 = NonZeroU16::new(10).unwrap();
@@ -51,6 +51,7 @@ impl Device for MockDevice {
         let value = *self.get_mut(operation.from);
         *self.get_mut(operation.to) = value;
 
+        self.wear.read(operation.from);
         self.wear.increase(operation.to);
 
         Ok(())
@@ -63,6 +64,15 @@ impl Device for MockDevice {
     fn page_count(&self) -> core::num::NonZeroU16 {
         PAGE_COUNT
     }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        (match slot {
+            PRIMARY => self.primary.len(),
+            SECONDARY => self.secondary.len(),
+            SCRATCH => self.scratch.len(),
+            _ => 0,
+        }) as u16
+    }
 }
 
 impl DeviceWithScratch for MockDevice {
@@ -75,6 +85,16 @@ impl DeviceWithScratch for MockDevice {
     }
 }
 
+impl DeviceWithErase for MockDevice {
+    async fn erase(&mut self, location: MemoryLocation) -> Result<(), crate::Error> {
+        *self.get_mut(location) = 0xFF;
+
+        self.wear.increase(location);
+
+        Ok(())
+    }
+}
+
 impl DeviceWithPrimarySlot for MockDevice {
     fn get_primary(&self) -> Slot {
         PRIMARY
@@ -0,0 +1,121 @@
+use std::{vec, vec::Vec};
+
+use embedded_storage_async::nor_flash::{
+    ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+
+/// RAM backed NOR flash, with the ability to inject I/O errors.
+pub struct MockFlash<const WRITE_SIZE: usize = 4, const ERASE_SIZE: usize = 1024> {
+    pub data: Vec<u8>,
+    /// Number of upcoming accesses that will fail with `MockFlashError::Io`.
+    pub failing_accesses: usize,
+    /// Number of upcoming writes that silently leave their first byte unwritten.
+    pub corrupt_writes: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockFlashError {
+    Io,
+    NotAligned,
+    OutOfBounds,
+}
+
+impl NorFlashError for MockFlashError {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            MockFlashError::Io => NorFlashErrorKind::Other,
+            MockFlashError::NotAligned => NorFlashErrorKind::NotAligned,
+            MockFlashError::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+        }
+    }
+}
+
+impl<const WRITE_SIZE: usize, const ERASE_SIZE: usize> MockFlash<WRITE_SIZE, ERASE_SIZE> {
+    /// Create an erased flash of `pages` erase pages.
+    pub fn new(pages: usize) -> Self {
+        Self {
+            data: vec![0xFF; pages * ERASE_SIZE],
+            failing_accesses: 0,
+            corrupt_writes: 0,
+        }
+    }
+
+    fn access(&mut self, offset: u32, len: usize) -> Result<(), MockFlashError> {
+        if self.failing_accesses > 0 {
+            self.failing_accesses -= 1;
+            return Err(MockFlashError::Io);
+        }
+
+        if offset as usize + len > self.data.len() {
+            return Err(MockFlashError::OutOfBounds);
+        }
+
+        Ok(())
+    }
+}
+
+impl<const WRITE_SIZE: usize, const ERASE_SIZE: usize> ErrorType
+    for MockFlash<WRITE_SIZE, ERASE_SIZE>
+{
+    type Error = MockFlashError;
+}
+
+impl<const WRITE_SIZE: usize, const ERASE_SIZE: usize> ReadNorFlash
+    for MockFlash<WRITE_SIZE, ERASE_SIZE>
+{
+    const READ_SIZE: usize = 1;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.access(offset, bytes.len())?;
+
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl<const WRITE_SIZE: usize, const ERASE_SIZE: usize> NorFlash
+    for MockFlash<WRITE_SIZE, ERASE_SIZE>
+{
+    const WRITE_SIZE: usize = WRITE_SIZE;
+    const ERASE_SIZE: usize = ERASE_SIZE;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.access(from, to.saturating_sub(from) as usize)?;
+
+        let (from, to) = (from as usize, to as usize);
+        if !from.is_multiple_of(ERASE_SIZE) || !to.is_multiple_of(ERASE_SIZE) || to < from {
+            return Err(MockFlashError::NotAligned);
+        }
+
+        self.data[from..to].fill(0xFF);
+        Ok(())
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.access(offset, bytes.len())?;
+
+        let offset = offset as usize;
+        if !offset.is_multiple_of(WRITE_SIZE) || !bytes.len().is_multiple_of(WRITE_SIZE) {
+            return Err(MockFlashError::NotAligned);
+        }
+
+        // NOR flash can only clear bits, never set them without an erase.
+        for (cell, byte) in self.data[offset..offset + bytes.len()]
+            .iter_mut()
+            .zip(bytes)
+        {
+            *cell &= byte;
+        }
+
+        if self.corrupt_writes > 0 {
+            self.corrupt_writes -= 1;
+            self.data[offset] = 0xFF;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use core::num::NonZeroU16;
+
+use crate::{
+    CopyOperation, Device, DeviceWithPrimarySlot, DeviceWithScratch, DeviceWithSlotLayout,
+    MemoryLocation, Page, Slot, SlotLayout, mock::WearTracker,
+};
+
+const PAGE_COUNT: NonZeroU16 = NonZeroU16::new(3).unwrap();
+const SCRATCH_PAGE_COUNT: NonZeroU16 = NonZeroU16::new(1).unwrap();
+
+/// Device with all slots in a single memory, each starting at a different page.
+pub struct MockDevice {
+    pub memory: [u8; 12],
+    pub wear: WearTracker,
+}
+
+pub const IMAGE_A: [u8; PAGE_COUNT.get() as usize] = [0x01, 0x02, 0x03];
+pub const IMAGE_B: [u8; PAGE_COUNT.get() as usize] = [0x04, 0x05, 0x06];
+
+pub const PRIMARY: Slot = Slot(0);
+pub const SECONDARY: Slot = Slot(1);
+pub const SCRATCH: Slot = Slot(2);
+
+const PRIMARY_LAYOUT: SlotLayout = SlotLayout {
+    base: Page(1),
+    len: PAGE_COUNT,
+};
+const SECONDARY_LAYOUT: SlotLayout = SlotLayout {
+    base: Page(6),
+    len: PAGE_COUNT,
+};
+const SCRATCH_LAYOUT: SlotLayout = SlotLayout {
+    base: Page(10),
+    len: SCRATCH_PAGE_COUNT,
+};
+
+impl MockDevice {
+    pub const fn new() -> MockDevice {
+        MockDevice {
+            memory: [
+                0xFF, 0x01, 0x02, 0x03, 0xFF, 0xFF, 0x04, 0x05, 0x06, 0xFF, 0xFF, 0xFF,
+            ],
+            wear: WearTracker::new(),
+        }
+    }
+
+    /// Contents of a slot, as seen by the strategies.
+    pub fn slot(&self, slot: Slot) -> &[u8] {
+        let layout = self.slot_layout(slot).unwrap();
+        let base = layout.base.0 as usize;
+        &self.memory[base..base + layout.len.get() as usize]
+    }
+
+    fn get_mut(&mut self, addr: MemoryLocation) -> &mut u8 {
+        let page = self
+            .slot_layout(addr.slot)
+            .and_then(|layout| layout.translate(addr.page))
+            .unwrap();
+
+        &mut self.memory[page.0 as usize]
+    }
+}
+
+impl Device for MockDevice {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), crate::Error> {
+        let value = *self.get_mut(operation.from);
+        *self.get_mut(operation.to) = value;
+
+        self.wear.read(operation.from);
+        self.wear.increase(operation.to);
+
+        Ok(())
+    }
+
+    fn boot(self, _slot: Slot) -> ! {
+        unimplemented!()
+    }
+
+    fn page_count(&self) -> core::num::NonZeroU16 {
+        PAGE_COUNT
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        self.slot_layout(slot).map_or(0, |layout| layout.len.get())
+    }
+}
+
+impl DeviceWithSlotLayout for MockDevice {
+    fn slot_layout(&self, slot: Slot) -> Option<SlotLayout> {
+        match slot {
+            PRIMARY => Some(PRIMARY_LAYOUT),
+            SECONDARY => Some(SECONDARY_LAYOUT),
+            SCRATCH => Some(SCRATCH_LAYOUT),
+            _ => None,
+        }
+    }
+}
+
+impl DeviceWithScratch for MockDevice {
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        SCRATCH_PAGE_COUNT
+    }
+
+    fn get_scratch(&self) -> Slot {
+        SCRATCH
+    }
+}
+
+impl DeviceWithPrimarySlot for MockDevice {
+    fn get_primary(&self) -> Slot {
+        PRIMARY
+    }
+}
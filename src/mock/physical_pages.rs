@@ -0,0 +1,99 @@
+use core::num::{NonZeroU16, NonZeroU32};
+use std::collections::BTreeMap;
+
+use crate::{
+    CopyOperation, Device, DeviceWithPhysicalPages, DeviceWithPrimarySlot, DeviceWithScratch,
+    MemoryLocation, Page, Slot, mock::WearTracker,
+};
+
+/// Device decorator of which the slots reside in memories with differing physical page sizes.
+pub struct PhysicalPagesDevice<D> {
+    pub inner: D,
+    pub page_size: NonZeroU32,
+    pub physical_page_sizes: BTreeMap<Slot, NonZeroU32>,
+    /// Erases of every physical page, of which the page of each location is a physical page index.
+    pub physical_wear: WearTracker,
+}
+
+impl<D> PhysicalPagesDevice<D> {
+    pub fn new(inner: D, page_size: u32, physical_page_sizes: &[(Slot, u32)]) -> Self {
+        Self {
+            inner,
+            page_size: NonZeroU32::new(page_size).unwrap(),
+            physical_page_sizes: physical_page_sizes
+                .iter()
+                .map(|(slot, size)| (*slot, NonZeroU32::new(*size).unwrap()))
+                .collect(),
+            physical_wear: WearTracker::new(),
+        }
+    }
+}
+
+impl<D: Device> Device for PhysicalPagesDevice<D> {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), crate::Error> {
+        self.inner.copy(operation).await?;
+
+        // Writing a page erases each of the physical pages it spans.
+        let erases = self.erases_per_page(operation.to.slot) as u16;
+        let start = MemoryLocation {
+            slot: operation.to.slot,
+            page: Page(operation.to.page.0 * erases),
+        };
+        self.physical_wear.increase_range(start, erases);
+        Ok(())
+    }
+
+    fn boot(self, slot: Slot) -> ! {
+        self.inner.boot(slot)
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        self.inner.page_count()
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        self.inner.capacity_pages(slot)
+    }
+
+    fn slot_count(&self) -> u8 {
+        self.inner.slot_count()
+    }
+
+    fn is_executable(&self, slot: Slot) -> bool {
+        self.inner.is_executable(slot)
+    }
+}
+
+impl<D: Device> DeviceWithPhysicalPages for PhysicalPagesDevice<D> {
+    fn page_size(&self) -> NonZeroU32 {
+        self.page_size
+    }
+
+    fn physical_page_size(&self, slot: Slot) -> NonZeroU32 {
+        self.physical_page_sizes[&slot]
+    }
+}
+
+impl<D: DeviceWithScratch> DeviceWithScratch for PhysicalPagesDevice<D> {
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        self.inner.scratch_page_count()
+    }
+
+    fn get_scratch(&self) -> Slot {
+        self.inner.get_scratch()
+    }
+
+    fn scratch_base(&self) -> Page {
+        self.inner.scratch_base()
+    }
+
+    fn scratch_is_durable(&self) -> bool {
+        self.inner.scratch_is_durable()
+    }
+}
+
+impl<D: DeviceWithPrimarySlot> DeviceWithPrimarySlot for PhysicalPagesDevice<D> {
+    fn get_primary(&self) -> Slot {
+        self.inner.get_primary()
+    }
+}
@@ -0,0 +1,80 @@
+use core::num::NonZeroU16;
+use std::vec::Vec;
+
+use crate::{
+    CopyOperation, Device, DeviceWithPrimarySlot, DeviceWithScratch, Error, MemoryLocation, Page,
+    Slot,
+    mock::generic::{PRIMARY, SCRATCH, SECONDARY},
+};
+
+/// Device that only records the operations it is asked to execute, without holding any data.
+///
+/// Uses the same slots as [`GenericMock`](super::generic::GenericMock), such that plans can be compared against it.
+pub struct RecordingDevice {
+    pub page_count: NonZeroU16,
+    pub scratch_page_count: NonZeroU16,
+    pub operations: Vec<CopyOperation>,
+}
+
+impl RecordingDevice {
+    pub fn new(page_count: u16, scratch_page_count: u16) -> Self {
+        Self {
+            page_count: NonZeroU16::new(page_count).unwrap(),
+            scratch_page_count: NonZeroU16::new(scratch_page_count).unwrap(),
+            operations: Vec::new(),
+        }
+    }
+}
+
+impl Device for RecordingDevice {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), Error> {
+        self.operations.push(operation);
+        Ok(())
+    }
+
+    fn boot(self, _slot: Slot) -> ! {
+        unimplemented!()
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        self.page_count
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        match slot {
+            PRIMARY | SECONDARY => self.page_count.get(),
+            SCRATCH => self.scratch_page_count.get(),
+            _ => 0,
+        }
+    }
+}
+
+impl DeviceWithScratch for RecordingDevice {
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        self.scratch_page_count
+    }
+
+    fn get_scratch(&self) -> Slot {
+        SCRATCH
+    }
+}
+
+impl DeviceWithPrimarySlot for RecordingDevice {
+    fn get_primary(&self) -> Slot {
+        PRIMARY
+    }
+}
+
+/// Operation copying page `from` of `slot_from` to page `to` of `slot_to`, for writing down expected operations.
+pub const fn operation(slot_from: Slot, from: u16, slot_to: Slot, to: u16) -> CopyOperation {
+    CopyOperation {
+        from: MemoryLocation {
+            slot: slot_from,
+            page: Page(from),
+        },
+        to: MemoryLocation {
+            slot: slot_to,
+            page: Page(to),
+        },
+    }
+}
@@ -0,0 +1,100 @@
+use core::num::NonZeroU16;
+
+use crate::{
+    CopyOperation, Device, DeviceWithPrimarySlot, DeviceWithScratch, MemoryLocation, Slot,
+    mock::WearTracker,
+};
+
+/// Images of a single page, the smallest image a strategy has to handle.
+const PAGE_COUNT: NonZeroU16 = NonZeroU16::new(1).unwrap();
+const SCRATCH_PAGE_COUNT: NonZeroU16 = NonZeroU16::new(1).unwrap();
+
+pub struct MockDevice {
+    pub primary: [u8; PAGE_COUNT.get() as usize],
+    pub alpha: [u8; PAGE_COUNT.get() as usize],
+    pub beta: [u8; PAGE_COUNT.get() as usize],
+    pub scratch: [u8; SCRATCH_PAGE_COUNT.get() as usize],
+    pub wear: WearTracker,
+}
+
+pub const IMAGE_A: [u8; PAGE_COUNT.get() as usize] = [0x01];
+pub const IMAGE_B: [u8; PAGE_COUNT.get() as usize] = [0x04];
+
+pub const PRIMARY: Slot = Slot(0);
+pub const ALPHA: Slot = Slot(1);
+pub const BETA: Slot = Slot(2);
+pub const SCRATCH: Slot = Slot(3);
+
+impl MockDevice {
+    pub const fn new() -> MockDevice {
+        MockDevice {
+            primary: IMAGE_A,
+            alpha: IMAGE_A,
+            beta: IMAGE_B,
+            scratch: [0xff],
+            wear: WearTracker::new(),
+        }
+    }
+
+    fn get_mut(&mut self, addr: MemoryLocation) -> &mut u8 {
+        match addr.slot {
+            PRIMARY => self.primary.as_mut_slice(),
+            ALPHA => self.alpha.as_mut_slice(),
+            BETA => self.beta.as_mut_slice(),
+            SCRATCH => self.scratch.as_mut_slice(),
+            _ => unimplemented!(),
+        }
+        .get_mut(addr.page.0 as usize)
+        .unwrap()
+    }
+}
+
+impl Device for MockDevice {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), crate::Error> {
+        let value = *self.get_mut(operation.from);
+        *self.get_mut(operation.to) = value;
+
+        self.wear.read(operation.from);
+        self.wear.increase(operation.to);
+
+        Ok(())
+    }
+
+    fn boot(self, _slot: Slot) -> ! {
+        unimplemented!()
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        PAGE_COUNT
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        (match slot {
+            PRIMARY => self.primary.len(),
+            ALPHA => self.alpha.len(),
+            BETA => self.beta.len(),
+            SCRATCH => self.scratch.len(),
+            _ => 0,
+        }) as u16
+    }
+
+    fn is_executable(&self, slot: Slot) -> bool {
+        slot == PRIMARY
+    }
+}
+
+impl DeviceWithScratch for MockDevice {
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        SCRATCH_PAGE_COUNT
+    }
+
+    fn get_scratch(&self) -> Slot {
+        SCRATCH
+    }
+}
+
+impl DeviceWithPrimarySlot for MockDevice {
+    fn get_primary(&self) -> Slot {
+        PRIMARY
+    }
+}
@@ -1,7 +1,8 @@
 use core::num::NonZeroU16;
 
 use crate::{
-    CopyOperation, Device, DeviceWithPrimarySlot, MemoryLocation, Slot, mock::WearTracker,
+    CopyOperation, Device, DeviceWithDigest, DeviceWithErase, DeviceWithExplicitErase,
+    DeviceWithPrimarySlot, MemoryLocation, Slot, mock::WearTracker,
 };
 
 const PAGE_COUNT: NonZeroU16 = NonZeroU16::new(3).unwrap();
@@ -47,6 +48,7 @@ impl Device for MockDevice {
         let value = *self.get_mut(operation.from);
         *self.get_mut(operation.to) = value;
 
+        self.wear.read(operation.from);
         self.wear.increase(operation.to);
 
         Ok(())
@@ -59,6 +61,44 @@ impl Device for MockDevice {
     fn page_count(&self) -> core::num::NonZeroU16 {
         PAGE_COUNT
     }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        (match slot {
+            PRIMARY => self.primary.len(),
+            ALPHA => self.alpha.len(),
+            BETA => self.beta.len(),
+            _ => 0,
+        }) as u16
+    }
+
+    fn is_executable(&self, slot: Slot) -> bool {
+        slot == PRIMARY
+    }
+}
+
+impl DeviceWithErase for MockDevice {
+    async fn erase(&mut self, location: MemoryLocation) -> Result<(), crate::Error> {
+        *self.get_mut(location) = 0xFF;
+
+        self.wear.increase(location);
+
+        Ok(())
+    }
+}
+
+impl DeviceWithExplicitErase for MockDevice {
+    async fn copy_erased(&mut self, operation: CopyOperation) -> Result<(), crate::Error> {
+        assert_eq!(
+            *self.get_mut(operation.to),
+            0xFF,
+            "copying onto a page that is not erased"
+        );
+        *self.get_mut(operation.to) = *self.get_mut(operation.from);
+
+        self.wear.read(operation.from);
+
+        Ok(())
+    }
 }
 
 impl DeviceWithPrimarySlot for MockDevice {
@@ -66,3 +106,17 @@ impl DeviceWithPrimarySlot for MockDevice {
         PRIMARY
     }
 }
+
+/// Digest of a slot being its contents, as every image fits in a few bytes.
+impl DeviceWithDigest for MockDevice {
+    type Digest = [u8; PAGE_COUNT.get() as usize];
+
+    async fn digest(&mut self, slot: Slot) -> Result<Self::Digest, crate::Error> {
+        match slot {
+            PRIMARY => Ok(self.primary),
+            ALPHA => Ok(self.alpha),
+            BETA => Ok(self.beta),
+            _ => Err(crate::Error::OutOfRange),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use core::num::NonZeroU16;
+
+use crate::{CopyOperation, Device, DeviceWithPrimarySlot, DeviceWithScratch, Page, Slot};
+
+/// Device decorator that yields to the executor around every copy, giving a future driving it points to be dropped at.
+pub struct YieldingDevice<D> {
+    pub inner: D,
+}
+
+impl<D> YieldingDevice<D> {
+    pub fn new(inner: D) -> Self {
+        Self { inner }
+    }
+}
+
+impl<D: Device> Device for YieldingDevice<D> {
+    async fn copy(&mut self, operation: CopyOperation) -> Result<(), crate::Error> {
+        embassy_futures::yield_now().await;
+        self.inner.copy(operation).await?;
+        embassy_futures::yield_now().await;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), crate::Error> {
+        embassy_futures::yield_now().await;
+        self.inner.flush().await
+    }
+
+    async fn unlock(&mut self) -> Result<(), crate::Error> {
+        self.inner.unlock().await
+    }
+
+    async fn lock(&mut self) -> Result<(), crate::Error> {
+        self.inner.lock().await
+    }
+
+    async fn shutdown(&mut self) -> Result<(), crate::Error> {
+        self.inner.shutdown().await
+    }
+
+    fn boot(self, slot: Slot) -> ! {
+        self.inner.boot(slot)
+    }
+
+    fn page_count(&self) -> NonZeroU16 {
+        self.inner.page_count()
+    }
+
+    fn capacity_pages(&self, slot: Slot) -> u16 {
+        self.inner.capacity_pages(slot)
+    }
+
+    fn slot_count(&self) -> u8 {
+        self.inner.slot_count()
+    }
+
+    fn is_executable(&self, slot: Slot) -> bool {
+        self.inner.is_executable(slot)
+    }
+}
+
+impl<D: DeviceWithScratch> DeviceWithScratch for YieldingDevice<D> {
+    fn scratch_page_count(&self) -> NonZeroU16 {
+        self.inner.scratch_page_count()
+    }
+
+    fn get_scratch(&self) -> Slot {
+        self.inner.get_scratch()
+    }
+
+    fn scratch_base(&self) -> Page {
+        self.inner.scratch_base()
+    }
+
+    fn scratch_is_durable(&self) -> bool {
+        self.inner.scratch_is_durable()
+    }
+}
+
+impl<D: DeviceWithPrimarySlot> DeviceWithPrimarySlot for YieldingDevice<D> {
+    fn get_primary(&self) -> Slot {
+        self.inner.get_primary()
+    }
+}
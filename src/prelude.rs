@@ -0,0 +1,39 @@
+//! Re-exports of the traits and types needed by most bootloaders, to be imported with `use bootlick::prelude::*`.
+//!
+//! Strategies themselves are found in [`strategies::prelude`](crate::strategies::prelude).
+//!
+//! ```
+//! use bootlick::prelude::*;
+//! use bootlick::strategies::prelude::*;
+//! # use core::num::NonZeroU16;
+//! # struct MyDevice;
+//! # impl Device for MyDevice {
+//! #     async fn copy(&mut self, _operation: CopyOperation) -> Result<(), Error> { Ok(()) }
+//! #     fn boot(self, _slot: Slot) -> ! { loop {} }
+//! #     fn page_count(&self) -> NonZeroU16 { NonZeroU16::new(4).unwrap() }
+//! #     fn capacity_pages(&self, _slot: Slot) -> u16 { 4 }
+//! # }
+//! # impl DeviceWithScratch for MyDevice {
+//! #     fn scratch_page_count(&self) -> NonZeroU16 { NonZeroU16::MIN }
+//! #     fn get_scratch(&self) -> Slot { Slot(2) }
+//! # }
+//! # impl DeviceWithPrimarySlot for MyDevice {
+//! #     fn get_primary(&self) -> Slot { Slot(0) }
+//! # }
+//!
+//! let device = MyDevice;
+//! let strategy = SwapScootch::from_request(
+//!     &device,
+//!     SwapScootchRequest {
+//!         slot_secondary: Slot(1),
+//!     },
+//! );
+//! assert_eq!(strategy.total_operations(), 12);
+//! ```
+
+pub use crate::{
+    CopyOperation, Device, DeviceWithErase, DeviceWithPrimarySlot, DeviceWithRead,
+    DeviceWithScratch, Error, MemoryLocation, Page, Slot, Step,
+    state::{State, StateStorage},
+    strategies::{FromRequest, Strategy},
+};
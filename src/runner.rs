@@ -0,0 +1,1406 @@
+//! Execution of strategies on a device, persisting the progress in the bootloader state.
+
+use core::convert::Infallible;
+
+use crate::{
+    CopyOperation, Device, DeviceWithEraseAhead, DeviceWithExplicitErase, EraseOperation, Error,
+    MemoryLocation, Operation, Slot, Step,
+    state::{State, StateStorage},
+    strategies::{FromRequest, Strategy, StrategyError},
+};
+
+/// Failure whilst running a strategy.
+#[derive(Debug, PartialEq)]
+pub enum RunnerError<E> {
+    /// The device failed to execute an operation.
+    Device(Error),
+    /// The state could not be persisted.
+    State(E),
+    /// The strategy does not match the device or the state.
+    Strategy(StrategyError),
+}
+
+impl<E> From<Error> for RunnerError<E> {
+    fn from(error: Error) -> Self {
+        RunnerError::Device(error)
+    }
+}
+
+impl<E> From<StrategyError> for RunnerError<E> {
+    fn from(error: StrategyError) -> Self {
+        RunnerError::Strategy(error)
+    }
+}
+
+/// Work performed by a single [`StrategyRunner::run`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RunStats {
+    /// Number of executed copy operations, excluding retries and operations skipped by the completion ledger.
+    pub operations: u32,
+    /// Number of executed and recorded steps.
+    pub steps: u32,
+    /// Ticks of the clock elapsed during the run, if a clock was set using [`StrategyRunner::with_clock`].
+    pub elapsed: Option<u64>,
+}
+
+/// Limit on the page erases each slot may endure over the lifetime of the device, see [`StrategyRunner::with_wear_budget`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WearBudget {
+    /// Maximum number of page erases of a single slot.
+    pub max_erases: u32,
+}
+
+/// Progress of the request in `state` as `(operations_done, operations_total)`, or `None` if there is no request.
+///
+/// `total` is the [number of operations](Strategy::total_operations) of the strategy as requested. Whilst reverting,
+/// `strategy` is the reverted strategy, of which the number of operations may differ, and progress counts down from
+/// `total` in proportion to the reversal, as the operations of the original request are being undone.
+pub fn progress<S>(
+    state: &State<S>,
+    strategy: &impl Strategy,
+    total: usize,
+) -> Option<(usize, usize)> {
+    let request = state.request.as_ref()?;
+    let done = strategy.operations_before(request.step);
+
+    Some(if request.revert {
+        // Widened, as the product exceeds `usize` on 32-bit targets for large slots.
+        let remaining = strategy.total_operations().saturating_sub(done) as u64;
+        let undone = (total as u64 * remaining)
+            .checked_div(strategy.total_operations() as u64)
+            .unwrap_or(0);
+        (undone as usize, total)
+    } else {
+        debug_assert_eq!(
+            total,
+            strategy.total_operations(),
+            "total does not match the requested strategy"
+        );
+        (done, total)
+    })
+}
+
+/// Hooks called by the [`StrategyRunner`] at every milestone, for example for field diagnostics.
+///
+/// All methods default to doing nothing, such that an implementation only needs to handle the events it is interested in.
+pub trait RunnerObserver {
+    /// Execution of a step is about to start.
+    fn step_started(&mut self, _step: Step) {}
+
+    /// A copy operation of the current step has been executed.
+    fn copy_done(&mut self, _operation: &CopyOperation) {}
+
+    /// Progress has been stored, with `step` being the next step to execute.
+    fn step_recorded(&mut self, _step: Step) {}
+
+    /// The device is about to boot the image in `slot`.
+    fn boot(&mut self, _slot: Slot) {}
+
+    /// The request has failed, and the runner switched to reverting it.
+    fn revert_triggered(&mut self) {}
+}
+
+impl<O: RunnerObserver + ?Sized> RunnerObserver for &mut O {
+    fn step_started(&mut self, step: Step) {
+        (**self).step_started(step)
+    }
+
+    fn copy_done(&mut self, operation: &CopyOperation) {
+        (**self).copy_done(operation)
+    }
+
+    fn step_recorded(&mut self, step: Step) {
+        (**self).step_recorded(step)
+    }
+
+    fn boot(&mut self, slot: Slot) {
+        (**self).boot(slot)
+    }
+
+    fn revert_triggered(&mut self) {
+        (**self).revert_triggered()
+    }
+}
+
+/// Observer that ignores all events.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct NoopObserver;
+
+impl RunnerObserver for NoopObserver {}
+
+/// Observer that logs all events using `defmt`.
+#[cfg(feature = "defmt")]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct DefmtObserver;
+
+#[cfg(feature = "defmt")]
+impl RunnerObserver for DefmtObserver {
+    fn step_started(&mut self, step: Step) {
+        defmt::debug!("step {} started", step.as_u32());
+    }
+
+    fn copy_done(&mut self, operation: &CopyOperation) {
+        defmt::trace!(
+            "copied {}:{} to {}:{}",
+            operation.from.slot.0,
+            operation.from.page.0,
+            operation.to.slot.0,
+            operation.to.page.0
+        );
+    }
+
+    fn step_recorded(&mut self, step: Step) {
+        defmt::debug!("recorded step {}", step.as_u32());
+    }
+
+    fn boot(&mut self, slot: Slot) {
+        defmt::info!("booting slot {}", slot.0);
+    }
+
+    fn revert_triggered(&mut self) {
+        defmt::warn!("reverting request");
+    }
+}
+
+/// How the runner erases the destinations of copies: by the copy itself, ahead of time, or as separate operations.
+trait EraseMode<D> {
+    /// Whether the destination of the next copy is erased ahead of time.
+    const ENABLED: bool;
+
+    async fn begin(device: &mut D, location: MemoryLocation) -> Result<(), Error>;
+    async fn finish(device: &mut D) -> Result<(), Error>;
+
+    /// Execute an [`Operation::Erase`], which the copy following it does by itself unless erasing explicitly.
+    async fn erase(_device: &mut D, _operation: EraseOperation) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn copy(device: &mut D, operation: CopyOperation) -> Result<(), Error>;
+}
+
+struct NoEraseAhead;
+
+impl<D: Device> EraseMode<D> for NoEraseAhead {
+    const ENABLED: bool = false;
+
+    async fn begin(_device: &mut D, _location: MemoryLocation) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn finish(_device: &mut D) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn copy(device: &mut D, operation: CopyOperation) -> Result<(), Error> {
+        device.copy(operation).await
+    }
+}
+
+struct WithEraseAhead;
+
+impl<D: DeviceWithEraseAhead> EraseMode<D> for WithEraseAhead {
+    const ENABLED: bool = true;
+
+    async fn begin(device: &mut D, location: MemoryLocation) -> Result<(), Error> {
+        device.begin_erase(location).await
+    }
+
+    async fn finish(device: &mut D) -> Result<(), Error> {
+        device.finish_erase().await
+    }
+
+    async fn copy(device: &mut D, operation: CopyOperation) -> Result<(), Error> {
+        device.copy(operation).await
+    }
+}
+
+struct ExplicitErase;
+
+impl<D: DeviceWithExplicitErase> EraseMode<D> for ExplicitErase {
+    const ENABLED: bool = false;
+
+    async fn begin(_device: &mut D, _location: MemoryLocation) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn finish(_device: &mut D) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn erase(device: &mut D, operation: EraseOperation) -> Result<(), Error> {
+        device.erase(operation.location).await
+    }
+
+    async fn copy(device: &mut D, operation: CopyOperation) -> Result<(), Error> {
+        device.copy_erased(operation).await
+    }
+}
+
+/// Destination of the operation following `current` at `index` of `step`, if it can be erased whilst executing `current`.
+///
+/// A step interrupted by power loss is executed again, hence none of its sources may be erased ahead.
+fn erasable_next<S>(
+    state: &State<S>,
+    strategy: &impl Strategy,
+    step: Step,
+    index: usize,
+    current: &CopyOperation,
+) -> Option<MemoryLocation> {
+    let next = match strategy.plan(step).nth(index + 1) {
+        // An operation recorded as completed is not executed again, and thus must be left intact.
+        Some(_) if state.is_operation_completed(index + 1) => return None,
+        Some(next) => next,
+        None if step.next() < strategy.last_step() => strategy.plan(step.next()).next()?,
+        None => return None,
+    };
+
+    let needed = next.is_in_place()
+        || next.to == current.to
+        || strategy
+            .plan(step)
+            .any(|operation| operation.from == next.to);
+
+    (!needed).then_some(next.to)
+}
+
+/// Drives a strategy on a device, recording the progress after every step.
+pub struct StrategyRunner<D, SS, W = fn(), O = NoopObserver> {
+    device: D,
+    storage: SS,
+    feed_watchdog: W,
+    observer: O,
+    copy_retries: u8,
+    record_operations: bool,
+    clock: Option<fn() -> u64>,
+    wear_budget: Option<WearBudget>,
+    erases: fn(Slot) -> u32,
+    critical: bool,
+    /// Whether the device was unlocked by a run that was dropped before locking it again.
+    unlocked: bool,
+}
+
+impl<D: Device, SS> StrategyRunner<D, SS> {
+    pub fn new(device: D, storage: SS) -> Self {
+        Self {
+            device,
+            storage,
+            feed_watchdog: || {},
+            observer: NoopObserver,
+            copy_retries: 0,
+            record_operations: false,
+            clock: None,
+            wear_budget: None,
+            erases: |_| 0,
+            critical: false,
+            unlocked: false,
+        }
+    }
+}
+
+impl<D: Device, SS, W: FnMut(), O: RunnerObserver> StrategyRunner<D, SS, W, O> {
+    /// Call `feed_watchdog` after every executed copy operation, to keep a watchdog from resetting the device mid-strategy.
+    ///
+    /// The callback runs in the hot copy loop, and thus should be cheap.
+    pub fn with_watchdog<F: FnMut()>(self, feed_watchdog: F) -> StrategyRunner<D, SS, F, O> {
+        StrategyRunner {
+            device: self.device,
+            storage: self.storage,
+            feed_watchdog,
+            observer: self.observer,
+            copy_retries: self.copy_retries,
+            record_operations: self.record_operations,
+            clock: self.clock,
+            wear_budget: self.wear_budget,
+            erases: self.erases,
+            critical: self.critical,
+            unlocked: self.unlocked,
+        }
+    }
+
+    /// Notify `observer` of every transition of the runner.
+    pub fn with_observer<P: RunnerObserver>(self, observer: P) -> StrategyRunner<D, SS, W, P> {
+        StrategyRunner {
+            device: self.device,
+            storage: self.storage,
+            feed_watchdog: self.feed_watchdog,
+            observer,
+            copy_retries: self.copy_retries,
+            record_operations: self.record_operations,
+            clock: self.clock,
+            wear_budget: self.wear_budget,
+            erases: self.erases,
+            critical: self.critical,
+            unlocked: self.unlocked,
+        }
+    }
+
+    /// Retry a failed copy operation up to `retries` times before giving up, for memories with transient failures.
+    ///
+    /// Retrying is safe, as copy operations leave their source intact and can thus be repeated.
+    /// Once the retries are exhausted the error is returned, for example to [`revert`](Self::revert) the request.
+    pub fn with_copy_retries(mut self, retries: u8) -> Self {
+        self.copy_retries = retries;
+        self
+    }
+
+    /// Store the state after every copy operation, recording it in the [`CompletionLedger`](crate::state::CompletionLedger) of the request.
+    ///
+    /// A step interrupted halfway then only executes its unfinished operations when resumed, at the cost of a state store for every operation.
+    /// Worthwhile if copies are far more expensive than storing the state, for example for steps of many pages.
+    pub fn with_completion_ledger(mut self, record_operations: bool) -> Self {
+        self.record_operations = record_operations;
+        self
+    }
+
+    /// Measure the duration of every run using `clock`, returning a monotonic timestamp in ticks of the integrator's choosing.
+    pub fn with_clock(mut self, clock: fn() -> u64) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Refuse to start requests that would wear a slot beyond `budget`, as checked by [`check_wear`](Self::check_wear).
+    ///
+    /// `erases` yields the page erases a slot has endured so far, as persisted by the integrator. A refused request fails
+    /// with [`Error::WearExceeded`] before anything is copied; requests already in progress and reversals always run.
+    pub fn with_wear_budget(mut self, budget: WearBudget, erases: fn(Slot) -> u32) -> Self {
+        self.wear_budget = Some(budget);
+        self.erases = erases;
+        self
+    }
+
+    /// Start requests regardless of the [wear budget](Self::with_wear_budget) if `critical`, for example for a security fix.
+    pub fn with_critical_update(mut self, critical: bool) -> Self {
+        self.critical = critical;
+        self
+    }
+
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    pub fn device_mut(&mut self) -> &mut D {
+        &mut self.device
+    }
+
+    pub fn storage(&self) -> &SS {
+        &self.storage
+    }
+
+    pub fn storage_mut(&mut self) -> &mut SS {
+        &mut self.storage
+    }
+
+    /// Construct the strategy for `request`, which only compiles if the device has the capabilities the strategy requires.
+    pub fn strategy<T: FromRequest<D>>(&self, request: T::Request) -> T {
+        T::from_request(&self.device, request)
+    }
+
+    /// Execute the remaining steps of the request in `state`, up to the last step of `strategy`.
+    ///
+    /// Fails with [`RunnerError::Strategy`] before anything is copied if the strategy does not
+    /// [fit the device](Strategy::verify_layout), for example because of a misconfigured scratch base.
+    ///
+    /// The state is stored after every step that is a [safe point](Strategy::is_safe_point), such that an interrupted run
+    /// resumes at the last safe point before the step that was interrupted. Before the last step is recorded the device is flushed, guaranteeing that all copies are committed
+    /// before the state indicates that the image can be booted.
+    ///
+    /// The future is cancellation safe: dropping it, for example on a timeout, never leaves a step recorded of which not
+    /// all copies have completed. Resuming from either the stored state or `state` as left behind executes the interrupted step again.
+    /// Dropping the future does skip [`lock`](Device::lock), leaving the memory unlocked until the next run on this runner,
+    /// which does not unlock the device again and locks it when done.
+    ///
+    /// Returns the work performed, for example for telemetry.
+    pub async fn run<S, T: Strategy>(
+        &mut self,
+        state: &mut State<S>,
+        strategy: &T,
+    ) -> Result<RunStats, RunnerError<SS::Error>>
+    where
+        SS: StateStorage<S>,
+    {
+        self.run_inner::<S, T, NoEraseAhead>(state, strategy).await
+    }
+
+    /// Like [`run`](Self::run), but erasing the destination of the next copy whilst executing the current copy.
+    ///
+    /// Pages are only erased ahead if they are not needed when the current step is executed again after power loss,
+    /// thus the strategy remains power-safe.
+    pub async fn run_with_erase_ahead<S, T: Strategy>(
+        &mut self,
+        state: &mut State<S>,
+        strategy: &T,
+    ) -> Result<RunStats, RunnerError<SS::Error>>
+    where
+        SS: StateStorage<S>,
+        D: DeviceWithEraseAhead,
+    {
+        self.run_inner::<S, T, WithEraseAhead>(state, strategy)
+            .await
+    }
+
+    /// Like [`run`](Self::run), but executing the [operations](Strategy::plan_ops) of every step, erasing each
+    /// destination with [`erase`](crate::DeviceWithErase::erase) before copying onto it with
+    /// [`copy_erased`](DeviceWithExplicitErase::copy_erased).
+    ///
+    /// Meant for devices that erase and write through separate interfaces.
+    pub async fn run_with_explicit_erase<S, T: Strategy>(
+        &mut self,
+        state: &mut State<S>,
+        strategy: &T,
+    ) -> Result<RunStats, RunnerError<SS::Error>>
+    where
+        SS: StateStorage<S>,
+        D: DeviceWithExplicitErase,
+    {
+        self.run_inner::<S, T, ExplicitErase>(state, strategy).await
+    }
+
+    async fn run_inner<S, T: Strategy, E: EraseMode<D>>(
+        &mut self,
+        state: &mut State<S>,
+        strategy: &T,
+    ) -> Result<RunStats, RunnerError<SS::Error>>
+    where
+        SS: StateStorage<S>,
+    {
+        let start = self.clock.map(|clock| clock());
+        let mut stats = RunStats::default();
+
+        if let Err(error) = self.check_request(state, strategy) {
+            // A cancelled run left the device unlocked, which must not be carried into the booted image.
+            if self.unlocked {
+                let _ = self.device.lock().await;
+                self.unlocked = false;
+            }
+            return Err(error);
+        }
+
+        if !self.unlocked {
+            self.device.unlock().await.map_err(RunnerError::Device)?;
+            self.unlocked = true;
+        }
+        let result = self.run_steps::<S, T, E>(state, strategy, &mut stats).await;
+        // Relock even if the strategy failed, but report the failure of the strategy first.
+        let locked = self.device.lock().await;
+        self.unlocked = false;
+        result?;
+        locked.map_err(RunnerError::Device)?;
+
+        stats.elapsed = self
+            .clock
+            .zip(start)
+            .map(|(clock, start)| clock().wrapping_sub(start));
+        Ok(stats)
+    }
+
+    /// Refuse to run `strategy` if it does not fit the device, or if it is a fresh request exceeding the wear budget.
+    fn check_request<S, T: Strategy>(
+        &self,
+        state: &State<S>,
+        strategy: &T,
+    ) -> Result<(), RunnerError<SS::Error>>
+    where
+        SS: StateStorage<S>,
+    {
+        strategy.verify_layout(&self.device)?;
+
+        let fresh = state.request.as_ref().is_some_and(|request| {
+            !request.revert
+                && request.step == Step::default()
+                && request.completed == Default::default()
+        });
+        if fresh {
+            self.check_wear(strategy).map_err(RunnerError::Device)?;
+        }
+
+        Ok(())
+    }
+
+    async fn run_steps<S, T: Strategy, E: EraseMode<D>>(
+        &mut self,
+        state: &mut State<S>,
+        strategy: &T,
+        stats: &mut RunStats,
+    ) -> Result<(), RunnerError<SS::Error>>
+    where
+        SS: StateStorage<S>,
+    {
+        let last_step = strategy.last_step();
+
+        while let Some(step) = state.request.as_ref().map(|request| request.step) {
+            strategy.check_step(step)?;
+            if step >= last_step {
+                break;
+            }
+
+            self.observer.step_started(step);
+
+            // Number of copies so far within the step, by which completed operations are recorded.
+            let mut copies = 0;
+            for operation in strategy.plan_ops(step) {
+                let operation = match operation {
+                    // An erase belongs to the copy following it, and is skipped along with it.
+                    Operation::Erase(erase) => {
+                        if !state.is_operation_completed(copies) {
+                            E::erase(&mut self.device, erase)
+                                .await
+                                .map_err(RunnerError::Device)?;
+                        }
+                        continue;
+                    }
+                    Operation::Copy(operation) => operation,
+                };
+                let index = copies;
+                copies += 1;
+
+                if state.is_operation_completed(index) {
+                    continue;
+                }
+
+                if E::ENABLED {
+                    // The destination of this operation might still be being erased.
+                    E::finish(&mut self.device)
+                        .await
+                        .map_err(RunnerError::Device)?;
+
+                    if let Some(location) = erasable_next(state, strategy, step, index, &operation)
+                    {
+                        E::begin(&mut self.device, location)
+                            .await
+                            .map_err(RunnerError::Device)?;
+                    }
+                }
+
+                self.copy::<E>(operation)
+                    .await
+                    .map_err(RunnerError::Device)?;
+                stats.operations += 1;
+                self.observer.copy_done(&operation);
+                (self.feed_watchdog)();
+
+                if self.record_operations && state.complete_operation(index) {
+                    self.storage
+                        .store(state)
+                        .await
+                        .map_err(RunnerError::State)?;
+                }
+            }
+
+            let next = step.next();
+            if next == last_step {
+                self.device.flush().await.map_err(RunnerError::Device)?;
+            }
+
+            state.advance_step();
+            stats.steps += 1;
+
+            // Operations are recorded per step, which requires every step to be stored.
+            if next == last_step || self.record_operations || strategy.is_safe_point(next) {
+                self.storage
+                    .store(state)
+                    .await
+                    .map_err(RunnerError::State)?;
+                self.observer.step_recorded(next);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn copy<E: EraseMode<D>>(&mut self, operation: CopyOperation) -> Result<(), Error> {
+        debug_assert!(
+            !operation.is_in_place(),
+            "strategy planned to copy a page onto itself"
+        );
+
+        let mut retries = self.copy_retries;
+        loop {
+            match E::copy(&mut self.device, operation).await {
+                Err(_) if retries > 0 => retries -= 1,
+                result => return result,
+            }
+        }
+    }
+
+    /// Check that executing `strategy` keeps every slot within the [wear budget](Self::with_wear_budget), failing with [`Error::WearExceeded`] otherwise.
+    ///
+    /// Every page copied into a slot adds an erase to those it has endured so far. Always succeeds for a
+    /// [critical update](Self::with_critical_update). Called by [`run`](Self::run) before the first step of a request.
+    pub fn check_wear(&self, strategy: &impl Strategy) -> Result<(), Error> {
+        let Some(budget) = self.wear_budget.filter(|_| !self.critical) else {
+            return Ok(());
+        };
+
+        for slot in (0..self.device.slot_count()).map(Slot) {
+            let planned = strategy
+                .plan_all()
+                .filter(|(_, operation)| operation.to.slot == slot)
+                .count() as u32;
+            if (self.erases)(slot).saturating_add(planned) > budget.max_erases {
+                return Err(Error::WearExceeded);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Mark the request in `state` as failed, and store it such that the strategy is reverted from its first step.
+    ///
+    /// Returns the reverted strategy to [`run`](Self::run), or `None` without touching the state if the strategy can not be reverted.
+    pub async fn revert<S, T: Strategy>(
+        &mut self,
+        state: &mut State<S>,
+        strategy: T,
+    ) -> Result<Option<T>, RunnerError<SS::Error>>
+    where
+        SS: StateStorage<S>,
+    {
+        if state.request.is_none() {
+            return Ok(None);
+        }
+        let Some(strategy) = strategy.revert() else {
+            return Ok(None);
+        };
+
+        state.begin_revert();
+        self.observer.revert_triggered();
+
+        self.storage
+            .store(state)
+            .await
+            .map_err(RunnerError::State)?;
+
+        Ok(Some(strategy))
+    }
+
+    /// Shut the device down and boot it into the image in `slot`, typically the [`boot_slot`](Strategy::boot_slot) of the strategy, or the primary slot if it has none.
+    ///
+    /// Only returns if the device failed to shut down, in which case it has not booted.
+    pub async fn boot(mut self, slot: Slot) -> Result<Infallible, Error> {
+        self.observer.boot(slot);
+        self.device.shutdown().await?;
+        self.device.boot(slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{vec, vec::Vec};
+
+    use super::*;
+    use crate::{
+        mock::buffered::BufferedDevice,
+        state::mem::MemStateStorage,
+        strategies::copy::{self, Copy},
+    };
+
+    fn copy_request() -> (
+        StrategyRunner<
+            BufferedDevice<crate::mock::tri_slot::MockDevice>,
+            MemStateStorage<copy::Request>,
+        >,
+        State<copy::Request>,
+        Copy,
+    ) {
+        use crate::mock::tri_slot::{ALPHA, BETA, MockDevice};
+
+        let request = copy::Request {
+            slot_secondary: BETA,
+            slot_backup: Some(ALPHA),
+            image_pages: None,
+        };
+        let device = BufferedDevice::new(MockDevice::new());
+        let strategy = Copy::new(&device, request.clone());
+        let state = State::new_request(request);
+
+        (
+            StrategyRunner::new(device, MemStateStorage::new()),
+            state,
+            strategy,
+        )
+    }
+
+    #[test]
+    fn flush_before_last_step() {
+        use crate::mock::tri_slot::IMAGE_B;
+
+        let (mut runner, mut state, strategy) = copy_request();
+
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+
+        assert_eq!(runner.device().inner.primary, IMAGE_B);
+        assert_eq!(runner.device().unflushed, 0);
+        assert_eq!(runner.device().flushes, 1);
+        assert_eq!(runner.storage().step(), Some(strategy.last_step()));
+    }
+
+    #[test]
+    fn lock_brackets_strategy() {
+        let (mut runner, mut state, strategy) = copy_request();
+
+        // The buffered device refuses copies whilst locked, so every copy happened after unlocking.
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+        assert!(!runner.device().unlocked);
+        assert_eq!(runner.device().locks, 1);
+
+        // A failing strategy is locked again as well.
+        let (mut runner, mut state, strategy) = copy_request();
+        state.request.as_mut().unwrap().step = strategy.last_step().next();
+        assert!(embassy_futures::block_on(runner.run(&mut state, &strategy)).is_err());
+        assert!(!runner.device().unlocked);
+        assert_eq!(runner.device().locks, 1);
+    }
+
+    #[test]
+    fn step_out_of_range() {
+        let (mut runner, mut state, strategy) = copy_request();
+        state.request.as_mut().unwrap().step = strategy.last_step().next();
+
+        let result = embassy_futures::block_on(runner.run(&mut state, &strategy));
+        assert_eq!(
+            result,
+            Err(RunnerError::Strategy(StrategyError::StepOutOfRange))
+        );
+        assert_eq!(
+            runner.device().inner.primary,
+            crate::mock::tri_slot::IMAGE_A
+        );
+        assert_eq!(runner.storage().stores, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "not implemented")]
+    fn flush_before_boot() {
+        use crate::mock::tri_slot::PRIMARY;
+
+        let (mut runner, mut state, strategy) = copy_request();
+
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+
+        // The buffered device asserts that all writes have been flushed and that it has been shut down,
+        // before the mock refuses to boot.
+        let _ = embassy_futures::block_on(runner.boot(PRIMARY));
+    }
+
+    #[test]
+    #[should_panic(expected = "booting without shutting down")]
+    fn boot_requires_shutdown() {
+        use crate::mock::tri_slot::PRIMARY;
+
+        let (mut runner, mut state, strategy) = copy_request();
+
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+
+        runner.device.boot(PRIMARY)
+    }
+
+    #[test]
+    fn watchdog_fed_per_operation() {
+        use crate::{
+            mock::single_scratch::{MockDevice, SECONDARY},
+            strategies::swap_scootch::{self, SwapScootch},
+        };
+
+        let request = swap_scootch::Request {
+            slot_secondary: SECONDARY,
+        };
+        let device = MockDevice::new();
+        let strategy = SwapScootch::new(&device, request.clone());
+        let mut state = State::new_request(request);
+
+        let operations: usize = Step::range(Step::default(), strategy.last_step())
+            .map(|step| strategy.plan(step).count())
+            .sum();
+
+        let mut feeds = 0;
+        let mut runner =
+            StrategyRunner::new(device, MemStateStorage::new()).with_watchdog(|| feeds += 1);
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+        drop(runner);
+
+        assert_eq!(feeds, operations);
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        StepStarted(Step),
+        CopyDone(CopyOperation),
+        StepRecorded(Step),
+        Boot(Slot),
+        RevertTriggered,
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver(Vec<Event>);
+
+    impl RunnerObserver for RecordingObserver {
+        fn step_started(&mut self, step: Step) {
+            self.0.push(Event::StepStarted(step));
+        }
+
+        fn copy_done(&mut self, operation: &CopyOperation) {
+            self.0.push(Event::CopyDone(*operation));
+        }
+
+        fn step_recorded(&mut self, step: Step) {
+            self.0.push(Event::StepRecorded(step));
+        }
+
+        fn boot(&mut self, slot: Slot) {
+            self.0.push(Event::Boot(slot));
+        }
+
+        fn revert_triggered(&mut self) {
+            self.0.push(Event::RevertTriggered);
+        }
+    }
+
+    #[test]
+    fn observer_events() {
+        use crate::mock::tri_slot::PRIMARY;
+
+        let (runner, mut state, strategy) = copy_request();
+        let mut observer = RecordingObserver::default();
+        let mut runner = runner.with_observer(&mut observer);
+
+        let forward: Vec<_> = strategy.plan(Step::default()).collect();
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+        let strategy = embassy_futures::block_on(runner.revert(&mut state, strategy))
+            .unwrap()
+            .unwrap();
+        let backward: Vec<_> = strategy.plan(Step::default()).collect();
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+
+        // The mock device refuses to boot, but only after the observer has been notified.
+        let boot = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            embassy_futures::block_on(runner.boot(PRIMARY))
+        }));
+        assert!(boot.is_err());
+
+        let mut expected = vec![Event::StepStarted(Step::default())];
+        expected.extend(forward.into_iter().map(Event::CopyDone));
+        expected.push(Event::StepRecorded(Step::default().next()));
+        expected.push(Event::RevertTriggered);
+        expected.push(Event::StepStarted(Step::default()));
+        expected.extend(backward.into_iter().map(Event::CopyDone));
+        expected.push(Event::StepRecorded(Step::default().next()));
+        expected.push(Event::Boot(PRIMARY));
+
+        assert_eq!(observer.0, expected);
+    }
+
+    #[test]
+    fn progress_swap_scootch() {
+        use crate::{
+            mock::single_scratch::{MockDevice, SECONDARY},
+            strategies::swap_scootch::{self, SwapScootch},
+        };
+
+        let request = swap_scootch::Request {
+            slot_secondary: SECONDARY,
+        };
+        let device = MockDevice::new();
+        let strategy = SwapScootch::new(&device, request.clone());
+        let total = strategy.total_operations();
+        let mut state = State::new_request(request);
+
+        assert_eq!(progress(&state, &strategy, total), Some((0, total)));
+
+        state.advance_step();
+        state.advance_step();
+        assert_eq!(progress(&state, &strategy, total), Some((2, total)));
+
+        // Resume as if power was lost after the third step was recorded.
+        state.advance_step();
+        let mut runner = StrategyRunner::new(device, MemStateStorage::new());
+        assert_eq!(progress(&state, &strategy, total), Some((3, total)));
+
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+        assert_eq!(progress(&state, &strategy, total), Some((total, total)));
+
+        let strategy = embassy_futures::block_on(runner.revert(&mut state, strategy))
+            .unwrap()
+            .unwrap();
+        assert_eq!(progress(&state, &strategy, total), Some((total, total)));
+
+        state.advance_step();
+        assert_eq!(progress(&state, &strategy, total), Some((total - 1, total)));
+
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+        assert_eq!(progress(&state, &strategy, total), Some((0, total)));
+
+        state.clear();
+        assert_eq!(progress(&state, &strategy, total), None);
+    }
+
+    #[test]
+    fn progress_reverting() {
+        use crate::{
+            mock::tri_slot::{ALPHA, BETA, MockDevice},
+            strategies::copy_with_backup::{self, CopyWithBackup},
+        };
+
+        let request = copy_with_backup::Request {
+            slot_secondary: BETA,
+            slot_backup: ALPHA,
+        };
+        let mut runner = StrategyRunner::new(MockDevice::new(), MemStateStorage::new());
+        let strategy = runner.strategy::<CopyWithBackup>(request.clone());
+        let total = strategy.total_operations();
+        let mut state = State::new_request(request);
+
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+        assert_eq!(progress(&state, &strategy, total), Some((total, total)));
+
+        // Restoring the backup takes fewer operations than making it and copying the new image.
+        let strategy = embassy_futures::block_on(runner.revert(&mut state, strategy))
+            .unwrap()
+            .unwrap();
+        assert!(strategy.total_operations() < total);
+        assert_eq!(progress(&state, &strategy, total), Some((total, total)));
+
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+        assert_eq!(progress(&state, &strategy, total), Some((0, total)));
+    }
+
+    #[test]
+    fn wear_budget() {
+        use crate::mock::tri_slot::{IMAGE_A, IMAGE_B, PRIMARY};
+
+        const BUDGET: WearBudget = WearBudget { max_erases: 10 };
+
+        // Copying erases each of the three pages of the primary slot once more.
+        let (runner, _, strategy) = copy_request();
+        let runner = runner.with_wear_budget(BUDGET, |slot| if slot == PRIMARY { 7 } else { 0 });
+        assert_eq!(runner.check_wear(&strategy), Ok(()));
+
+        let (runner, mut state, strategy) = copy_request();
+        let runner = runner.with_wear_budget(BUDGET, |slot| if slot == PRIMARY { 8 } else { 0 });
+        assert_eq!(runner.check_wear(&strategy), Err(Error::WearExceeded));
+
+        // The request is refused before anything is copied.
+        let mut runner = runner.with_critical_update(false);
+        assert_eq!(
+            embassy_futures::block_on(runner.run(&mut state, &strategy)),
+            Err(RunnerError::Device(Error::WearExceeded))
+        );
+        assert_eq!(runner.device().inner.primary, IMAGE_A);
+        assert_eq!(runner.device().locks, 0);
+
+        // A critical update overrides the budget.
+        let mut runner = runner.with_critical_update(true);
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+        assert_eq!(runner.device().inner.primary, IMAGE_B);
+    }
+
+    #[test]
+    fn misconfigured_scratch_base() {
+        use crate::{
+            mock::generic::{GenericMock, SECONDARY},
+            strategies::{
+                StrategyError,
+                swap_scootch::{self, SwapScootch},
+            },
+        };
+
+        // The second scratch page lies beyond the last representable page.
+        let mut device = GenericMock::new(4, 2);
+        device.scratch_base = u16::MAX;
+
+        let request = swap_scootch::Request {
+            slot_secondary: SECONDARY,
+        };
+        let mut runner = StrategyRunner::new(device, MemStateStorage::new());
+        let strategy = runner.strategy::<SwapScootch>(request.clone());
+        let mut state = State::new_request(request);
+
+        assert_eq!(
+            embassy_futures::block_on(runner.run(&mut state, &strategy)),
+            Err(RunnerError::Strategy(StrategyError::PageOverflow))
+        );
+        assert_eq!(runner.device().primary, GenericMock::image_a(4));
+    }
+
+    #[test]
+    fn copy_retries() {
+        use crate::{
+            mock::{
+                flaky::FlakyDevice,
+                single_scratch::{IMAGE_A, IMAGE_B, MockDevice, SECONDARY},
+            },
+            strategies::swap_scootch::{self, SwapScootch},
+        };
+
+        let request = swap_scootch::Request {
+            slot_secondary: SECONDARY,
+        };
+
+        // Every step has a single operation, of which the first attempt fails.
+        let flaky = || FlakyDevice::new(MockDevice::new(), (0..64).step_by(2));
+
+        let mut runner = StrategyRunner::new(flaky(), MemStateStorage::new());
+        let strategy = runner.strategy::<SwapScootch>(request.clone());
+        let mut state = State::new_request(request.clone());
+        assert_eq!(
+            embassy_futures::block_on(runner.run(&mut state, &strategy)),
+            Err(RunnerError::Device(Error::Storage))
+        );
+
+        let mut runner = StrategyRunner::new(flaky(), MemStateStorage::new()).with_copy_retries(1);
+        let mut state = State::new_request(request);
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+
+        assert_eq!(runner.device().inner.primary, IMAGE_B);
+        assert_eq!(runner.device().inner.secondary, IMAGE_A);
+        assert_eq!(runner.device().attempts, 2 * strategy.total_operations());
+    }
+
+    #[test]
+    fn copy_retries_exhausted() {
+        use crate::mock::{
+            flaky::FlakyDevice,
+            tri_slot::{ALPHA, BETA, IMAGE_A, IMAGE_B, MockDevice},
+        };
+
+        let request = copy::Request {
+            slot_secondary: BETA,
+            slot_backup: Some(ALPHA),
+            image_pages: None,
+        };
+
+        // The first two attempts of the first copy fail.
+        for (retries, result) in [
+            (0, Err(RunnerError::Device(Error::Storage))),
+            (1, Err(RunnerError::Device(Error::Storage))),
+            (2, Ok(())),
+        ] {
+            let device = FlakyDevice::new(MockDevice::new(), [0, 1]);
+            let mut runner =
+                StrategyRunner::new(device, MemStateStorage::new()).with_copy_retries(retries);
+            let strategy = runner.strategy::<Copy>(request.clone());
+            let mut state = State::new_request(request.clone());
+
+            let outcome = embassy_futures::block_on(runner.run(&mut state, &strategy));
+            assert_eq!(outcome.map(|_| ()), result);
+
+            if result.is_ok() {
+                assert_eq!(runner.device().inner.primary, IMAGE_B);
+            } else {
+                // Giving up on the first copy leaves the request at its first step, to be executed again or reverted.
+                assert_eq!(runner.device().inner.primary, IMAGE_A);
+                assert_eq!(runner.device().attempts, 1 + retries as usize);
+                assert_eq!(state.request.unwrap().step, Step::default());
+            }
+        }
+    }
+
+    #[test]
+    fn completion_ledger() {
+        use crate::mock::{
+            flaky::FlakyDevice,
+            tri_slot::{ALPHA, BETA, IMAGE_B, MockDevice},
+        };
+
+        let request = copy::Request {
+            slot_secondary: BETA,
+            slot_backup: Some(ALPHA),
+            image_pages: None,
+        };
+
+        // The single step copies three pages, of which the third copy is interrupted.
+        let run = |record_operations: bool| {
+            let device = FlakyDevice::new(MockDevice::new(), [2]);
+            let mut runner = StrategyRunner::new(device, MemStateStorage::new())
+                .with_completion_ledger(record_operations);
+            let strategy = runner.strategy::<Copy>(request.clone());
+
+            let mut state = State::new_request(request.clone());
+            embassy_futures::block_on(runner.storage_mut().store(&state)).unwrap();
+            assert_eq!(
+                embassy_futures::block_on(runner.run(&mut state, &strategy)),
+                Err(RunnerError::Device(Error::Storage))
+            );
+
+            // Resume from the stored state, as after a reset.
+            let mut state = embassy_futures::block_on(runner.storage_mut().fetch()).unwrap();
+            embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+
+            assert_eq!(runner.device().inner.primary, IMAGE_B);
+            runner.device().attempts
+        };
+
+        assert_eq!(run(false), 3 + 3);
+        assert_eq!(run(true), 3 + 1);
+    }
+
+    #[test]
+    fn run_stats() {
+        use core::sync::atomic::{AtomicU64, Ordering};
+
+        use crate::{
+            mock::single_scratch::{MockDevice, SECONDARY},
+            strategies::swap_scootch::{self, SwapScootch},
+        };
+
+        static TICKS: AtomicU64 = AtomicU64::new(0);
+        fn clock() -> u64 {
+            TICKS.fetch_add(5, Ordering::Relaxed)
+        }
+
+        let request = swap_scootch::Request {
+            slot_secondary: SECONDARY,
+        };
+        let mut runner = StrategyRunner::new(MockDevice::new(), MemStateStorage::new());
+        let strategy = runner.strategy::<SwapScootch>(request.clone());
+        let num_pages = runner.device().page_count().get() as u32;
+
+        let mut state = State::new_request(request.clone());
+        let stats = embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+
+        // A scootch and two copies for every page.
+        assert_eq!(stats.operations, num_pages * 3);
+        assert_eq!(stats.steps, strategy.last_step().as_u32());
+        assert_eq!(stats.elapsed, None);
+
+        let mut runner = runner.with_clock(clock);
+        let mut state = State::new_request(request);
+        let stats = embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+        assert_eq!(stats.elapsed, Some(5));
+
+        // Nothing is left to do once the last step has been recorded.
+        let stats = embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+        assert_eq!(stats.operations, 0);
+        assert_eq!(stats.steps, 0);
+    }
+
+    #[test]
+    fn safe_points() {
+        use crate::{
+            mock::tri_slot::{BETA, IMAGE_B, MockDevice},
+            strategies::delta_copy::{self, DeltaCopy},
+        };
+
+        /// Strategy of which only the last step is a safe point, as re-executing any of its copies is harmless.
+        struct OnlyLast(DeltaCopy);
+
+        impl Strategy for OnlyLast {
+            type Plan<'a> = delta_copy::DeltaCopyPlan;
+
+            fn last_step(&self) -> Step {
+                self.0.last_step()
+            }
+
+            fn plan(&self, step: Step) -> Self::Plan<'_> {
+                self.0.plan(step)
+            }
+
+            fn image_slot(&self) -> Option<Slot> {
+                self.0.image_slot()
+            }
+
+            fn boot_slot(&self) -> Option<Slot> {
+                self.0.boot_slot()
+            }
+
+            fn revert(self) -> Option<Self> {
+                self.0.revert().map(OnlyLast)
+            }
+
+            fn is_safe_point(&self, _step: Step) -> bool {
+                false
+            }
+        }
+
+        let request = delta_copy::Request {
+            slot_secondary: BETA,
+            dirty_regions: 0b111,
+        };
+        let mut runner = StrategyRunner::new(MockDevice::new(), MemStateStorage::new());
+        let strategy = OnlyLast(runner.strategy::<DeltaCopy>(request.clone()));
+
+        let mut state = State::new_request(request);
+        let stats = embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+
+        // Every step is executed, but only reaching the last step is stored.
+        assert_eq!(stats.steps, 3);
+        assert_eq!(runner.storage().stores, 1);
+        assert_eq!(runner.storage().step(), Some(strategy.last_step()));
+        assert_eq!(runner.device().primary, IMAGE_B);
+    }
+
+    #[test]
+    fn cancellation() {
+        use core::{
+            pin::pin,
+            task::{Context, Waker},
+        };
+
+        use crate::{
+            mock::{
+                single_scratch::{IMAGE_A, IMAGE_B, MockDevice, SECONDARY},
+                yielding::YieldingDevice,
+            },
+            strategies::swap_scootch::{self, SwapScootch},
+        };
+
+        let request = swap_scootch::Request {
+            slot_secondary: SECONDARY,
+        };
+
+        // Drop the run future after every number of polls, until it completes within that number of polls.
+        for polls in 1.. {
+            let completed = [true, false].map(|reset| {
+                let device = YieldingDevice::new(MockDevice::new());
+                let mut runner = StrategyRunner::new(device, MemStateStorage::new());
+                let strategy = runner.strategy::<SwapScootch>(request.clone());
+                let mut state = State::new_request(request.clone());
+                embassy_futures::block_on(runner.storage_mut().store(&state)).unwrap();
+
+                let completed = {
+                    let mut run = pin!(runner.run(&mut state, &strategy));
+                    let mut context = Context::from_waker(Waker::noop());
+                    (0..polls).any(|_| run.as_mut().poll(&mut context).is_ready())
+                };
+
+                // Resume either from the stored state, as after a reset, or from the state as left behind.
+                if reset {
+                    state = runner.storage().state().unwrap().clone();
+                }
+                embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+
+                assert_eq!(runner.storage().step(), Some(strategy.last_step()));
+                assert_eq!(runner.device().inner.primary, IMAGE_B);
+                assert_eq!(runner.device().inner.secondary, IMAGE_A);
+                completed
+            });
+
+            if completed.into_iter().all(|completed| completed) {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn cancellation_skips_lock() {
+        use core::{
+            pin::pin,
+            task::{Context, Waker},
+        };
+
+        use crate::mock::{
+            tri_slot::{IMAGE_B, MockDevice},
+            yielding::YieldingDevice,
+        };
+
+        let (_, mut state, strategy) = copy_request();
+        let device = BufferedDevice::new(YieldingDevice::new(MockDevice::new()));
+        let mut runner = StrategyRunner::new(device, MemStateStorage::new());
+
+        {
+            let mut run = pin!(runner.run(&mut state, &strategy));
+            let mut context = Context::from_waker(Waker::noop());
+            assert!(run.as_mut().poll(&mut context).is_pending());
+        }
+        assert!(runner.device().unlocked);
+        assert_eq!(runner.device().locks, 0);
+
+        // The next run neither unlocks twice nor leaves the device unlocked.
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+        assert!(!runner.device().unlocked);
+        assert_eq!(runner.device().locks, 1);
+        assert_eq!(runner.device().inner.inner.primary, IMAGE_B);
+    }
+
+    #[test]
+    fn cancellation_then_refused() {
+        use core::{
+            pin::pin,
+            task::{Context, Waker},
+        };
+
+        use crate::mock::{
+            tri_slot::{IMAGE_A, MockDevice, PRIMARY},
+            yielding::YieldingDevice,
+        };
+
+        let (_, mut state, strategy) = copy_request();
+        let device = BufferedDevice::new(YieldingDevice::new(MockDevice::new()));
+        let mut runner = StrategyRunner::new(device, MemStateStorage::new());
+
+        {
+            let mut run = pin!(runner.run(&mut state, &strategy));
+            let mut context = Context::from_waker(Waker::noop());
+            assert!(run.as_mut().poll(&mut context).is_pending());
+        }
+        assert!(runner.device().unlocked);
+
+        // The request is still fresh, and refused by the wear budget before anything is copied.
+        let budget = WearBudget { max_erases: 1 };
+        let mut runner = runner.with_wear_budget(budget, |slot| (slot == PRIMARY) as u32);
+        assert_eq!(
+            embassy_futures::block_on(runner.run(&mut state, &strategy)),
+            Err(RunnerError::Device(Error::WearExceeded))
+        );
+        assert!(!runner.device().unlocked);
+        assert_eq!(runner.device().locks, 1);
+        assert_eq!(runner.device().inner.inner.primary, IMAGE_A);
+    }
+
+    #[test]
+    fn erase_ahead() {
+        use crate::{
+            mock::{
+                erase_ahead::EraseAheadDevice,
+                tri_slot::{ALPHA, BETA, IMAGE_A, IMAGE_B, MockDevice},
+            },
+            strategies::copy_with_backup::{self, CopyWithBackup},
+        };
+
+        let request = copy_with_backup::Request {
+            slot_secondary: BETA,
+            slot_backup: ALPHA,
+        };
+        let device = EraseAheadDevice::new(MockDevice::new());
+        let mut runner = StrategyRunner::new(device, MemStateStorage::new());
+        let strategy = runner.strategy::<CopyWithBackup>(request.clone());
+
+        let mut state = State::new_request(request);
+        let stats =
+            embassy_futures::block_on(runner.run_with_erase_ahead(&mut state, &strategy)).unwrap();
+
+        let device = runner.device();
+        assert_eq!(device.inner.primary, IMAGE_B);
+        assert_eq!(device.inner.alpha, IMAGE_A);
+        assert_eq!(stats.operations, 6);
+
+        // The primary pages are backed up in the first step, thus can not be erased ahead of the second step.
+        assert_eq!(device.erased_ahead, 4);
+        assert_eq!(device.overlaps, 4);
+    }
+
+    #[test]
+    fn explicit_erase() {
+        use crate::mock::tri_slot::{BETA, IMAGE_B, MockDevice, PRIMARY};
+
+        let request = copy::Request {
+            slot_secondary: BETA,
+            slot_backup: None,
+            image_pages: None,
+        };
+        let mut runner = StrategyRunner::new(MockDevice::new(), MemStateStorage::new());
+        let strategy = runner.strategy::<Copy>(request.clone());
+
+        // The mock refuses to copy onto a page that has not been erased first.
+        let mut state = State::new_request(request);
+        let stats =
+            embassy_futures::block_on(runner.run_with_explicit_erase(&mut state, &strategy))
+                .unwrap();
+
+        let device = runner.device();
+        assert_eq!(device.primary, IMAGE_B);
+        assert_eq!(stats.operations, 3);
+
+        // Every page is erased once, by the explicit erase rather than by the copy.
+        assert!(device.wear.check_slot(PRIMARY, 1));
+    }
+}
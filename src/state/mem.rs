@@ -0,0 +1,112 @@
+//! State storage in RAM, to exercise runners and flows without any non-volatile memory.
+
+use crate::{
+    Step,
+    state::{State, StateStorage},
+};
+
+/// Error of [`MemStateStorage`], returned for a store that was set up to fail.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StoreFailed;
+
+/// State storage keeping the last stored state in memory, which is lost on reset.
+///
+/// Fetching before anything has been stored yields the default state.
+pub struct MemStateStorage<S> {
+    state: Option<State<S>>,
+    /// Number of successful stores.
+    pub stores: usize,
+    /// Number of upcoming stores that will fail with [`StoreFailed`], leaving the previously stored state as is.
+    pub failing_stores: usize,
+}
+
+impl<S> MemStateStorage<S> {
+    pub const fn new() -> Self {
+        Self {
+            state: None,
+            stores: 0,
+            failing_stores: 0,
+        }
+    }
+
+    /// The last stored state, if any.
+    pub fn state(&self) -> Option<&State<S>> {
+        self.state.as_ref()
+    }
+
+    /// Step of the last stored request, if any.
+    pub fn step(&self) -> Option<Step> {
+        self.state
+            .as_ref()
+            .and_then(|state| state.request.as_ref())
+            .map(|request| request.step)
+    }
+}
+
+impl<S> Default for MemStateStorage<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Clone> StateStorage<S> for MemStateStorage<S> {
+    type Error = StoreFailed;
+
+    async fn store(&mut self, state: &State<S>) -> Result<(), Self::Error> {
+        if self.failing_stores > 0 {
+            self.failing_stores -= 1;
+            return Err(StoreFailed);
+        }
+
+        self.state = Some(state.clone());
+        self.stores += 1;
+        Ok(())
+    }
+
+    async fn fetch(&mut self) -> Result<State<S>, Self::Error> {
+        Ok(self.state.clone().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock::tri_slot::{ALPHA, BETA, IMAGE_A, IMAGE_B, MockDevice},
+        runner::{RunnerError, StrategyRunner},
+        strategies::{
+            Strategy,
+            copy::{self, Copy},
+        },
+    };
+
+    #[test]
+    fn failing_store() {
+        let request = copy::Request {
+            slot_secondary: BETA,
+            slot_backup: Some(ALPHA),
+            image_pages: None,
+        };
+        let mut storage = MemStateStorage::new();
+        embassy_futures::block_on(storage.store(&State::new_request(request.clone()))).unwrap();
+        storage.failing_stores = 1;
+
+        let mut runner = StrategyRunner::new(MockDevice::new(), storage);
+        let strategy = runner.strategy::<Copy>(request);
+
+        // Power is lost before the state update completes, hence the copy is still pending.
+        let mut state = embassy_futures::block_on(runner.storage_mut().fetch()).unwrap();
+        let result = embassy_futures::block_on(runner.run(&mut state, &strategy));
+        assert_eq!(result, Err(RunnerError::State(StoreFailed)));
+        assert_eq!(runner.storage().step(), Some(Step::default()));
+        assert_eq!(runner.device().primary, IMAGE_B);
+
+        // Resuming executes the copy again, after which the image can be booted.
+        let mut state = embassy_futures::block_on(runner.storage_mut().fetch()).unwrap();
+        embassy_futures::block_on(runner.run(&mut state, &strategy)).unwrap();
+        assert_eq!(runner.storage().step(), Some(strategy.last_step()));
+        assert_eq!(runner.storage().stores, 2);
+        assert_eq!(runner.device().primary, IMAGE_B);
+        assert_eq!(runner.device().alpha, IMAGE_A);
+    }
+}
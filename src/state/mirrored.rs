@@ -0,0 +1,133 @@
+//! State storage mirrored across two independent storages, for when a single copy of the state is not trusted enough.
+
+use crate::state::{State, StateStorage};
+
+/// Error of [`MirroredStateStorage`], telling which of the storages failed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MirroredError<A, B> {
+    /// Storing in the primary storage failed, leaving the mirror as is.
+    Primary(A),
+    /// Storing in the mirror failed, after storing in the primary storage succeeded.
+    Mirror(B),
+    /// Fetching failed from both storages.
+    Both(A, B),
+}
+
+/// State storage writing to both `A` and `B`, and fetching from `B` only if fetching from `A` fails.
+///
+/// A corrupt primary copy, for example due to power loss whilst storing, is thus recovered from the mirror.
+/// As the primary storage is written first, the mirror holds either the same or the previous state.
+pub struct MirroredStateStorage<A, B> {
+    primary: A,
+    mirror: B,
+}
+
+impl<A, B> MirroredStateStorage<A, B> {
+    pub const fn new(primary: A, mirror: B) -> Self {
+        Self { primary, mirror }
+    }
+
+    pub fn primary(&self) -> &A {
+        &self.primary
+    }
+
+    pub fn mirror(&self) -> &B {
+        &self.mirror
+    }
+
+    pub fn into_inner(self) -> (A, B) {
+        (self.primary, self.mirror)
+    }
+}
+
+impl<S, A: StateStorage<S>, B: StateStorage<S>> StateStorage<S> for MirroredStateStorage<A, B> {
+    type Error = MirroredError<A::Error, B::Error>;
+
+    async fn store(&mut self, state: &State<S>) -> Result<(), Self::Error> {
+        self.primary
+            .store(state)
+            .await
+            .map_err(MirroredError::Primary)?;
+        self.mirror
+            .store(state)
+            .await
+            .map_err(MirroredError::Mirror)
+    }
+
+    async fn fetch(&mut self) -> Result<State<S>, Self::Error> {
+        match self.primary.fetch().await {
+            Ok(state) => Ok(state),
+            Err(primary) => self
+                .mirror
+                .fetch()
+                .await
+                .map_err(|mirror| MirroredError::Both(primary, mirror)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Step,
+        state::mem::{MemStateStorage, StoreFailed},
+    };
+
+    /// Storage of which the state is corrupted once stored, failing every fetch.
+    struct CorruptStorage;
+
+    #[derive(Debug, PartialEq)]
+    struct Corrupted;
+
+    impl StateStorage<u8> for CorruptStorage {
+        type Error = Corrupted;
+
+        async fn store(&mut self, _state: &State<u8>) -> Result<(), Corrupted> {
+            Ok(())
+        }
+
+        async fn fetch(&mut self) -> Result<State<u8>, Corrupted> {
+            Err(Corrupted)
+        }
+    }
+
+    #[test]
+    fn corrupt_primary() {
+        let mut storage = MirroredStateStorage::new(CorruptStorage, MemStateStorage::new());
+        let mut state = State::new_request(42u8);
+        state.advance_step();
+
+        embassy_futures::block_on(storage.store(&state)).unwrap();
+        let fetched = embassy_futures::block_on(storage.fetch()).unwrap();
+
+        let request = fetched.request.unwrap();
+        assert_eq!(request.strategy, 42);
+        assert_eq!(request.step, Step(1));
+    }
+
+    #[test]
+    fn corrupt_both() {
+        let mut storage = MirroredStateStorage::new(CorruptStorage, CorruptStorage);
+
+        let result = embassy_futures::block_on(storage.fetch());
+        assert!(matches!(
+            result,
+            Err(MirroredError::Both(Corrupted, Corrupted))
+        ));
+    }
+
+    #[test]
+    fn failing_store() {
+        let mut storage = MirroredStateStorage::new(MemStateStorage::new(), MemStateStorage::new());
+        storage.primary.failing_stores = 1;
+
+        let result = embassy_futures::block_on(storage.store(&State::new_request(42u8)));
+        assert_eq!(result, Err(MirroredError::Primary(StoreFailed)));
+        assert_eq!(storage.mirror().stores, 0);
+
+        embassy_futures::block_on(storage.store(&State::new_request(42u8))).unwrap();
+        assert_eq!(storage.primary().stores, 1);
+        assert_eq!(storage.mirror().stores, 1);
+    }
+}
@@ -1,11 +1,16 @@
-use serde::{Deserialize, Serialize};
+use core::num::NonZeroU16;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::Step;
 
+#[cfg(any(test, feature = "testkit"))]
+pub mod mem;
+pub mod mirrored;
 #[cfg(feature = "simple_state")]
 pub mod simple;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Request<S> {
     /// The underlying strategy specific request.
     pub strategy: S,
@@ -19,15 +24,294 @@ pub struct Request<S> {
     /// Bit to indicate that the original request was attempted and failed.
     /// The steps now indicate how far along the strategy is in reverting to the previous (working) situation.
     pub revert: bool,
+
+    /// Operations of the current step that have completed, if recorded by the runner.
+    ///
+    /// Stored as part of the [`State`] rather than the request, such that requests keep their original layout.
+    #[serde(skip)]
+    pub completed: CompletionLedger,
+
+    /// Minimum version from before the request was made as a [downgrade](State::request_downgrade), restored if the
+    /// downgrade is reverted.
+    ///
+    /// Stored as part of the [`State`] rather than the request, like [`completed`](Self::completed).
+    #[serde(skip)]
+    pub restore_min_version: Option<u32>,
+}
+
+/// Bitmap of the operations of a step that have completed, by their index in the plan of the step.
+///
+/// Allows a step interrupted halfway to resume at its first unfinished operation, instead of executing all its operations again.
+/// Only the first [`CAPACITY`](Self::CAPACITY) operations of a step can be recorded; any further operations are always executed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct CompletionLedger(u32);
+
+impl CompletionLedger {
+    /// Number of operations of a step that can be recorded.
+    pub const CAPACITY: usize = u32::BITS as usize;
+
+    /// Whether the operation at `index` in the plan of the current step has completed.
+    pub const fn is_completed(&self, index: usize) -> bool {
+        index < Self::CAPACITY && self.0 & (1 << index) != 0
+    }
+
+    /// Record the operation at `index` as completed, returning `false` if it can not be recorded.
+    pub(crate) fn complete(&mut self, index: usize) -> bool {
+        if index >= Self::CAPACITY {
+            return false;
+        }
+        self.0 |= 1 << index;
+        true
+    }
 }
 
 /// State as stored by the bootloader.
-#[derive(Serialize, Deserialize)]
+///
+/// Serialized in the original layout for as long as the scratch offset, minimum version and completed operations are unset,
+/// and in an extended layout otherwise, such that states stored before these fields were introduced can still be decoded.
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct State<S> {
     /// Request indicating that the bootloader should perform a specific strategy.
     ///
     /// If no request is set, it will try to boot the primary image, if any exists.
     pub request: Option<Request<S>>,
+
+    /// Scratch page at which strategies wrapped in [`WearLeveling`](crate::strategies::wear_leveling::WearLeveling) start,
+    /// rotated after every request to spread wear over all scratch pages.
+    pub scratch_offset: u16,
+
+    /// Lowest image version that may be installed, to protect against rolling back to an older (vulnerable) image.
+    pub min_version: u32,
+}
+
+impl<S> State<S> {
+    /// State with a pending request to execute `strategy` from its first step.
+    pub const fn new_request(strategy: S) -> Self {
+        Self {
+            request: Some(Request {
+                strategy,
+                step: Step(0),
+                revert: false,
+                completed: CompletionLedger(0),
+                restore_min_version: None,
+            }),
+            scratch_offset: 0,
+            min_version: 0,
+        }
+    }
+
+    /// Set a pending request to execute `strategy` from its first step, replacing the current request if any.
+    pub fn set_request(&mut self, strategy: S) {
+        self.request = Some(Request {
+            strategy,
+            step: Step::default(),
+            revert: false,
+            completed: CompletionLedger::default(),
+            restore_min_version: None,
+        });
+    }
+
+    /// Move on to the next scratch page, for a device with `scratch_page_count` scratch pages.
+    ///
+    /// Should only be called when no request is in progress, as the offset determines where a strategy keeps its data.
+    pub fn rotate_scratch(&mut self, scratch_page_count: NonZeroU16) {
+        // Reduced first, as a corrupted or outdated offset may lie far beyond the scratch pages.
+        let scratch_page_count = scratch_page_count.get();
+        self.scratch_offset = (self.scratch_offset % scratch_page_count + 1) % scratch_page_count;
+    }
+
+    /// Forget the request, if any, such that the primary image is booted.
+    pub fn clear(&mut self) {
+        self.request = None;
+    }
+
+    /// Accept the booted image of `version` as working, forgetting the request and refusing older images from now on.
+    pub fn confirm(&mut self, version: u32) {
+        self.request = None;
+        self.min_version = self.min_version.max(version);
+    }
+
+    /// Whether an update is staged or being installed, and has not failed.
+    ///
+    /// Remains set whilst the new image is being tried, until it is confirmed.
+    pub fn is_update_pending(&self) -> bool {
+        self.request.as_ref().is_some_and(|request| !request.revert)
+    }
+
+    /// Whether a failed update is being reverted.
+    pub fn is_reverting(&self) -> bool {
+        self.request.as_ref().is_some_and(|request| request.revert)
+    }
+
+    /// Accept the booted image of `version` as working like [`confirm`](Self::confirm), and set `strategy` as the next request.
+    ///
+    /// For updates spanning multiple boots, for example migrating an image after it has been confirmed.
+    pub fn confirm_and_request(&mut self, version: u32, strategy: S) {
+        self.confirm(version);
+        self.set_request(strategy);
+    }
+
+    /// Deliberately install the older image of `version`, by setting `strategy` as the next request.
+    ///
+    /// Unlike the revert of a failed update, a downgrade is an ordinary request: it is tried and confirmed like any update,
+    /// and reverted if it fails its trial. The minimum version is lowered to `version`, such that the older image is not
+    /// refused as a rollback, and restored if the downgrade is reverted. Only call this on explicit intent, for example
+    /// from an authenticated fleet management command.
+    pub fn request_downgrade(&mut self, version: u32, strategy: S) {
+        // Replacing a pending downgrade keeps the minimum version from before that downgrade.
+        let restore = self
+            .request
+            .as_ref()
+            .and_then(|request| request.restore_min_version)
+            .map_or(self.min_version, |restore| restore.max(self.min_version));
+
+        self.set_request(strategy);
+        if let Some(request) = self.request.as_mut() {
+            request.restore_min_version = Some(restore);
+        }
+        self.min_version = self.min_version.min(version);
+    }
+
+    /// Record the operation at `index` of the current step as completed, returning whether it was recorded.
+    pub(crate) fn complete_operation(&mut self, index: usize) -> bool {
+        self.request
+            .as_mut()
+            .is_some_and(|request| request.completed.complete(index))
+    }
+
+    /// Whether the operation at `index` of the current step has been recorded as completed.
+    pub(crate) fn is_operation_completed(&self, index: usize) -> bool {
+        self.request
+            .as_ref()
+            .is_some_and(|request| request.completed.is_completed(index))
+    }
+
+    /// Move the request to its next step, returning that step.
+    pub(crate) fn advance_step(&mut self) -> Option<Step> {
+        let request = self.request.as_mut()?;
+        request.step = request.step.next();
+        request.completed = CompletionLedger::default();
+        Some(request.step)
+    }
+
+    /// Mark the request as failed, such that its reversal is executed from the first step.
+    ///
+    /// Returns `false` if there is no request to revert.
+    pub(crate) fn begin_revert(&mut self) -> bool {
+        let Some(request) = self.request.as_mut() else {
+            return false;
+        };
+        request.revert = true;
+        request.step = Step::default();
+        request.completed = CompletionLedger::default();
+        if let Some(restore) = request.restore_min_version.take() {
+            self.min_version = self.min_version.max(restore);
+        }
+        true
+    }
+}
+
+impl<S> Default for State<S> {
+    fn default() -> Self {
+        Self {
+            request: None,
+            scratch_offset: 0,
+            min_version: 0,
+        }
+    }
+}
+
+/// Stored layout of the [`State`].
+///
+/// The first two variants encode exactly like the original `Option<Request<S>>` of the state, and are written whenever
+/// the fields added since are at their default, thus states can be exchanged with bootloaders and applications that predate them.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "State")]
+enum Record<S> {
+    Idle,
+    Request(Request<S>),
+    Extended {
+        request: Option<Request<S>>,
+        completed: CompletionLedger,
+        scratch_offset: u16,
+        min_version: u32,
+        restore_min_version: Option<u32>,
+    },
+}
+
+/// Borrowed counterpart of [`Record`], to serialize a [`State`] without cloning it.
+#[derive(Serialize)]
+#[serde(rename = "State")]
+enum RecordRef<'a, S> {
+    Idle,
+    Request(&'a Request<S>),
+    Extended {
+        request: &'a Option<Request<S>>,
+        completed: CompletionLedger,
+        scratch_offset: u16,
+        min_version: u32,
+        restore_min_version: Option<u32>,
+    },
+}
+
+impl<S: Serialize> Serialize for State<S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let completed = self
+            .request
+            .as_ref()
+            .map(|request| request.completed)
+            .unwrap_or_default();
+        let restore_min_version = self
+            .request
+            .as_ref()
+            .and_then(|request| request.restore_min_version);
+        let extended = completed != CompletionLedger::default()
+            || restore_min_version.is_some()
+            || self.scratch_offset != 0
+            || self.min_version != 0;
+
+        let record = match &self.request {
+            _ if extended => RecordRef::Extended {
+                request: &self.request,
+                completed,
+                scratch_offset: self.scratch_offset,
+                min_version: self.min_version,
+                restore_min_version,
+            },
+            None => RecordRef::Idle,
+            Some(request) => RecordRef::Request(request),
+        };
+        record.serialize(serializer)
+    }
+}
+
+impl<'de, S: Deserialize<'de>> Deserialize<'de> for State<S> {
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        Ok(match Record::deserialize(deserializer)? {
+            Record::Idle => Self::default(),
+            Record::Request(request) => Self {
+                request: Some(request),
+                ..Self::default()
+            },
+            Record::Extended {
+                mut request,
+                completed,
+                scratch_offset,
+                min_version,
+                restore_min_version,
+            } => Self {
+                request: {
+                    if let Some(request) = request.as_mut() {
+                        request.completed = completed;
+                        request.restore_min_version = restore_min_version;
+                    }
+                    request
+                },
+                scratch_offset,
+                min_version,
+            },
+        })
+    }
 }
 
 /// Trait that arranges the state to be stored.
@@ -37,4 +321,225 @@ pub trait StateStorage<S> {
 
     async fn store(&mut self, state: &State<S>) -> Result<(), Self::Error>;
     async fn fetch(&mut self) -> Result<State<S>, Self::Error>;
+
+    /// Store `new`, but only if the stored state equals `expected`, returning whether it was stored.
+    ///
+    /// Guards against lost updates when a transition is based on a state that has since been replaced,
+    /// for example by a retry that was interrupted. Defaults to fetching and then storing.
+    async fn compare_and_store(
+        &mut self,
+        expected: &State<S>,
+        new: &State<S>,
+    ) -> Result<bool, Self::Error>
+    where
+        S: PartialEq,
+    {
+        if self.fetch().await? != *expected {
+            return Ok(false);
+        }
+
+        self.store(new).await?;
+        Ok(true)
+    }
+}
+
+impl<S, T: StateStorage<S> + ?Sized> StateStorage<S> for &mut T {
+    type Error = T::Error;
+
+    async fn store(&mut self, state: &State<S>) -> Result<(), Self::Error> {
+        (**self).store(state).await
+    }
+
+    async fn fetch(&mut self) -> Result<State<S>, Self::Error> {
+        (**self).fetch().await
+    }
+
+    async fn compare_and_store(
+        &mut self,
+        expected: &State<S>,
+        new: &State<S>,
+    ) -> Result<bool, Self::Error>
+    where
+        S: PartialEq,
+    {
+        (**self).compare_and_store(expected, new).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitions() {
+        let mut state = State::new_request(42u8);
+
+        let request = state.request.as_ref().unwrap();
+        assert_eq!(request.strategy, 42);
+        assert_eq!(request.step, Step::default());
+        assert!(!request.revert);
+
+        assert_eq!(state.advance_step(), Some(Step(1)));
+        assert_eq!(state.advance_step(), Some(Step(2)));
+
+        assert!(state.begin_revert());
+        let request = state.request.as_ref().unwrap();
+        assert_eq!(request.step, Step::default());
+        assert!(request.revert);
+
+        assert_eq!(state.advance_step(), Some(Step(1)));
+
+        state.clear();
+        assert!(state.request.is_none());
+        assert_eq!(state.advance_step(), None);
+        assert!(!state.begin_revert());
+    }
+
+    #[test]
+    fn queries() {
+        let mut state = State::<u8>::default();
+        assert!(!state.is_update_pending());
+        assert!(!state.is_reverting());
+
+        state.set_request(1);
+        assert!(state.is_update_pending());
+        assert!(!state.is_reverting());
+
+        state.advance_step();
+        assert!(state.is_update_pending());
+
+        state.begin_revert();
+        assert!(!state.is_update_pending());
+        assert!(state.is_reverting());
+
+        state.clear();
+        assert!(!state.is_update_pending());
+        assert!(!state.is_reverting());
+    }
+
+    #[test]
+    fn rotate_scratch() {
+        let scratch_page_count = NonZeroU16::new(3).unwrap();
+        let mut state = State::<u8>::default();
+
+        state.rotate_scratch(scratch_page_count);
+        state.set_request(1);
+        assert_eq!(state.scratch_offset, 1);
+
+        state.rotate_scratch(scratch_page_count);
+        state.rotate_scratch(scratch_page_count);
+        assert_eq!(state.scratch_offset, 0);
+        assert_eq!(state.request.as_ref().unwrap().strategy, 1);
+
+        state.scratch_offset = u16::MAX;
+        state.rotate_scratch(scratch_page_count);
+        assert_eq!(state.scratch_offset, 1);
+    }
+
+    #[test]
+    fn confirm() {
+        let mut state = State::new_request(1u8);
+
+        state.confirm(3);
+        assert!(state.request.is_none());
+        assert_eq!(state.min_version, 3);
+
+        // Confirming an older image never lowers the minimum version.
+        state.confirm(2);
+        assert_eq!(state.min_version, 3);
+    }
+
+    #[test]
+    fn failed_downgrade() {
+        let mut state = State::<u8>::default();
+        state.confirm(3);
+
+        state.request_downgrade(1, 1);
+        assert_eq!(state.min_version, 1);
+        state.request_downgrade(2, 2);
+        assert_eq!(state.min_version, 1);
+
+        // Reverting the downgrade restores the minimum version from before the first downgrade.
+        state.advance_step();
+        assert!(state.begin_revert());
+        assert_eq!(state.min_version, 3);
+        state.clear();
+        assert_eq!(state.min_version, 3);
+
+        // A confirmed downgrade keeps the lowered minimum version.
+        state.request_downgrade(1, 1);
+        state.confirm(1);
+        assert_eq!(state.min_version, 1);
+    }
+
+    #[test]
+    fn clone_is_independent() {
+        use crate::{mock::generic::SECONDARY, strategies::swap_scootch};
+
+        let state = State::new_request(swap_scootch::Request {
+            slot_secondary: SECONDARY,
+        });
+
+        let mut tentative = state.clone();
+        tentative.advance_step();
+        tentative.confirm(1);
+        assert!(tentative.request.is_none());
+
+        let request = state.request.as_ref().unwrap();
+        assert_eq!(request.step, Step::default());
+        assert_eq!(request.strategy.slot_secondary, SECONDARY);
+        assert_eq!(state.min_version, 0);
+    }
+
+    #[cfg(feature = "simple_state")]
+    #[test]
+    fn legacy_layout() {
+        // Idle state as stored before the scratch offset and minimum version were introduced.
+        let state: State<u8> = postcard::from_bytes(&[0]).unwrap();
+        assert_eq!(state, State::default());
+
+        // A state without the new fields set is stored in the original layout.
+        let mut buffer = [0u8; 16];
+        assert_eq!(
+            postcard::to_slice(&State::<u8>::default(), &mut buffer).unwrap(),
+            &[0]
+        );
+
+        let mut state = State::<u8>::default();
+        state.rotate_scratch(NonZeroU16::new(3).unwrap());
+        state.confirm(300);
+        let encoded = postcard::to_slice(&state, &mut buffer).unwrap();
+        assert_eq!(postcard::from_bytes::<State<u8>>(encoded).unwrap(), state);
+
+        state.request_downgrade(200, 1);
+        let encoded = postcard::to_slice(&state, &mut buffer).unwrap();
+        assert_eq!(postcard::from_bytes::<State<u8>>(encoded).unwrap(), state);
+    }
+
+    #[cfg(feature = "simple_state")]
+    #[test]
+    fn legacy_request() {
+        // Request for strategy 7 reverting at step 2, as stored before operations were recorded.
+        let blob = [1, 7, 2, 1];
+        let state: State<u8> = postcard::from_bytes(&blob).unwrap();
+        let request = state.request.as_ref().unwrap();
+        assert_eq!(
+            (request.strategy, request.step, request.revert),
+            (7, Step(2), true)
+        );
+        assert_eq!(request.completed, CompletionLedger::default());
+
+        // Without recorded operations the request is stored in the original layout.
+        let mut buffer = [0u8; 16];
+        assert_eq!(postcard::to_slice(&state, &mut buffer).unwrap(), &blob);
+        assert_eq!(
+            postcard::to_slice(state.request.as_ref().unwrap(), &mut buffer).unwrap(),
+            &blob[1..]
+        );
+
+        let mut state = state;
+        assert!(state.complete_operation(3));
+        let encoded = postcard::to_slice(&state, &mut buffer).unwrap();
+        assert_eq!(postcard::from_bytes::<State<u8>>(encoded).unwrap(), state);
+    }
 }
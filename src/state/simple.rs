@@ -9,22 +9,69 @@ use embedded_storage_async::nor_flash::NorFlash;
 use sequential_storage::{cache::KeyPointerCache, map::SerializationError};
 use serde::{Serialize, de::DeserializeOwned};
 
-use crate::state::{State, StateStorage};
+use crate::{
+    Error,
+    state::{State, StateStorage},
+};
 
-pub struct SimpleStateStorage<NVM, S> {
+/// Policy on how to handle a failure to fetch the state from NVM.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FetchErrorPolicy {
+    /// Propagate the error to the caller, which typically refuses to boot.
+    #[default]
+    FailClosed,
+    /// Act as if no request is set, which results in booting the primary image.
+    ///
+    /// The scratch offset and minimum version are kept from the state last fetched or stored, such that a glitch does not
+    /// lower the anti-rollback floor. If no state has been fetched or stored yet, as on a cold boot, the minimum version is
+    /// `min_version`, which should be the lowest version the integrator still accepts.
+    ///
+    /// **Warning**: a pending or in-progress request is silently ignored when the NVM glitches.
+    /// If a swap was interrupted halfway, the primary slot might not contain a bootable image.
+    FailSafe {
+        /// Minimum version when the state is unknown.
+        min_version: u32,
+    },
+}
+
+/// State storage on a NOR flash partition of `SECTORS` erase pages.
+///
+/// `SECTORS` and `KEYS` size the [`KeyPointerCache`] indexing the partition, and should match its number of erase pages
+/// and the number of keys stored in it respectively. As the state is stored under a single key, `KEYS` rarely needs changing.
+pub struct SimpleStateStorage<NVM, S, const SECTORS: usize = 2, const KEYS: usize = 1> {
     nvm: NVM,
-    nvm_cache: KeyPointerCache<2, (), 1>,
+    nvm_cache: KeyPointerCache<SECTORS, (), KEYS>,
+    on_fetch_error: FetchErrorPolicy,
+    retry_store: bool,
+    /// Scratch offset and minimum version of the state last fetched or stored, kept when failing safe.
+    retained: Option<(u16, u32)>,
     _phantom: PhantomData<S>,
 }
 
-impl<NVM, S> SimpleStateStorage<NVM, S> {
+impl<NVM, S, const SECTORS: usize, const KEYS: usize> SimpleStateStorage<NVM, S, SECTORS, KEYS> {
+    /// Create a state storage that propagates all errors, see [FetchErrorPolicy::FailClosed].
     pub fn new(nvm: NVM) -> Self {
         Self {
             nvm,
             nvm_cache: KeyPointerCache::new(),
+            on_fetch_error: FetchErrorPolicy::FailClosed,
+            retry_store: false,
+            retained: None,
             _phantom: PhantomData,
         }
     }
+
+    /// Set the policy for handling errors when fetching the state.
+    pub fn with_fetch_error_policy(mut self, policy: FetchErrorPolicy) -> Self {
+        self.on_fetch_error = policy;
+        self
+    }
+
+    /// Retry storing the state once before propagating the error.
+    pub fn with_store_retry(mut self, retry: bool) -> Self {
+        self.retry_store = retry;
+        self
+    }
 }
 
 const MAX_SERIALIZED_SIZE: usize = 64;
@@ -64,14 +111,15 @@ where
     }
 }
 
-impl<NVM, S> StateStorage<S> for SimpleStateStorage<NVM, S>
+impl<NVM, S, const SECTORS: usize, const KEYS: usize> SimpleStateStorage<NVM, S, SECTORS, KEYS>
 where
     NVM: NorFlash,
     S: Serialize + DeserializeOwned,
 {
-    type Error = sequential_storage::Error<NVM::Error>;
-
-    async fn store(&mut self, state: &State<S>) -> Result<(), Self::Error> {
+    async fn try_store(
+        &mut self,
+        state: &State<S>,
+    ) -> Result<(), sequential_storage::Error<NVM::Error>> {
         let mut data_buffer = [0u8; MAX_SERIALIZED_SIZE];
         let nvm_size = self.nvm.capacity() as u32;
 
@@ -86,27 +134,290 @@ where
         .await
     }
 
-    async fn fetch(&mut self) -> Result<State<S>, Self::Error> {
+    /// Fetch the stored state, or `None` if none has been stored, regardless of the [`FetchErrorPolicy`].
+    async fn try_fetch(
+        &mut self,
+    ) -> Result<Option<State<S>>, sequential_storage::Error<NVM::Error>> {
         let mut data_buffer = [0u8; MAX_SERIALIZED_SIZE];
 
         let nvm_size = self.nvm.capacity() as u32;
-        let state = sequential_storage::map::fetch_item::<(), State<S>, _>(
+        sequential_storage::map::fetch_item::<(), State<S>, _>(
             &mut self.nvm,
             0..nvm_size,
             &mut self.nvm_cache,
             &mut data_buffer,
             &(),
         )
-        .await?;
+        .await
+    }
+
+    /// Forget any pending request, by storing the current state without its request.
+    ///
+    /// This is a regular store, appending to the NVM like any other state transition. The scratch offset and minimum
+    /// version are kept, thus the current state must be readable; use [`clear_nvm`](Self::clear_nvm) otherwise.
+    pub async fn reset(&mut self) -> Result<(), sequential_storage::Error<NVM::Error>> {
+        let mut state = self.try_fetch().await?.unwrap_or_default();
+        state.clear();
+        self.store(&state).await
+    }
+
+    /// Erase the entire NVM, including all previously stored states.
+    ///
+    /// Contrary to [`reset`](Self::reset) this wears every page of the NVM, and is intended for factory provisioning
+    /// or recovering from a corrupted NVM. Fetching afterwards yields a state without a request.
+    pub async fn clear_nvm(&mut self) -> Result<(), sequential_storage::Error<NVM::Error>> {
+        let nvm_size = self.nvm.capacity() as u32;
+        sequential_storage::erase_all(&mut self.nvm, 0..nvm_size).await?;
+
+        // The cache refers to items which no longer exist.
+        self.nvm_cache = KeyPointerCache::new();
+        self.retained = None;
+        Ok(())
+    }
+}
+
+impl<NVM, S, const SECTORS: usize, const KEYS: usize> StateStorage<S>
+    for SimpleStateStorage<NVM, S, SECTORS, KEYS>
+where
+    NVM: NorFlash,
+    S: Serialize + DeserializeOwned,
+{
+    type Error = sequential_storage::Error<NVM::Error>;
+
+    async fn store(&mut self, state: &State<S>) -> Result<(), Self::Error> {
+        match self.try_store(state).await {
+            Err(_) if self.retry_store => self.try_store(state).await,
+            result => result,
+        }?;
 
-        let state = match state {
-            Some(state) => state,
-            None => {
-                // defmt::debug!("State NVM does not contain value");
-                State { request: None }
+        self.retained = Some((state.scratch_offset, state.min_version));
+        Ok(())
+    }
+
+    async fn fetch(&mut self) -> Result<State<S>, Self::Error> {
+        let state = match (self.try_fetch().await, self.on_fetch_error) {
+            (Ok(state), _) => state.unwrap_or_default(),
+            (Err(_), FetchErrorPolicy::FailSafe { min_version: floor }) => {
+                let (scratch_offset, min_version) = self.retained.unwrap_or((0, floor));
+                State {
+                    request: None,
+                    scratch_offset,
+                    min_version: u32::max(min_version, floor),
+                }
             }
+            (Err(e), _) => return Err(e),
         };
 
+        self.retained = Some((state.scratch_offset, state.min_version));
         Ok(state)
     }
+
+    /// Like the default, but failing on any error fetching the state regardless of the [`FetchErrorPolicy`],
+    /// as a failed fetch must never be mistaken for the expected state.
+    async fn compare_and_store(
+        &mut self,
+        expected: &State<S>,
+        new: &State<S>,
+    ) -> Result<bool, Self::Error>
+    where
+        S: PartialEq,
+    {
+        if self.try_fetch().await?.unwrap_or_default() != *expected {
+            return Ok(false);
+        }
+
+        self.store(new).await?;
+        Ok(true)
+    }
+}
+
+/// Failure of the state NVM as [`Error::Storage`], and any other failure to store or fetch the state as [`Error::State`].
+impl<E> From<sequential_storage::Error<E>> for Error {
+    fn from(error: sequential_storage::Error<E>) -> Self {
+        match error {
+            sequential_storage::Error::Storage { .. } => Error::Storage,
+            _ => Error::State,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Slot, Step, mock::nvm::MockFlash, state::Request,
+        strategies::swap_scootch::Request as ScootchRequest,
+    };
+
+    fn pending() -> State<ScootchRequest> {
+        State {
+            request: Some(Request {
+                strategy: ScootchRequest {
+                    slot_secondary: Slot(1),
+                },
+                step: Step(4),
+                revert: false,
+                completed: Default::default(),
+                restore_min_version: None,
+            }),
+            scratch_offset: 0,
+            min_version: 0,
+        }
+    }
+
+    #[test]
+    fn fail_closed() {
+        let mut nvm = MockFlash::<4, 1024>::new(2);
+        nvm.failing_accesses = 1;
+
+        let mut storage = SimpleStateStorage::<_, ScootchRequest>::new(nvm);
+
+        embassy_futures::block_on(async {
+            assert!(storage.fetch().await.is_err());
+            assert!(storage.fetch().await.unwrap().request.is_none());
+        });
+    }
+
+    #[test]
+    fn fail_safe() {
+        let mut nvm = MockFlash::<4, 1024>::new(2);
+        nvm.failing_accesses = 1;
+
+        let mut storage = SimpleStateStorage::<_, ScootchRequest>::new(nvm)
+            .with_fetch_error_policy(FetchErrorPolicy::FailSafe { min_version: 2 });
+
+        embassy_futures::block_on(async {
+            // A glitch on the first fetch after power-on boots the primary image, with the configured minimum version.
+            let fetched = storage.fetch().await.unwrap();
+            assert!(fetched.request.is_none());
+            assert_eq!(fetched.min_version, 2);
+
+            let mut state = pending();
+            state.min_version = 3;
+            storage.store(&state).await.unwrap();
+            storage.nvm.failing_accesses = 1;
+
+            // The pending request is ignored, but the minimum version is kept.
+            let fetched = storage.fetch().await.unwrap();
+            assert!(fetched.request.is_none());
+            assert_eq!(fetched.min_version, 3);
+            assert_eq!(storage.fetch().await.unwrap(), state);
+        });
+    }
+
+    #[test]
+    fn store_retry() {
+        let mut nvm = MockFlash::<4, 1024>::new(2);
+        nvm.failing_accesses = 1;
+
+        let mut storage = SimpleStateStorage::<_, ScootchRequest>::new(nvm);
+        embassy_futures::block_on(async {
+            assert!(storage.store(&pending()).await.is_err());
+        });
+
+        let mut nvm = MockFlash::<4, 1024>::new(2);
+        nvm.failing_accesses = 1;
+
+        let mut storage = SimpleStateStorage::<_, ScootchRequest>::new(nvm).with_store_retry(true);
+        embassy_futures::block_on(async {
+            storage.store(&pending()).await.unwrap();
+            assert_eq!(
+                storage.fetch().await.unwrap().request.unwrap().step,
+                Step(4)
+            );
+        });
+    }
+
+    #[test]
+    fn sectors() {
+        let mut storage =
+            SimpleStateStorage::<_, ScootchRequest, 4>::new(MockFlash::<4, 1024>::new(4));
+
+        embassy_futures::block_on(async {
+            assert!(storage.fetch().await.unwrap().request.is_none());
+
+            // Fill more than the default two sectors worth of states.
+            let mut state = pending();
+            for step in 0..100 {
+                state.request.as_mut().unwrap().step = Step(step);
+                storage.store(&state).await.unwrap();
+            }
+
+            assert_eq!(
+                storage.fetch().await.unwrap().request.unwrap().step,
+                Step(99)
+            );
+        });
+    }
+
+    #[test]
+    fn compare_and_store() {
+        let mut storage =
+            SimpleStateStorage::<_, ScootchRequest>::new(MockFlash::<4, 1024>::new(2))
+                .with_fetch_error_policy(FetchErrorPolicy::FailSafe { min_version: 0 });
+
+        embassy_futures::block_on(async {
+            let stale = State::default();
+            let mut advanced = pending();
+            advanced.request.as_mut().unwrap().step = Step(5);
+
+            assert_eq!(
+                storage.compare_and_store(&stale, &pending()).await,
+                Ok(true)
+            );
+
+            // The state has been replaced since `stale` was fetched, thus it is left unchanged.
+            assert_eq!(
+                storage.compare_and_store(&stale, &advanced).await,
+                Ok(false)
+            );
+            assert_eq!(storage.fetch().await.unwrap(), pending());
+
+            // A failing fetch is not taken for the default state, despite failing safe.
+            storage.store(&stale).await.unwrap();
+            storage.nvm.failing_accesses = 1;
+            assert!(storage.compare_and_store(&stale, &advanced).await.is_err());
+
+            assert_eq!(storage.compare_and_store(&stale, &advanced).await, Ok(true));
+            assert_eq!(storage.fetch().await.unwrap(), advanced);
+        });
+    }
+
+    #[test]
+    fn into_error() {
+        use crate::mock::nvm::MockFlashError;
+
+        let mut storage =
+            SimpleStateStorage::<_, ScootchRequest>::new(MockFlash::<4, 1024>::new(2));
+        storage.nvm.failing_accesses = usize::MAX;
+
+        let error = embassy_futures::block_on(storage.store(&pending())).unwrap_err();
+        assert_eq!(Error::from(error), Error::Storage);
+
+        let error = sequential_storage::Error::<MockFlashError>::FullStorage;
+        assert_eq!(Error::from(error), Error::State);
+    }
+
+    #[test]
+    fn reset() {
+        let mut storage =
+            SimpleStateStorage::<_, ScootchRequest>::new(MockFlash::<4, 1024>::new(2));
+
+        embassy_futures::block_on(async {
+            let mut state = pending();
+            state.scratch_offset = 1;
+            state.min_version = 3;
+            storage.store(&state).await.unwrap();
+            storage.reset().await.unwrap();
+
+            // Only the request is forgotten.
+            state.clear();
+            assert_eq!(storage.fetch().await.unwrap(), state);
+
+            storage.store(&pending()).await.unwrap();
+            storage.clear_nvm().await.unwrap();
+            assert!(storage.fetch().await.unwrap().request.is_none());
+            assert!(storage.nvm.data.iter().all(|byte| *byte == 0xFF));
+        });
+    }
 }
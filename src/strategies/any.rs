@@ -0,0 +1,218 @@
+//! Strategy of which the kind is chosen at runtime, for bootloaders supporting multiple strategies.
+//!
+//! The [`Request`] is an enum over the requests of the supported strategies, and is stored in the state as such.
+//! On resume the stored variant determines which strategy is reconstructed, for example to copy one update and swap the next.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DeviceWithPrimarySlot, DeviceWithScratch, Slot, Step,
+    strategies::{
+        BlockPlan, FromRequest, Strategy,
+        copy::{self, Copy},
+        swap_sabs::{self, SwapSABS},
+        swap_scootch::{self, SwapScootch},
+    },
+};
+
+/// Discriminant of the strategies supported by [`AnyStrategy`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StrategyKind {
+    Copy,
+    SwapScootch,
+    SwapSABS,
+}
+
+/// Request of any of the supported strategies, of which the variant selects the strategy.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Request {
+    Copy(copy::Request),
+    SwapScootch(swap_scootch::Request),
+    SwapSABS(swap_sabs::Request),
+}
+
+impl Request {
+    /// Kind of the strategy requested.
+    pub const fn kind(&self) -> StrategyKind {
+        match self {
+            Request::Copy(_) => StrategyKind::Copy,
+            Request::SwapScootch(_) => StrategyKind::SwapScootch,
+            Request::SwapSABS(_) => StrategyKind::SwapSABS,
+        }
+    }
+}
+
+/// Any of [`Copy`], [`SwapScootch`] or [`SwapSABS`], as selected by the [`Request`].
+pub enum AnyStrategy {
+    Copy(Copy),
+    SwapScootch(SwapScootch),
+    SwapSABS(SwapSABS),
+}
+
+impl AnyStrategy {
+    pub fn new(
+        device: &(impl DeviceWithScratch + DeviceWithPrimarySlot),
+        request: Request,
+    ) -> Self {
+        match request {
+            Request::Copy(request) => AnyStrategy::Copy(Copy::new(device, request)),
+            Request::SwapScootch(request) => {
+                AnyStrategy::SwapScootch(SwapScootch::new(device, request))
+            }
+            Request::SwapSABS(request) => AnyStrategy::SwapSABS(SwapSABS::new(device, request)),
+        }
+    }
+
+    /// Kind of the strategy in use.
+    pub const fn kind(&self) -> StrategyKind {
+        match self {
+            AnyStrategy::Copy(_) => StrategyKind::Copy,
+            AnyStrategy::SwapScootch(_) => StrategyKind::SwapScootch,
+            AnyStrategy::SwapSABS(_) => StrategyKind::SwapSABS,
+        }
+    }
+}
+
+impl<D: DeviceWithScratch + DeviceWithPrimarySlot> FromRequest<D> for AnyStrategy {
+    type Request = Request;
+
+    fn from_request(device: &D, request: Request) -> Self {
+        Self::new(device, request)
+    }
+}
+
+/// Plan of a single step of [`AnyStrategy`].
+pub type AnyStrategyPlan = BlockPlan;
+
+impl Strategy for AnyStrategy {
+    type Plan<'a> = AnyStrategyPlan;
+
+    fn last_step(&self) -> Step {
+        match self {
+            AnyStrategy::Copy(strategy) => strategy.last_step(),
+            AnyStrategy::SwapScootch(strategy) => strategy.last_step(),
+            AnyStrategy::SwapSABS(strategy) => strategy.last_step(),
+        }
+    }
+
+    fn plan(&self, step: Step) -> AnyStrategyPlan {
+        match self {
+            AnyStrategy::Copy(strategy) => strategy.plan(step),
+            AnyStrategy::SwapScootch(strategy) => strategy.plan(step),
+            AnyStrategy::SwapSABS(strategy) => strategy.plan(step),
+        }
+    }
+
+    fn image_slot(&self) -> Option<Slot> {
+        match self {
+            AnyStrategy::Copy(strategy) => strategy.image_slot(),
+            AnyStrategy::SwapScootch(strategy) => strategy.image_slot(),
+            AnyStrategy::SwapSABS(strategy) => strategy.image_slot(),
+        }
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        match self {
+            AnyStrategy::Copy(strategy) => strategy.boot_slot(),
+            AnyStrategy::SwapScootch(strategy) => strategy.boot_slot(),
+            AnyStrategy::SwapSABS(strategy) => strategy.boot_slot(),
+        }
+    }
+
+    fn revert(self) -> Option<Self> {
+        Some(match self {
+            AnyStrategy::Copy(strategy) => AnyStrategy::Copy(strategy.revert()?),
+            AnyStrategy::SwapScootch(strategy) => AnyStrategy::SwapScootch(strategy.revert()?),
+            AnyStrategy::SwapSABS(strategy) => AnyStrategy::SwapSABS(strategy.revert()?),
+        })
+    }
+
+    fn is_safe_point(&self, step: Step) -> bool {
+        match self {
+            AnyStrategy::Copy(strategy) => strategy.is_safe_point(step),
+            AnyStrategy::SwapScootch(strategy) => strategy.is_safe_point(step),
+            AnyStrategy::SwapSABS(strategy) => strategy.is_safe_point(step),
+        }
+    }
+
+    fn requires_scratch(&self) -> bool {
+        match self {
+            AnyStrategy::Copy(strategy) => strategy.requires_scratch(),
+            AnyStrategy::SwapScootch(strategy) => strategy.requires_scratch(),
+            AnyStrategy::SwapSABS(strategy) => strategy.requires_scratch(),
+        }
+    }
+
+    fn phase_name(&self, step: Step) -> &'static str {
+        match self {
+            AnyStrategy::Copy(strategy) => strategy.phase_name(step),
+            AnyStrategy::SwapScootch(strategy) => strategy.phase_name(step),
+            AnyStrategy::SwapSABS(strategy) => strategy.phase_name(step),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        flow::{BootFlow, Phase},
+        mock::generic::{GenericMock, SECONDARY},
+        runner::StrategyRunner,
+        state::{State, StateStorage, mem::MemStateStorage},
+    };
+
+    #[cfg(feature = "simple_state")]
+    #[test]
+    fn persisted_kind() {
+        let request = Request::SwapScootch(swap_scootch::Request {
+            slot_secondary: SECONDARY,
+        });
+
+        let mut buffer = [0u8; 64];
+        let stored = postcard::to_slice(&State::new_request(request.clone()), &mut buffer).unwrap();
+
+        // After a reboot the stored variant selects the strategy.
+        let state: State<Request> = postcard::from_bytes(stored).unwrap();
+        let device = GenericMock::new(4, 1);
+        let strategy = AnyStrategy::new(&device, state.request.unwrap().strategy);
+        assert_eq!(strategy.kind(), StrategyKind::SwapScootch);
+        assert_eq!(strategy.kind(), request.kind());
+        assert!(strategy.requires_scratch());
+    }
+
+    #[test]
+    fn resume() {
+        let mut storage = MemStateStorage::new();
+        for (request, kind) in [
+            (
+                Request::Copy(copy::Request {
+                    slot_secondary: SECONDARY,
+                    slot_backup: None,
+                    image_pages: None,
+                }),
+                StrategyKind::Copy,
+            ),
+            (
+                Request::SwapScootch(swap_scootch::Request {
+                    slot_secondary: SECONDARY,
+                }),
+                StrategyKind::SwapScootch,
+            ),
+        ] {
+            embassy_futures::block_on(storage.store(&State::new_request(request))).unwrap();
+
+            let mut flow = BootFlow::new(StrategyRunner::new(GenericMock::new(4, 1), &mut storage));
+            assert_eq!(
+                embassy_futures::block_on(flow.resume::<AnyStrategy>()),
+                Ok(Phase::Trialing)
+            );
+
+            let device = flow.runner().device();
+            assert_eq!(device.primary, GenericMock::image_b(4));
+            // Only a swap keeps the previous image around.
+            let swapped = device.secondary == GenericMock::image_a(4);
+            assert_eq!(swapped, kind == StrategyKind::SwapScootch);
+        }
+    }
+}
@@ -9,19 +9,26 @@ use core::num::NonZeroU16;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    CopyOperation, DeviceWithPrimarySlot, MemoryLocation, Page, Slot, Step, strategies::Strategy,
+    DeviceWithPrimarySlot, MemoryLocation, Page, Slot, Step,
+    strategies::{BlockPlan, FromRequest, Strategy},
 };
 
 /// Request to boot a secondary image, with an optional backup if the secondary image is invalid.
 ///
 /// * Note that if the backup is not provided, the device might brick itself.
 /// * Note that the backup should have run successfully previously to ensure successful operation.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Request {
     /// The image to copy to the primary slot.
     pub slot_secondary: Slot,
     /// The image to copy to the primary slot when the secondary image fails to boot.
     pub slot_backup: Option<Slot>,
+    /// Only copy the first `image_pages` pages, for an image that does not fill the entire slot, or `None` for all pages.
+    ///
+    /// Saves erasing the blank pages trailing the image, for example for an image of which the size is known from its
+    /// [`ImageHeader`](crate::image::ImageHeader::pages). The pages of the primary slot beyond the image are left as is.
+    /// Reverting copies the entire backup, as its size is unknown.
+    pub image_pages: Option<NonZeroU16>,
 }
 
 pub struct Copy {
@@ -40,26 +47,58 @@ impl Copy {
     }
 }
 
+impl<D: DeviceWithPrimarySlot> FromRequest<D> for Copy {
+    type Request = Request;
+
+    fn from_request(device: &D, request: Request) -> Self {
+        Self::new(device, request)
+    }
+}
+
+/// Plan of a single step of [`Copy`].
+pub type CopyPlan = BlockPlan;
+
 impl Strategy for Copy {
+    type Plan<'a> = CopyPlan;
+
     fn last_step(&self) -> Step {
         // We only need two steps: one to copy all over, one to boot.
         // More steps are not necessary because on resume we can just start over.
         Step(1)
     }
 
-    fn plan(&self, _step: Step) -> impl Iterator<Item = CopyOperation> {
-        (0..self.num_pages.get())
-            .map(Page)
-            .map(move |page| CopyOperation {
-                from: MemoryLocation {
-                    slot: self.request.slot_secondary,
-                    page,
-                },
-                to: MemoryLocation {
-                    slot: self.slot_primary,
-                    page,
-                },
-            })
+    fn plan(&self, step: Step) -> CopyPlan {
+        // Nothing is left to copy once the last step has been reached.
+        let num_pages = if step < self.last_step() {
+            self.request
+                .image_pages
+                .map_or(self.num_pages, |image_pages| {
+                    image_pages.min(self.num_pages)
+                })
+                .get()
+        } else {
+            0
+        };
+
+        BlockPlan::new(
+            MemoryLocation {
+                slot: self.request.slot_secondary,
+                page: Page(0),
+            },
+            MemoryLocation {
+                slot: self.slot_primary,
+                page: Page(0),
+            },
+            num_pages,
+        )
+    }
+
+    fn image_slot(&self) -> Option<Slot> {
+        Some(self.request.slot_secondary)
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        Some(self.slot_primary)
     }
 
     fn revert(self) -> Option<Self> {
@@ -68,6 +107,7 @@ impl Strategy for Copy {
                 request: Request {
                     slot_secondary: slot_backup,
                     slot_backup: None,
+                    image_pages: None,
                 },
                 num_pages: self.num_pages,
                 slot_primary: self.slot_primary,
@@ -81,15 +121,35 @@ impl Strategy for Copy {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::run_strategy_to_completion;
 
-    fn perform_copy(device: &mut impl DeviceWithPrimarySlot, strategy: &Copy) {
-        for step_i in 0..strategy.last_step().0 {
-            let step = Step(step_i);
-            for operation in strategy.plan(step) {
-                embassy_futures::block_on(async {
-                    device.copy(operation).await.unwrap();
-                })
-            }
+    #[test]
+    fn plan_ops() {
+        use crate::{
+            Operation,
+            mock::tri_slot::{ALPHA, BETA, MockDevice, PRIMARY},
+        };
+
+        let device = MockDevice::new();
+        let strategy = Copy::new(
+            &device,
+            Request {
+                slot_secondary: BETA,
+                slot_backup: Some(ALPHA),
+                image_pages: None,
+            },
+        );
+
+        let operations: std::vec::Vec<_> = strategy.plan_ops(Step(0)).collect();
+        assert_eq!(operations.len(), 2 * strategy.plan(Step(0)).count());
+
+        // Every page of the primary slot is erased right before it is copied onto.
+        for pair in operations.chunks(2) {
+            let [Operation::Erase(erased), Operation::Copy(operation)] = pair else {
+                panic!("expected an erase followed by a copy, got {pair:?}");
+            };
+            assert_eq!(erased.location.slot, PRIMARY);
+            assert_eq!(erased.location, operation.to);
         }
     }
 
@@ -103,6 +163,7 @@ mod tests {
             Request {
                 slot_secondary: BETA,
                 slot_backup: Some(ALPHA),
+                image_pages: None,
             },
         );
 
@@ -110,7 +171,7 @@ mod tests {
         assert_eq!(device.alpha, IMAGE_A);
         assert_eq!(device.beta, IMAGE_B);
 
-        perform_copy(&mut device, &strategy);
+        run_strategy_to_completion(&mut device, &strategy);
 
         assert_eq!(device.primary, IMAGE_B);
         assert_eq!(device.alpha, IMAGE_A);
@@ -122,10 +183,38 @@ mod tests {
 
         let strategy = strategy.revert().unwrap();
 
-        perform_copy(&mut device, &strategy);
+        run_strategy_to_completion(&mut device, &strategy);
 
         assert_eq!(device.primary, IMAGE_A);
         assert_eq!(device.alpha, IMAGE_A);
         assert_eq!(device.beta, IMAGE_B);
     }
+
+    #[test]
+    fn image_pages() {
+        use crate::mock::tri_slot::{BETA, IMAGE_A, IMAGE_B, MockDevice, PRIMARY};
+
+        let mut device = MockDevice::new();
+        let strategy = Copy::from_request(
+            &device,
+            Request {
+                slot_secondary: BETA,
+                slot_backup: None,
+                image_pages: NonZeroU16::new(1),
+            },
+        );
+
+        assert_eq!(strategy.total_operations(), 1);
+
+        run_strategy_to_completion(&mut device, &strategy);
+
+        assert_eq!(device.primary, [IMAGE_B[0], IMAGE_A[1], IMAGE_A[2]]);
+        for (page, wear) in [(0, 1), (1, 0), (2, 0)] {
+            let location = MemoryLocation {
+                slot: PRIMARY,
+                page: Page(page),
+            };
+            assert_eq!(device.wear.wear_at(location), wear);
+        }
+    }
 }
@@ -0,0 +1,152 @@
+//! Strategy to copy a slot from secondary to the primary slot, after first backing up the primary slot.
+//!
+//! Similar to the plain copy strategy, but guarantees that a rollback target exists: the image that was running
+//! before the update is kept in the backup slot, and is restored when the secondary image fails to boot.
+//!
+//! The backup slot is overwritten on every update, hence it should not hold an image that needs to be kept around.
+
+use core::num::NonZeroU16;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DeviceWithPrimarySlot, MemoryLocation, Page, Slot, Step,
+    strategies::{BlockPlan, FromRequest, Strategy},
+};
+
+/// Request to boot a secondary image, backing up the current primary image.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Request {
+    /// The image to copy to the primary slot.
+    pub slot_secondary: Slot,
+    /// The slot to which the current primary image is backed up.
+    pub slot_backup: Slot,
+}
+
+pub struct CopyWithBackup {
+    request: Request,
+    num_pages: NonZeroU16,
+    slot_primary: Slot,
+    /// Whether the backup is restored, instead of the secondary image being installed.
+    restore: bool,
+}
+
+impl CopyWithBackup {
+    pub fn new(device: &impl DeviceWithPrimarySlot, request: Request) -> Self {
+        Self {
+            request,
+            num_pages: device.page_count(),
+            slot_primary: device.get_primary(),
+            restore: false,
+        }
+    }
+}
+
+impl<D: DeviceWithPrimarySlot> FromRequest<D> for CopyWithBackup {
+    type Request = Request;
+
+    fn from_request(device: &D, request: Request) -> Self {
+        Self::new(device, request)
+    }
+}
+
+/// Plan of a single step of [`CopyWithBackup`].
+pub type CopyWithBackupPlan = BlockPlan;
+
+impl Strategy for CopyWithBackup {
+    type Plan<'a> = CopyWithBackupPlan;
+
+    fn last_step(&self) -> Step {
+        // When restoring, the backup must not be overwritten with the failed image.
+        if self.restore { Step(1) } else { Step(2) }
+    }
+
+    fn plan(&self, step: Step) -> CopyWithBackupPlan {
+        let (from, to) = match (self.restore, step.0) {
+            (false, 0) => (self.slot_primary, self.request.slot_backup),
+            (false, _) => (self.request.slot_secondary, self.slot_primary),
+            (true, _) => (self.request.slot_backup, self.slot_primary),
+        };
+
+        // Nothing is left to copy once the last step has been reached.
+        let num_pages = if step < self.last_step() {
+            self.num_pages.get()
+        } else {
+            0
+        };
+
+        BlockPlan::new(
+            MemoryLocation {
+                slot: from,
+                page: Page(0),
+            },
+            MemoryLocation {
+                slot: to,
+                page: Page(0),
+            },
+            num_pages,
+        )
+    }
+
+    fn image_slot(&self) -> Option<Slot> {
+        Some(self.request.slot_secondary)
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        Some(self.slot_primary)
+    }
+
+    fn revert(self) -> Option<Self> {
+        if self.restore {
+            None
+        } else {
+            Some(Self {
+                restore: true,
+                ..self
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::run_strategy_to_completion;
+
+    #[test]
+    fn test() {
+        use crate::mock::tri_slot::{ALPHA, BETA, IMAGE_A, IMAGE_B, MockDevice, PRIMARY};
+
+        let mut device = MockDevice::new();
+        device.alpha = [0xFF; 3];
+
+        let strategy = CopyWithBackup::new(
+            &device,
+            Request {
+                slot_secondary: BETA,
+                slot_backup: ALPHA,
+            },
+        );
+
+        run_strategy_to_completion(&mut device, &strategy);
+
+        assert_eq!(device.primary, IMAGE_B);
+        assert_eq!(device.alpha, IMAGE_A);
+        assert_eq!(device.beta, IMAGE_B);
+
+        assert!(device.wear.check_slot(PRIMARY, 1));
+        assert!(device.wear.check_slot(ALPHA, 1));
+        assert!(device.wear.check_slot(BETA, 0));
+
+        let strategy = strategy.revert().unwrap();
+
+        run_strategy_to_completion(&mut device, &strategy);
+
+        assert_eq!(device.primary, IMAGE_A);
+        assert_eq!(device.alpha, IMAGE_A);
+        assert_eq!(device.beta, IMAGE_B);
+
+        // The backup is not overwritten by the failed image.
+        assert!(device.wear.check_slot(ALPHA, 1));
+        assert!(strategy.revert().is_none());
+    }
+}
@@ -0,0 +1,199 @@
+//! Strategy to decompress a compressed secondary image into the primary slot, completely forgetting what is in the primary slot.
+//!
+//! Useful for over-the-air updates over limited bandwidth, for which only a compressed image is transferred.
+//! The decompression itself is performed by a [`DecompressingDevice`](crate::devices::decompressing::DecompressingDevice),
+//! with a [`Decompressor`](crate::devices::decompressing::Decompressor) of your choosing.
+//!
+//! As an image can not be compressed again, there is no way back once the primary slot has been overwritten.
+
+use core::num::NonZeroU16;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DeviceWithPrimarySlot, MemoryLocation, Page, Slot, Step,
+    strategies::{BlockPlan, FromRequest, Strategy},
+};
+
+/// Request to decompress a compressed image into the primary slot.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Request {
+    /// The slot holding the compressed image.
+    pub slot_compressed: Slot,
+}
+
+pub struct DecompressCopy {
+    request: Request,
+    num_pages: NonZeroU16,
+    slot_primary: Slot,
+}
+
+impl DecompressCopy {
+    pub fn new(device: &impl DeviceWithPrimarySlot, request: Request) -> Self {
+        Self {
+            request,
+            num_pages: device.page_count(),
+            slot_primary: device.get_primary(),
+        }
+    }
+}
+
+impl<D: DeviceWithPrimarySlot> FromRequest<D> for DecompressCopy {
+    type Request = Request;
+
+    fn from_request(device: &D, request: Request) -> Self {
+        Self::new(device, request)
+    }
+}
+
+/// Plan of a single step of [`DecompressCopy`].
+pub type DecompressCopyPlan = BlockPlan;
+
+impl Strategy for DecompressCopy {
+    type Plan<'a> = DecompressCopyPlan;
+
+    fn last_step(&self) -> Step {
+        // A single step decompressing the entire image, as the stream can only be resumed from its start.
+        Step(1)
+    }
+
+    fn plan(&self, step: Step) -> DecompressCopyPlan {
+        let num_pages = if step < self.last_step() {
+            self.num_pages.get()
+        } else {
+            0
+        };
+
+        // Page `n` of the compressed slot denotes page `n` of the decompressed image.
+        BlockPlan::new(
+            MemoryLocation {
+                slot: self.request.slot_compressed,
+                page: Page(0),
+            },
+            MemoryLocation {
+                slot: self.slot_primary,
+                page: Page(0),
+            },
+            num_pages,
+        )
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        Some(self.slot_primary)
+    }
+
+    fn revert(self) -> Option<Self> {
+        // The original image has been overwritten, and the compressed image can not be compressed again.
+        None
+    }
+
+    fn phase_name(&self, step: Step) -> &'static str {
+        if step < self.last_step() {
+            "decompress"
+        } else {
+            "boot"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Error,
+        devices::{
+            decompressing::{DecompressingDevice, Decompressor},
+            nor_flash::NorFlashDevice,
+        },
+        mock::{PowerLoss, nvm::MockFlash, run_strategy_with_power_loss},
+    };
+
+    type Flash = MockFlash<4, 64>;
+
+    const PAGE_SIZE: u32 = 128;
+    const PRIMARY: Slot = Slot(0);
+    const COMPRESSED: Slot = Slot(1);
+
+    /// Passes the stream on as is, a few bytes at a time to exercise the streaming.
+    struct Identity;
+
+    impl Decompressor for Identity {
+        fn reset(&mut self) {}
+
+        fn decompress(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), Error> {
+            let len = input.len().min(output.len()).min(5);
+            output[..len].copy_from_slice(&input[..len]);
+            Ok((len, len))
+        }
+    }
+
+    fn no_boot(_slot: Slot) -> ! {
+        unimplemented!()
+    }
+
+    #[test]
+    fn identity() {
+        for power_loss in [None, Some(2)] {
+            let mut partitions = [Flash::new(6), Flash::new(6)];
+            for (offset, byte) in partitions[1].data.iter_mut().enumerate() {
+                *byte = (offset % 251) as u8;
+            }
+
+            let inner =
+                NorFlashDevice::<_, 2>::new(partitions, PAGE_SIZE, PRIMARY, PRIMARY, no_boot)
+                    .unwrap();
+            let mut device = DecompressingDevice::<_, _, 24>::new(inner, Identity, COMPRESSED);
+            let strategy = DecompressCopy::new(
+                &device,
+                Request {
+                    slot_compressed: COMPRESSED,
+                },
+            );
+            run_strategy_with_power_loss(
+                &mut device,
+                &strategy,
+                power_loss.map(|operations| PowerLoss {
+                    step: Step(0),
+                    operations,
+                }),
+            );
+
+            let partitions = device.inner().partitions();
+            assert_eq!(partitions[0].data, partitions[1].data);
+            assert!(strategy.revert().is_none());
+        }
+    }
+
+    #[test]
+    fn out_of_order() {
+        use crate::{CopyOperation, Device};
+
+        let mut partitions = [Flash::new(6), Flash::new(6)];
+        for (offset, byte) in partitions[1].data.iter_mut().enumerate() {
+            *byte = (offset % 251) as u8;
+        }
+
+        let inner =
+            NorFlashDevice::<_, 2>::new(partitions, PAGE_SIZE, PRIMARY, PRIMARY, no_boot).unwrap();
+        let mut device = DecompressingDevice::<_, _, 24>::new(inner, Identity, COMPRESSED);
+
+        // Skipping ahead decompresses the stream up to the page, and going back starts over.
+        for page in [2, 0, 1] {
+            let operation = CopyOperation {
+                from: MemoryLocation {
+                    slot: COMPRESSED,
+                    page: Page(page),
+                },
+                to: MemoryLocation {
+                    slot: PRIMARY,
+                    page: Page(page),
+                },
+            };
+            embassy_futures::block_on(device.copy(operation)).unwrap();
+        }
+
+        let partitions = device.inner().partitions();
+        let len = 3 * PAGE_SIZE as usize;
+        assert_eq!(partitions[0].data[..len], partitions[1].data[..len]);
+    }
+}
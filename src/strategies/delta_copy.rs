@@ -0,0 +1,222 @@
+//! Strategy to copy only the changed pages from secondary to the primary slot, leaving the other pages intact.
+//!
+//! Useful for small patches, for which a full copy would needlessly wear all pages of the primary slot.
+//! Like [`Copy`](super::copy::Copy) without a backup there is no way back once the primary slot has been patched.
+//!
+//! To keep the request small enough to be persisted, the image is divided into [`REGIONS`] regions of equal size,
+//! and the request marks which of these regions have changed. For images of up to [`REGIONS`] pages a region is a single page.
+
+use core::num::NonZeroU16;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DeviceWithPrimarySlot, MemoryLocation, Page, Slot, Step,
+    strategies::{BlockPlan, FromRequest, Strategy},
+};
+
+/// Number of regions in which the image is divided.
+pub const REGIONS: u16 = u32::BITS as u16;
+
+/// Request to patch the primary image with the changed regions of a secondary image.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Request {
+    /// The image to copy the changed regions from.
+    pub slot_secondary: Slot,
+    /// Bitmap of changed regions, of which bit `n` denotes region `n`.
+    pub dirty_regions: u32,
+}
+
+impl Request {
+    /// Request for a secondary image of `num_pages` pages of which `dirty_pages` have changed.
+    ///
+    /// Dirty pages past the end of the image are ignored.
+    pub fn new(
+        slot_secondary: Slot,
+        num_pages: NonZeroU16,
+        dirty_pages: impl IntoIterator<Item = Page>,
+    ) -> Self {
+        let region_size = region_size(num_pages);
+        let dirty_regions = dirty_pages
+            .into_iter()
+            .filter(|page| page.0 < num_pages.get())
+            .fold(0, |regions, page| regions | 1 << (page.0 / region_size));
+
+        Self {
+            slot_secondary,
+            dirty_regions,
+        }
+    }
+}
+
+/// Number of pages in a single region.
+const fn region_size(num_pages: NonZeroU16) -> u16 {
+    num_pages.get().div_ceil(REGIONS)
+}
+
+pub struct DeltaCopy {
+    request: Request,
+    num_pages: NonZeroU16,
+    slot_primary: Slot,
+}
+
+impl DeltaCopy {
+    pub fn new(device: &impl DeviceWithPrimarySlot, request: Request) -> Self {
+        Self {
+            request,
+            num_pages: device.page_count(),
+            slot_primary: device.get_primary(),
+        }
+    }
+}
+
+impl<D: DeviceWithPrimarySlot> FromRequest<D> for DeltaCopy {
+    type Request = Request;
+
+    fn from_request(device: &D, request: Request) -> Self {
+        Self::new(device, request)
+    }
+}
+
+/// Plan of a single step of [`DeltaCopy`], copying a single region.
+pub type DeltaCopyPlan = BlockPlan;
+
+impl Strategy for DeltaCopy {
+    type Plan<'a> = DeltaCopyPlan;
+
+    fn last_step(&self) -> Step {
+        // A step for each changed region, such that an interrupted patch does not start over.
+        Step(self.request.dirty_regions.count_ones())
+    }
+
+    fn plan(&self, step: Step) -> DeltaCopyPlan {
+        let region_size = region_size(self.num_pages);
+
+        // The region of this step is the n-th changed region.
+        let region = (0..REGIONS)
+            .filter(|region| self.request.dirty_regions & (1 << region) != 0)
+            .nth(step.0 as usize);
+
+        let (start, end) = match region {
+            Some(region) => {
+                // Widened, as the last region may end past the last page.
+                let num_pages = u32::from(self.num_pages.get());
+                let start = u32::min(u32::from(region) * u32::from(region_size), num_pages);
+                let end = u32::min(start + u32::from(region_size), num_pages);
+                (start as u16, end as u16)
+            }
+            None => (0, 0),
+        };
+
+        BlockPlan::new(
+            MemoryLocation {
+                slot: self.request.slot_secondary,
+                page: Page(start),
+            },
+            MemoryLocation {
+                slot: self.slot_primary,
+                page: Page(start),
+            },
+            end - start,
+        )
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        Some(self.slot_primary)
+    }
+
+    fn revert(self) -> Option<Self> {
+        // The original pages have been overwritten.
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Device,
+        mock::{
+            run_strategy_to_completion,
+            tri_slot::{ALPHA, BETA, IMAGE_A, MockDevice, PRIMARY},
+        },
+    };
+
+    #[test]
+    fn test() {
+        let mut device = MockDevice::new();
+
+        // Only the second page differs between the images.
+        device.beta = IMAGE_A;
+        device.beta[1] = 0xAA;
+
+        let request = Request::new(BETA, device.page_count(), [Page(1)]);
+        assert_eq!(request.dirty_regions, 0b010);
+
+        let strategy = DeltaCopy::new(&device, request);
+        assert_eq!(strategy.last_step(), Step(1));
+
+        run_strategy_to_completion(&mut device, &strategy);
+
+        assert_eq!(device.primary, [0x01, 0xAA, 0x03]);
+
+        let page = |page| MemoryLocation {
+            slot: PRIMARY,
+            page: Page(page),
+        };
+        assert_eq!(device.wear.wear_at(page(0)), 0);
+        assert_eq!(device.wear.wear_at(page(1)), 1);
+        assert_eq!(device.wear.wear_at(page(2)), 0);
+        assert!(device.wear.check_slot(ALPHA, 0));
+    }
+
+    #[test]
+    fn regions() {
+        use crate::mock::generic::{GenericMock, PRIMARY, SECONDARY};
+
+        // 100 pages are divided in regions of 4 pages, of which the last region is partial.
+        let mut device = GenericMock::new(100, 1);
+        let request = Request::new(SECONDARY, device.page_count(), [Page(5), Page(6), Page(99)]);
+        assert_eq!(request.dirty_regions, 1 << 1 | 1 << 24);
+
+        let strategy = DeltaCopy::new(&device, request);
+        run_strategy_to_completion(&mut device, &strategy);
+
+        let image_b = GenericMock::image_b(100);
+        for page in 0..100u16 {
+            let dirty = (4..8).contains(&page) || page >= 96;
+            assert_eq!(
+                device.primary[page as usize] == image_b[page as usize],
+                dirty
+            );
+            assert_eq!(
+                device.wear.wear_at(MemoryLocation {
+                    slot: PRIMARY,
+                    page: Page(page),
+                }),
+                dirty as usize
+            );
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        // Pages past the end of the image are ignored rather than marking a region that does not exist.
+        let num_pages = NonZeroU16::new(u16::MAX).unwrap();
+        let request = Request::new(BETA, num_pages, [Page(0), Page(u16::MAX)]);
+        assert_eq!(request.dirty_regions, 1);
+
+        // The last region of the largest image ends at the last page.
+        let strategy = DeltaCopy {
+            request: Request {
+                slot_secondary: BETA,
+                dirty_regions: 1 << (REGIONS - 1),
+            },
+            num_pages,
+            slot_primary: PRIMARY,
+        };
+        let plan: std::vec::Vec<_> = strategy.plan(Step(0)).collect();
+        assert_eq!(plan.len(), 2047);
+        assert_eq!(plan.last().unwrap().to.page, Page(u16::MAX - 1));
+    }
+}
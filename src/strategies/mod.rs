@@ -1,22 +1,833 @@
 //! Slot activation strategies like moving, copying or executing in place.
 
-use crate::{CopyOperation, Step};
+use core::{iter::FusedIterator, ops::Range};
 
+use crate::{
+    CopyOperation, Device, EraseOperation, Error, MemoryLocation, Operation, Page, Slot, Step,
+};
+
+pub mod any;
 pub mod copy;
+pub mod copy_with_backup;
+pub mod decompress_copy;
+pub mod delta_copy;
+#[cfg(feature = "heapless")]
+pub mod planned;
+pub mod prelude;
+pub mod swap;
+pub mod swap_banks;
 pub mod swap_sabs;
 pub mod swap_scootch;
+pub mod wear_leveling;
 pub mod xip;
+pub mod xip_or_copy;
+pub mod xip_then_copy;
+
+/// Errors of a strategy that does not fit the device or its state, as opposed to a failing device.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StrategyError {
+    /// The step lies beyond the last step of the strategy, for example as stored for another strategy.
+    StepOutOfRange,
+    /// A planned page lies beyond the end of its slot.
+    PageOverflow,
+    /// A planned slot does not exist on the device.
+    InvalidSlot,
+}
+
+impl StrategyError {
+    const fn message(&self) -> &'static str {
+        match self {
+            StrategyError::StepOutOfRange => "step beyond the last step of the strategy",
+            StrategyError::PageOverflow => "planned page beyond the end of its slot",
+            StrategyError::InvalidSlot => "planned slot does not exist",
+        }
+    }
+}
+
+impl core::fmt::Display for StrategyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for StrategyError {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=str}", self.message())
+    }
+}
+
+impl From<StrategyError> for Error {
+    fn from(error: StrategyError) -> Self {
+        match error {
+            StrategyError::PageOverflow => Error::OutOfRange,
+            StrategyError::StepOutOfRange | StrategyError::InvalidSlot => Error::Misconfigured,
+        }
+    }
+}
 
 /// A slot activation strategy.
 pub trait Strategy: Sized {
     /// The step which denotes that the swap has been completed, and that boot should occur.
     ///
-    /// **Warning**: for this specific step and any subsequent step planning a `CopyOperation` is undefined behaviour.
+    /// Must be pure: it is called repeatedly, for example on every iteration of the runner, and has to yield the same step
+    /// every time for the same strategy.
+    #[must_use]
     fn last_step(&self) -> Step;
 
+    /// Iterator over the operations of a single step, as returned by [`plan`](Self::plan).
+    ///
+    /// Being a named type, a partially consumed plan can be stored, and cloned to resume from the same operation.
+    type Plan<'a>: Iterator<Item = CopyOperation> + FusedIterator + Clone
+    where
+        Self: 'a;
+
     /// Plan the operations to be executed for a given step.
-    fn plan(&self, step: Step) -> impl Iterator<Item = CopyOperation>;
+    ///
+    /// For the last step and any subsequent step no operations are planned, as all that is left to do is boot.
+    #[must_use]
+    fn plan(&self, step: Step) -> Self::Plan<'_>;
 
     /// Convert this strategy into one that performs the reverse operation, if at all possible.
+    ///
+    /// Consumes the strategy, thus discarding the result loses the means to revert:
+    ///
+    /// ```compile_fail
+    /// # #![deny(unused_must_use)]
+    /// # use core::num::NonZeroU16;
+    /// # use bootlick::{CopyOperation, Device, DeviceWithPrimarySlot, Error, Slot};
+    /// # use bootlick::strategies::{Strategy, copy::{Copy, Request}};
+    /// # struct MyDevice;
+    /// # impl Device for MyDevice {
+    /// #     async fn copy(&mut self, _operation: CopyOperation) -> Result<(), Error> { Ok(()) }
+    /// #     fn boot(self, _slot: Slot) -> ! { loop {} }
+    /// #     fn page_count(&self) -> NonZeroU16 { NonZeroU16::MIN }
+    /// #     fn capacity_pages(&self, _slot: Slot) -> u16 { 1 }
+    /// # }
+    /// # impl DeviceWithPrimarySlot for MyDevice {
+    /// #     fn get_primary(&self) -> Slot { Slot(0) }
+    /// # }
+    /// let strategy = Copy::new(&MyDevice, Request { slot_secondary: Slot(1), slot_backup: Some(Slot(2)), image_pages: None });
+    /// strategy.revert();
+    /// ```
+    #[must_use]
     fn revert(self) -> Option<Self>;
+
+    /// Slot holding the new image before it is installed, which the [`BootFlow`](crate::flow::BootFlow) checks before the first step.
+    ///
+    /// Defaults to `None`, for strategies of which the image can not be inspected before it is installed, for example
+    /// because it is compressed or only consists of the changed pages.
+    fn image_slot(&self) -> Option<Slot> {
+        None
+    }
+
+    /// Slot to boot once the last step has been reached, for example the target slot of an image executed in place.
+    ///
+    /// Refers to the slot of the strategy at hand, thus the reverted strategy yields the slot to fall back to.
+    /// Defaults to `None`, denoting the [primary slot](crate::DeviceWithPrimarySlot::get_primary) of the device.
+    fn boot_slot(&self) -> Option<Slot> {
+        None
+    }
+
+    /// Whether reaching `step` is persisted, such that the strategy resumes from `step` after power loss.
+    ///
+    /// Progress past a step that is not a safe point is not stored, thus power loss re-executes all steps since the
+    /// last safe point. Only mark a step as unsafe if every operation from the previous safe point up to it still finds
+    /// its source intact when executed again. The last step is always persisted, regardless of this method.
+    ///
+    /// Defaults to every step being a safe point, which is what all strategies of this crate require.
+    fn is_safe_point(&self, _step: Step) -> bool {
+        true
+    }
+
+    /// Whether the strategy copies pages into the scratch slot, for example to warn if the device has none configured.
+    fn requires_scratch(&self) -> bool {
+        false
+    }
+
+    /// All operations of this strategy in order of execution, each tagged with the step it is planned in.
+    ///
+    /// Useful for reviewing or diffing strategies without touching any hardware.
+    fn plan_all(&self) -> impl Iterator<Item = (Step, CopyOperation)> {
+        Step::range(Step::default(), self.last_step())
+            .flat_map(move |step| self.plan(step).map(move |operation| (step, operation)))
+    }
+
+    /// The operations of [`plan`](Self::plan) with the erase of each destination made explicit, in order of execution.
+    ///
+    /// Every destination is erased right before it is copied onto, as erasing any earlier could destroy a page that is
+    /// still to be read within the step. Copies of a page onto itself are not preceded by an erase, as that would destroy the page.
+    ///
+    /// The runner executes this stream, dispatching the erases only when running
+    /// [with explicit erases](crate::runner::StrategyRunner::run_with_explicit_erase), as [`Device::copy`] erases by itself.
+    fn plan_ops(&self, step: Step) -> impl Iterator<Item = Operation> {
+        self.plan(step).flat_map(|operation| {
+            let erase = (!operation.is_in_place()).then_some(Operation::Erase(EraseOperation {
+                location: operation.to,
+            }));
+            erase.into_iter().chain([Operation::Copy(operation)])
+        })
+    }
+
+    /// Human readable name of the logical phase of `step`, for example for progress screens and logging.
+    ///
+    /// The step remains the source of truth; the name is purely informational.
+    fn phase_name(&self, step: Step) -> &'static str {
+        if step < self.last_step() {
+            "copy"
+        } else {
+            "boot"
+        }
+    }
+
+    /// Check that every planned operation fits within the slots of `device`.
+    ///
+    /// Catches a strategy that was set up for another layout, for example with more pages than the device has,
+    /// a scratch slot that is too small, or a slot that does not exist.
+    #[must_use = "a strategy that does not fit the device copies outside of its slots"]
+    fn verify_layout(&self, device: &impl Device) -> Result<(), StrategyError> {
+        let slot_count = device.slot_count();
+        let fits = |location: MemoryLocation| {
+            let capacity = device.capacity_pages(location.slot);
+            if location.slot.0 >= slot_count || capacity == 0 {
+                return Err(StrategyError::InvalidSlot);
+            }
+            if location.page.0 >= capacity {
+                return Err(StrategyError::PageOverflow);
+            }
+            Ok(())
+        };
+
+        self.plan_all().try_for_each(|(_, operation)| {
+            fits(operation.from)?;
+            fits(operation.to)
+        })
+    }
+
+    /// Check that `step` does not lie beyond the last step, as it would for a step stored for another strategy.
+    fn check_step(&self, step: Step) -> Result<(), StrategyError> {
+        if step > self.last_step() {
+            return Err(StrategyError::StepOutOfRange);
+        }
+        Ok(())
+    }
+
+    /// Number of copy operations planned in all steps before `step`.
+    fn operations_before(&self, step: Step) -> usize {
+        Step::range(Step::default(), step.min(self.last_step()))
+            .map(|step| self.plan(step).count())
+            .sum()
+    }
+
+    /// Number of copy operations required to complete this strategy.
+    fn total_operations(&self) -> usize {
+        self.operations_before(self.last_step())
+    }
+}
+
+/// Plan copying a block of consecutive pages from one location to another, as planned by most strategies.
+#[derive(Clone, Debug)]
+pub struct BlockPlan {
+    from: MemoryLocation,
+    to: MemoryLocation,
+    pages: Range<u16>,
+}
+
+impl BlockPlan {
+    /// Plan copying `pages` pages starting at `from` to the pages starting at `to`.
+    pub const fn new(from: MemoryLocation, to: MemoryLocation, pages: u16) -> Self {
+        Self {
+            from,
+            to,
+            pages: 0..pages,
+        }
+    }
+
+    /// Plan without any operations.
+    pub const fn empty() -> Self {
+        let nowhere = MemoryLocation {
+            slot: Slot(0),
+            page: Page(0),
+        };
+
+        Self::new(nowhere, nowhere, 0)
+    }
+}
+
+impl Iterator for BlockPlan {
+    type Item = CopyOperation;
+
+    fn next(&mut self) -> Option<CopyOperation> {
+        self.pages.next().map(|page| CopyOperation {
+            from: self.from.with_page_offset(page),
+            to: self.to.with_page_offset(page),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.pages.size_hint()
+    }
+}
+
+impl ExactSizeIterator for BlockPlan {}
+
+impl FusedIterator for BlockPlan {}
+
+/// Fold consecutive copy operations into `(from, to, pages)` ranges, of which both the sources and destinations are contiguous.
+///
+/// Allows a device to copy many pages at once rather than page by page, for example using DMA.
+pub fn coalesce_copies(
+    operations: impl IntoIterator<Item = CopyOperation>,
+) -> impl Iterator<Item = (MemoryLocation, MemoryLocation, u16)> {
+    let mut operations = operations.into_iter().peekable();
+
+    core::iter::from_fn(move || {
+        let first = operations.next()?;
+        let follows = |start: MemoryLocation, location: MemoryLocation, pages: u16| {
+            location.slot == start.slot && start.page.0.checked_add(pages) == Some(location.page.0)
+        };
+
+        let mut pages = 1;
+        while operations
+            .next_if(|next| {
+                follows(first.from, next.from, pages) && follows(first.to, next.to, pages)
+            })
+            .is_some()
+        {
+            pages += 1;
+        }
+
+        Some((first.from, first.to, pages))
+    })
+}
+
+/// Construction of a strategy from its request, for any device that has the capabilities the strategy requires.
+///
+/// The device is a parameter rather than an associated `type Device`, as a strategy is not tied to a single device type:
+/// [`Copy`](copy::Copy) for example works on every [`DeviceWithPrimarySlot`](crate::DeviceWithPrimarySlot). An
+/// associated type would require a strategy type per device type, whereas a parameter lets each strategy state its
+/// required capabilities once, as the bound of its implementation. [`Strategy`] itself only plans and thus needs no
+/// device; [`StrategyRunner::strategy`](crate::runner::StrategyRunner::strategy) and [`BootFlow`](crate::flow::BootFlow)
+/// construct strategies through this trait, such that a device lacking a capability is rejected at compile time:
+///
+/// ```compile_fail
+/// # use core::num::NonZeroU16;
+/// # use bootlick::{CopyOperation, Device, DeviceWithPrimarySlot, Error, Slot};
+/// # use bootlick::strategies::{FromRequest, swap_scootch::{Request, SwapScootch}};
+/// struct NoScratch;
+///
+/// impl Device for NoScratch {
+///     async fn copy(&mut self, _operation: CopyOperation) -> Result<(), Error> { Ok(()) }
+///     fn boot(self, _slot: Slot) -> ! { loop {} }
+///     fn page_count(&self) -> NonZeroU16 { NonZeroU16::MIN }
+///     fn capacity_pages(&self, _slot: Slot) -> u16 { 1 }
+/// }
+///
+/// impl DeviceWithPrimarySlot for NoScratch {
+///     fn get_primary(&self) -> Slot { Slot(0) }
+/// }
+///
+/// // Swapping requires a scratch slot, which `NoScratch` does not have.
+/// let strategy = SwapScootch::from_request(&NoScratch, Request { slot_secondary: Slot(1) });
+/// ```
+pub trait FromRequest<D: Device>: Strategy {
+    /// Strategy specific request, as stored in the bootloader state.
+    type Request;
+
+    fn from_request(device: &D, request: Self::Request) -> Self;
+
+    /// Apply the effect of the strategy that is not expressed as copies, once its last step has been recorded.
+    ///
+    /// Should be idempotent, as it is repeated when power is lost before the next step is recorded. Defaults to nothing.
+    fn activate(&self, _device: &mut D) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{generic::GenericMock, tri_slot};
+
+    fn assert_terminal(strategy: impl Strategy) {
+        let last_step = strategy.last_step();
+        assert_eq!(strategy.plan(last_step).count(), 0);
+        assert_eq!(strategy.plan(last_step.next()).count(), 0);
+
+        if let Some(strategy) = strategy.revert() {
+            let last_step = strategy.last_step();
+            assert_eq!(strategy.plan(last_step).count(), 0);
+            assert_eq!(strategy.plan(last_step.next()).count(), 0);
+        }
+    }
+
+    #[test]
+    fn last_step_is_stable() {
+        let device = GenericMock::new(5, 2);
+        let strategy = swap_scootch::SwapScootch::new(
+            &device,
+            swap_scootch::Request {
+                slot_secondary: crate::mock::generic::SECONDARY,
+            },
+        );
+
+        let last_step = strategy.last_step();
+        for _ in 0..8 {
+            assert_eq!(strategy.last_step(), last_step);
+        }
+
+        // Planning has no effect on the last step either.
+        assert_eq!(strategy.plan_all().count(), strategy.total_operations());
+        assert_eq!(strategy.last_step(), last_step);
+    }
+
+    #[test]
+    fn boot_slot() {
+        use crate::mock::tri_slot::{ALPHA, BETA, PRIMARY};
+
+        let device = tri_slot::MockDevice::new();
+
+        let strategy = xip::Xip::new(
+            &device,
+            xip::Request {
+                slot_target: BETA,
+                slot_backup: Some(ALPHA),
+            },
+        );
+        assert_eq!(strategy.boot_slot(), Some(BETA));
+        assert_eq!(strategy.revert().unwrap().boot_slot(), Some(ALPHA));
+
+        let strategy = copy::Copy::new(
+            &device,
+            copy::Request {
+                slot_secondary: BETA,
+                slot_backup: Some(ALPHA),
+                image_pages: None,
+            },
+        );
+        assert_eq!(strategy.boot_slot(), Some(PRIMARY));
+        assert_eq!(strategy.revert().unwrap().boot_slot(), Some(PRIMARY));
+
+        // Swapping banks boots the target bank, and falls back to the bank that was primary before.
+        use crate::mock::bank_swap;
+
+        let device = bank_swap::MockDevice::new();
+        let strategy =
+            swap_banks::SwapBanks::new(&device, swap_banks::Request::new(&device, bank_swap::BETA));
+        assert_eq!(strategy.boot_slot(), Some(bank_swap::BETA));
+        assert_eq!(
+            strategy.revert().unwrap().boot_slot(),
+            Some(bank_swap::ALPHA)
+        );
+    }
+
+    #[test]
+    fn coalesce() {
+        use crate::mock::tri_slot::{ALPHA, BETA, PRIMARY};
+
+        let device = tri_slot::MockDevice::new();
+        let strategy = copy::Copy::new(
+            &device,
+            copy::Request {
+                slot_secondary: BETA,
+                slot_backup: Some(ALPHA),
+                image_pages: None,
+            },
+        );
+        let location = |slot, page| MemoryLocation {
+            slot,
+            page: Page(page),
+        };
+
+        let mut ranges = coalesce_copies(strategy.plan(Step(0)));
+        assert_eq!(
+            ranges.next(),
+            Some((location(BETA, 0), location(PRIMARY, 0), 3))
+        );
+        assert_eq!(ranges.next(), None);
+
+        // A gap in either the sources or the destinations, or a change of slot, starts a new range.
+        let operations = [(0, 0), (1, 1), (3, 2), (4, 4)].map(|(from, to)| CopyOperation {
+            from: location(BETA, from),
+            to: location(PRIMARY, to),
+        });
+        let ranges: std::vec::Vec<_> =
+            coalesce_copies(operations.into_iter().chain([CopyOperation {
+                from: location(ALPHA, 5),
+                to: location(PRIMARY, 5),
+            }]))
+            .map(|(from, to, pages)| (from.page.0, to.page.0, pages))
+            .collect();
+        assert_eq!(ranges, [(0, 0, 2), (3, 2, 1), (4, 4, 1), (5, 5, 1)]);
+    }
+
+    #[test]
+    fn last_step_plans_nothing() {
+        let device = tri_slot::MockDevice::new();
+        assert_terminal(copy::Copy::new(
+            &device,
+            copy::Request {
+                slot_secondary: tri_slot::BETA,
+                slot_backup: Some(tri_slot::ALPHA),
+                image_pages: None,
+            },
+        ));
+        assert_terminal(copy_with_backup::CopyWithBackup::new(
+            &device,
+            copy_with_backup::Request {
+                slot_secondary: tri_slot::BETA,
+                slot_backup: tri_slot::ALPHA,
+            },
+        ));
+        assert_terminal(decompress_copy::DecompressCopy::new(
+            &device,
+            decompress_copy::Request {
+                slot_compressed: tri_slot::BETA,
+            },
+        ));
+        assert_terminal(delta_copy::DeltaCopy::new(
+            &device,
+            delta_copy::Request {
+                slot_secondary: tri_slot::BETA,
+                dirty_regions: 0b101,
+            },
+        ));
+        assert_terminal(xip::Xip::new(
+            &device,
+            xip::Request {
+                slot_target: tri_slot::BETA,
+                slot_backup: Some(tri_slot::ALPHA),
+            },
+        ));
+
+        for slot_target in [tri_slot::PRIMARY, tri_slot::BETA] {
+            assert_terminal(xip_or_copy::XipOrCopy::new(
+                &device,
+                xip_or_copy::Request {
+                    slot_target,
+                    slot_backup: Some(tri_slot::ALPHA),
+                },
+            ));
+        }
+        assert_terminal(xip_then_copy::XipThenCopy::new(
+            &device,
+            xip_then_copy::Request::new(tri_slot::BETA),
+        ));
+        assert_terminal(xip_then_copy::XipThenCopy::new(
+            &device,
+            xip_then_copy::Request::new(tri_slot::BETA).confirmed(),
+        ));
+
+        let device = crate::mock::bank_swap::MockDevice::new();
+        assert_terminal(swap_banks::SwapBanks::new(
+            &device,
+            swap_banks::Request::new(&device, crate::mock::bank_swap::BETA),
+        ));
+
+        // Include a scratch that does not evenly divide the image.
+        for (page_count, scratch_page_count) in [(1, 1), (5, 1), (5, 2), (6, 3)] {
+            let device = GenericMock::new(page_count, scratch_page_count);
+            assert_terminal(swap_sabs::SwapSABS::new(
+                &device,
+                swap_sabs::Request {
+                    slot_secondary: crate::mock::generic::SECONDARY,
+                },
+            ));
+            assert_terminal(swap_scootch::SwapScootch::new(
+                &device,
+                swap_scootch::Request {
+                    slot_secondary: crate::mock::generic::SECONDARY,
+                },
+            ));
+            assert_terminal(swap::Swap::new(
+                &device,
+                swap::Request {
+                    slot_secondary: crate::mock::generic::SECONDARY,
+                },
+            ));
+        }
+    }
+
+    #[test]
+    fn strategy_errors() {
+        use crate::mock::generic::SECONDARY;
+
+        let request = swap_scootch::Request {
+            slot_secondary: SECONDARY,
+        };
+        let strategy = swap_scootch::SwapScootch::new(&GenericMock::new(4, 1), request);
+
+        assert_eq!(strategy.verify_layout(&GenericMock::new(4, 1)), Ok(()));
+        assert_eq!(
+            strategy.verify_layout(&GenericMock::new(3, 1)),
+            Err(StrategyError::PageOverflow)
+        );
+
+        let strategy = swap_scootch::SwapScootch::new(
+            &GenericMock::new(4, 1),
+            swap_scootch::Request {
+                slot_secondary: Slot(3),
+            },
+        );
+        assert_eq!(
+            strategy.verify_layout(&GenericMock::new(4, 1)),
+            Err(StrategyError::InvalidSlot)
+        );
+
+        assert_eq!(strategy.check_step(strategy.last_step()), Ok(()));
+        assert_eq!(
+            strategy.check_step(strategy.last_step().next()),
+            Err(StrategyError::StepOutOfRange)
+        );
+        assert_eq!(
+            Error::from(StrategyError::StepOutOfRange),
+            Error::Misconfigured
+        );
+        assert_eq!(Error::from(StrategyError::PageOverflow), Error::OutOfRange);
+    }
+
+    #[test]
+    fn requires_scratch() {
+        let device = GenericMock::new(3, 1);
+        let secondary = crate::mock::generic::SECONDARY;
+
+        assert!(
+            swap_sabs::SwapSABS::new(
+                &device,
+                swap_sabs::Request {
+                    slot_secondary: secondary
+                }
+            )
+            .requires_scratch()
+        );
+        assert!(
+            swap_scootch::SwapScootch::new(
+                &device,
+                swap_scootch::Request {
+                    slot_secondary: secondary
+                }
+            )
+            .requires_scratch()
+        );
+        assert!(
+            swap::Swap::new(
+                &device,
+                swap::Request {
+                    slot_secondary: secondary
+                }
+            )
+            .requires_scratch()
+        );
+
+        let device = tri_slot::MockDevice::new();
+        assert!(
+            !copy::Copy::new(
+                &device,
+                copy::Request {
+                    slot_secondary: tri_slot::BETA,
+                    slot_backup: None,
+                    image_pages: None,
+                },
+            )
+            .requires_scratch()
+        );
+        assert!(
+            !xip::Xip::new(
+                &device,
+                xip::Request {
+                    slot_target: tri_slot::BETA,
+                    slot_backup: None,
+                },
+            )
+            .requires_scratch()
+        );
+    }
+
+    #[test]
+    fn single_page() {
+        use crate::mock::{
+            run_strategy_to_completion,
+            single_page::{ALPHA, BETA, IMAGE_A, IMAGE_B, MockDevice},
+        };
+
+        /// Install the image in beta, revert to the image in alpha if possible, and return the resulting primary image.
+        fn install(strategy: impl Strategy) -> ([u8; 1], Option<[u8; 1]>) {
+            let mut device = MockDevice::new();
+            assert_eq!(strategy.verify_layout(&device), Ok(()));
+
+            run_strategy_to_completion(&mut device, &strategy);
+            let installed = device.primary;
+
+            let reverted = strategy.revert().map(|strategy| {
+                run_strategy_to_completion(&mut device, &strategy);
+                device.primary
+            });
+            (installed, reverted)
+        }
+
+        let device = MockDevice::new();
+        let swapped = (IMAGE_B, Some(IMAGE_A));
+
+        assert_eq!(
+            install(swap_sabs::SwapSABS::new(
+                &device,
+                swap_sabs::Request {
+                    slot_secondary: BETA
+                },
+            )),
+            swapped
+        );
+        assert_eq!(
+            install(swap_scootch::SwapScootch::new(
+                &device,
+                swap_scootch::Request {
+                    slot_secondary: BETA
+                },
+            )),
+            swapped
+        );
+        assert_eq!(
+            install(swap::Swap::new(
+                &device,
+                swap::Request {
+                    slot_secondary: BETA
+                },
+            )),
+            swapped
+        );
+        assert_eq!(
+            install(wear_leveling::WearLeveling::new(
+                swap_scootch::SwapScootch::new(
+                    &device,
+                    swap_scootch::Request {
+                        slot_secondary: BETA,
+                    },
+                ),
+                &device,
+                1,
+            )),
+            swapped
+        );
+        assert_eq!(
+            install(copy::Copy::new(
+                &device,
+                copy::Request {
+                    slot_secondary: BETA,
+                    slot_backup: Some(ALPHA),
+                    image_pages: None,
+                },
+            )),
+            swapped
+        );
+        assert_eq!(
+            install(copy_with_backup::CopyWithBackup::new(
+                &device,
+                copy_with_backup::Request {
+                    slot_secondary: BETA,
+                    slot_backup: ALPHA,
+                },
+            )),
+            swapped
+        );
+        assert_eq!(
+            install(delta_copy::DeltaCopy::new(
+                &device,
+                delta_copy::Request::new(BETA, device.page_count(), [Page(0)]),
+            )),
+            (IMAGE_B, None)
+        );
+        assert_eq!(
+            install(xip_or_copy::XipOrCopy::new(
+                &device,
+                xip_or_copy::Request {
+                    slot_target: BETA,
+                    slot_backup: Some(ALPHA),
+                },
+            )),
+            swapped
+        );
+        assert_eq!(
+            install(xip_then_copy::XipThenCopy::new(
+                &device,
+                xip_then_copy::Request::new(BETA).confirmed(),
+            ))
+            .0,
+            IMAGE_B
+        );
+
+        // Executing in place copies nothing.
+        assert_eq!(
+            install(xip::Xip::new(
+                &device,
+                xip::Request {
+                    slot_target: BETA,
+                    slot_backup: Some(ALPHA),
+                },
+            )),
+            (IMAGE_A, Some(IMAGE_A))
+        );
+    }
+
+    #[test]
+    fn plan_clone_resumes_independently() {
+        let device = GenericMock::new(6, 3);
+        let strategy = swap_scootch::SwapScootch::new(
+            &device,
+            swap_scootch::Request {
+                slot_secondary: crate::mock::generic::SECONDARY,
+            },
+        );
+
+        let mut plan: swap_scootch::SwapScootchPlan = strategy.plan(Step(0));
+        let first = plan.next().unwrap();
+
+        let mut clone = plan.clone();
+        let rest: std::vec::Vec<_> = plan.by_ref().collect();
+        assert_eq!(rest.len(), 2);
+        assert_eq!(clone.next(), Some(rest[0]));
+        assert_eq!(clone.collect::<std::vec::Vec<_>>(), rest[1..]);
+        assert!(!rest.contains(&first));
+
+        // Exhausted plans stay exhausted.
+        assert_eq!(plan.next(), None);
+        assert_eq!(plan.next(), None);
+    }
+
+    #[test]
+    fn huge_image() {
+        use crate::{
+            Page,
+            mock::generic::{PRIMARY, SECONDARY},
+        };
+
+        // Three steps for every page exceed the range of the page indices.
+        let device = GenericMock::new(u16::MAX, 1);
+        let last_page = MemoryLocation {
+            slot: PRIMARY,
+            page: Page(u16::MAX - 1),
+        };
+
+        let strategy = swap_sabs::SwapSABS::new(
+            &device,
+            swap_sabs::Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+        assert_eq!(strategy.last_step(), Step(3 * u16::MAX as u32));
+        assert_eq!(strategy.total_operations(), 3 * u16::MAX as usize);
+        assert!(
+            strategy
+                .plan(Step(3 * u16::MAX as u32 - 2))
+                .all(|operation| operation.to == last_page)
+        );
+        assert_terminal(strategy);
+
+        let strategy = swap_scootch::SwapScootch::new(
+            &device,
+            swap_scootch::Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+        assert_eq!(strategy.last_step(), Step(3 * u16::MAX as u32));
+        assert_eq!(strategy.total_operations(), 3 * u16::MAX as usize);
+        assert_terminal(strategy);
+    }
 }
@@ -0,0 +1,116 @@
+//! Precomputed plans, trading RAM for the CPU time of planning every step on the fly.
+
+use core::{iter::Map, slice::Iter};
+
+use crate::{CopyOperation, Error, Slot, Step, strategies::Strategy};
+
+/// All operations of a strategy, computed once and stored for at most `N` operations.
+///
+/// Implements [`Strategy`] itself, such that it can be run directly instead of the strategy it was computed from.
+/// As the cache only covers a single direction, [`revert`](Strategy::revert) yields `None`;
+/// compute a new plan from the reverted strategy instead.
+pub struct PlannedSteps<const N: usize> {
+    operations: heapless::Vec<(Step, CopyOperation), N>,
+    last_step: Step,
+    boot_slot: Option<Slot>,
+    image_slot: Option<Slot>,
+}
+
+impl<const N: usize> PlannedSteps<N> {
+    /// Compute all operations of `strategy`, failing with [`Error::OutOfRange`] if they exceed the capacity `N`.
+    pub fn new(strategy: &impl Strategy) -> Result<Self, Error> {
+        let mut operations = heapless::Vec::new();
+        for operation in strategy.plan_all() {
+            operations.push(operation).map_err(|_| Error::OutOfRange)?;
+        }
+
+        Ok(Self {
+            operations,
+            last_step: strategy.last_step(),
+            boot_slot: strategy.boot_slot(),
+            image_slot: strategy.image_slot(),
+        })
+    }
+}
+
+/// Plan of a single step of [`PlannedSteps`], borrowing the stored operations.
+pub type PlannedStepsPlan<'a> =
+    Map<Iter<'a, (Step, CopyOperation)>, fn(&(Step, CopyOperation)) -> CopyOperation>;
+
+impl<const N: usize> Strategy for PlannedSteps<N> {
+    type Plan<'a> = PlannedStepsPlan<'a>;
+
+    fn last_step(&self) -> Step {
+        self.last_step
+    }
+
+    fn plan(&self, step: Step) -> PlannedStepsPlan<'_> {
+        // Operations are ordered by step, hence those of a single step are contiguous.
+        let start = self.operations.partition_point(|(s, _)| *s < step);
+        let end = self.operations.partition_point(|(s, _)| *s <= step);
+
+        self.operations[start..end]
+            .iter()
+            .map(|(_, operation)| *operation)
+    }
+
+    fn image_slot(&self) -> Option<Slot> {
+        self.image_slot
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        self.boot_slot
+    }
+
+    fn revert(self) -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::{
+        mock::multi_scratch::{MockDevice, SECONDARY},
+        strategies::swap_sabs::{Request, SwapSABS},
+    };
+
+    #[test]
+    fn matches_plan() {
+        let strategy = SwapSABS::new(
+            &MockDevice::new(),
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        let planned = PlannedSteps::<32>::new(&strategy).unwrap();
+
+        assert_eq!(planned.last_step(), strategy.last_step());
+        for step in Step::range(Step::default(), strategy.last_step().next()) {
+            assert_eq!(
+                planned.plan(step).collect::<Vec<_>>(),
+                strategy.plan(step).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn capacity() {
+        let strategy = SwapSABS::new(
+            &MockDevice::new(),
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        assert_eq!(strategy.total_operations(), 30);
+        assert!(PlannedSteps::<30>::new(&strategy).is_ok());
+        assert!(matches!(
+            PlannedSteps::<29>::new(&strategy),
+            Err(Error::OutOfRange)
+        ));
+    }
+}
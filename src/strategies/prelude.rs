@@ -0,0 +1,22 @@
+//! Re-exports of all strategies and their requests, to be imported with `use bootlick::strategies::prelude::*`.
+//!
+//! As every strategy names its request `Request`, the requests are re-exported prefixed with the name of their strategy.
+//! Likewise [`Copy`](super::copy::Copy) is re-exported as `CopyStrategy`, to not shadow [`core::marker::Copy`].
+
+#[cfg(feature = "heapless")]
+pub use super::planned::PlannedSteps;
+pub use super::{
+    any::{AnyStrategy, Request as AnyRequest, StrategyKind},
+    copy::{Copy as CopyStrategy, Request as CopyRequest},
+    copy_with_backup::{CopyWithBackup, Request as CopyWithBackupRequest},
+    decompress_copy::{DecompressCopy, Request as DecompressCopyRequest},
+    delta_copy::{DeltaCopy, Request as DeltaCopyRequest},
+    swap::{Request as SwapRequest, Swap},
+    swap_banks::{Request as SwapBanksRequest, SwapBanks},
+    swap_sabs::{Request as SwapSABSRequest, SwapSABS},
+    swap_scootch::{Request as SwapScootchRequest, SwapScootch},
+    wear_leveling::WearLeveling,
+    xip::{Request as XipRequest, Xip},
+    xip_or_copy::{Request as XipOrCopyRequest, XipOrCopy},
+    xip_then_copy::{Request as XipThenCopyRequest, XipThenCopy},
+};
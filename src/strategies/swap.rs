@@ -0,0 +1,247 @@
+//! Strategy to swap two slots, picking the swap that spares the scratch slot if it wears like the image slots.
+//!
+//! [`SwapSABS`] erases every page of both slots once, but erases its scratch for every block of pages.
+//! [`SwapScootch`] instead erases the primary slot twice, and every page of the scratch only once.
+//! Hence [`SwapSABS`] is preferable for a [durable](DeviceWithScratch::scratch_is_durable) scratch like FRAM,
+//! whilst [`SwapScootch`] is preferable if the scratch is flash like the image slots.
+//!
+//! Both swaps only need the recorded [`Step`] to recover from power loss, even when the scratch holds the only copy of a page.
+//! No step overwrites its own sources, hence a step that was executed but not yet recorded is simply executed again.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DeviceWithPrimarySlot, DeviceWithScratch, Slot, Step,
+    strategies::{
+        BlockPlan, FromRequest, Strategy,
+        swap_sabs::{self, SwapSABS},
+        swap_scootch::{self, SwapScootch},
+    },
+};
+
+/// Request to boot a secondary image.
+///
+/// When the secondary image fails to boot, will perform the swap again, restoring the original situation.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub slot_secondary: Slot,
+}
+
+/// Either [`SwapSABS`] or [`SwapScootch`], depending on whether the scratch slot is durable.
+pub struct Swap {
+    inner: Inner,
+}
+
+enum Inner {
+    Sabs(SwapSABS),
+    Scootch(SwapScootch),
+}
+
+impl Swap {
+    pub fn new(
+        device: &(impl DeviceWithScratch + DeviceWithPrimarySlot),
+        request: Request,
+    ) -> Self {
+        let inner = if device.scratch_is_durable() {
+            Inner::Sabs(SwapSABS::new(
+                device,
+                swap_sabs::Request {
+                    slot_secondary: request.slot_secondary,
+                },
+            ))
+        } else {
+            Inner::Scootch(SwapScootch::new(
+                device,
+                swap_scootch::Request {
+                    slot_secondary: request.slot_secondary,
+                },
+            ))
+        };
+
+        Self { inner }
+    }
+
+    /// Whether the slots are swapped by [`SwapSABS`], rather than by [`SwapScootch`].
+    pub fn is_sabs(&self) -> bool {
+        matches!(self.inner, Inner::Sabs(_))
+    }
+}
+
+impl<D: DeviceWithScratch + DeviceWithPrimarySlot> FromRequest<D> for Swap {
+    type Request = Request;
+
+    fn from_request(device: &D, request: Request) -> Self {
+        Self::new(device, request)
+    }
+}
+
+/// Plan of a single step of [`Swap`].
+pub type SwapPlan = BlockPlan;
+
+impl Strategy for Swap {
+    type Plan<'a> = SwapPlan;
+
+    fn last_step(&self) -> Step {
+        match &self.inner {
+            Inner::Sabs(strategy) => strategy.last_step(),
+            Inner::Scootch(strategy) => strategy.last_step(),
+        }
+    }
+
+    fn plan(&self, step: Step) -> SwapPlan {
+        match &self.inner {
+            Inner::Sabs(strategy) => strategy.plan(step),
+            Inner::Scootch(strategy) => strategy.plan(step),
+        }
+    }
+
+    fn phase_name(&self, step: Step) -> &'static str {
+        match &self.inner {
+            Inner::Sabs(strategy) => strategy.phase_name(step),
+            Inner::Scootch(strategy) => strategy.phase_name(step),
+        }
+    }
+
+    fn is_safe_point(&self, step: Step) -> bool {
+        match &self.inner {
+            Inner::Sabs(strategy) => strategy.is_safe_point(step),
+            Inner::Scootch(strategy) => strategy.is_safe_point(step),
+        }
+    }
+
+    fn requires_scratch(&self) -> bool {
+        true
+    }
+
+    fn image_slot(&self) -> Option<Slot> {
+        match &self.inner {
+            Inner::Sabs(strategy) => strategy.image_slot(),
+            Inner::Scootch(strategy) => strategy.image_slot(),
+        }
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        match &self.inner {
+            Inner::Sabs(strategy) => strategy.boot_slot(),
+            Inner::Scootch(strategy) => strategy.boot_slot(),
+        }
+    }
+
+    fn revert(self) -> Option<Self> {
+        let inner = match self.inner {
+            Inner::Sabs(strategy) => Inner::Sabs(strategy.revert()?),
+            Inner::Scootch(strategy) => Inner::Scootch(strategy.revert()?),
+        };
+
+        Some(Self { inner })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        MemoryLocation, Page,
+        mock::{
+            PowerLoss,
+            generic::{GenericMock, PRIMARY, SCRATCH, SECONDARY},
+            run_strategy_to_completion, run_strategy_with_power_loss,
+        },
+    };
+
+    fn swap(durable_scratch: bool) -> GenericMock {
+        let mut device = GenericMock::new(4, 1);
+        device.durable_scratch = durable_scratch;
+
+        let strategy = Swap::new(
+            &device,
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+        assert_eq!(strategy.is_sabs(), durable_scratch);
+
+        run_strategy_to_completion(&mut device, &strategy);
+        assert_eq!(device.primary, GenericMock::image_b(4));
+        assert_eq!(device.secondary, GenericMock::image_a(4));
+
+        let strategy = strategy.revert().unwrap();
+        run_strategy_to_completion(&mut device, &strategy);
+        assert_eq!(device.primary, GenericMock::image_a(4));
+        assert_eq!(device.secondary, GenericMock::image_b(4));
+
+        device
+    }
+
+    #[test]
+    fn durable_scratch() {
+        let device = swap(true);
+
+        // The scratch takes the wear, sparing the primary slot.
+        assert!(device.wear.check_slot(PRIMARY, 2));
+        let scratch = MemoryLocation {
+            slot: SCRATCH,
+            page: Page(0),
+        };
+        assert_eq!(device.wear.wear_at(scratch), 8);
+    }
+
+    #[test]
+    fn decorated() {
+        use crate::devices::{
+            skip_identical::SkipIdenticalDevice, verifying::ReadbackVerifyingDevice,
+        };
+
+        let mut device = GenericMock::new(4, 1);
+        device.durable_scratch = true;
+        let device = ReadbackVerifyingDevice::<_>::new(SkipIdenticalDevice::<_>::new(device));
+
+        // The durability of the scratch is seen through the decorators.
+        let strategy = Swap::new(
+            &device,
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+        assert!(strategy.is_sabs());
+    }
+
+    #[test]
+    fn executed_but_not_recorded() {
+        for durable_scratch in [false, true] {
+            let mut reference = GenericMock::new(4, 1);
+            reference.durable_scratch = durable_scratch;
+            let strategy = Swap::new(
+                &reference,
+                Request {
+                    slot_secondary: SECONDARY,
+                },
+            );
+
+            for step in Step::range(Step::default(), strategy.last_step()) {
+                let mut device = GenericMock::new(4, 1);
+                device.durable_scratch = durable_scratch;
+
+                // Power is lost after executing every operation of the step, but before recording it.
+                let operations = strategy.plan(step).count();
+                run_strategy_with_power_loss(
+                    &mut device,
+                    &strategy,
+                    Some(PowerLoss { step, operations }),
+                );
+
+                assert_eq!(device.primary, GenericMock::image_b(4));
+                assert_eq!(device.secondary, GenericMock::image_a(4));
+            }
+        }
+    }
+
+    #[test]
+    fn flash_scratch() {
+        let device = swap(false);
+
+        // The primary slot takes the wear, sparing the scratch.
+        assert!(!device.wear.check_slot(PRIMARY, 2));
+        assert!(device.wear.check_slot(SCRATCH, 2));
+    }
+}
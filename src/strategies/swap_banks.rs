@@ -0,0 +1,160 @@
+//! Strategy to swap images by letting the hardware select another bank as the primary slot.
+//!
+//! Some devices have a dual bank flash, for which the boot ROM selects the bank to boot from based on a register.
+//! Activating an image is then only a matter of flipping that register, without copying any pages.
+//!
+//! The register is flipped by [`FromRequest::activate`] once the last step has been recorded, which
+//! [`BootFlow`](crate::flow::BootFlow) does after both installing and reverting. Power loss in between is
+//! indistinguishable from a failed trial, upon which the previous bank is selected again, which it already was.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DeviceWithBankSwap, DeviceWithPrimarySlot, Slot, Step,
+    strategies::{BlockPlan, FromRequest, Strategy},
+};
+
+/// Request to make a target slot the primary slot.
+///
+/// When the target image fails to boot, the previous slot is selected again.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub slot_target: Slot,
+    /// Primary slot at the time of the request, which is retained as the device may have been switched to the target
+    /// slot by the time the update is reverted.
+    pub slot_previous: Slot,
+}
+
+impl Request {
+    /// Request to switch from the current primary slot of `device` to `slot_target`.
+    pub fn new(device: &impl DeviceWithPrimarySlot, slot_target: Slot) -> Self {
+        Self {
+            slot_target,
+            slot_previous: device.get_primary(),
+        }
+    }
+}
+
+/// Strategy to swap images by selecting another bank, instead of copying.
+///
+/// The plan of this strategy is empty; [`activate`](FromRequest::activate) it once the strategy has completed
+/// to record the new primary slot with the device.
+pub struct SwapBanks {
+    request: Request,
+}
+
+impl SwapBanks {
+    pub fn new(_device: &impl DeviceWithBankSwap, request: Request) -> Self {
+        Self { request }
+    }
+}
+
+impl<D: DeviceWithBankSwap> FromRequest<D> for SwapBanks {
+    type Request = Request;
+
+    fn from_request(device: &D, request: Request) -> Self {
+        Self::new(device, request)
+    }
+
+    /// Select the target slot as the primary slot of the device.
+    fn activate(&self, device: &mut D) {
+        device.set_primary(self.request.slot_target);
+    }
+}
+
+/// Plan of a single step of [`SwapBanks`], which never copies anything.
+pub type SwapBanksPlan = BlockPlan;
+
+impl Strategy for SwapBanks {
+    type Plan<'a> = SwapBanksPlan;
+
+    fn last_step(&self) -> Step {
+        // A single empty step, such that a completed request is distinguishable from a fresh one.
+        Step(1)
+    }
+
+    fn plan(&self, _step: Step) -> SwapBanksPlan {
+        BlockPlan::empty()
+    }
+
+    fn image_slot(&self) -> Option<Slot> {
+        Some(self.request.slot_target)
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        // Once activated, the target bank is the primary slot.
+        Some(self.request.slot_target)
+    }
+
+    fn revert(self) -> Option<Self> {
+        Some(Self {
+            request: Request {
+                slot_target: self.request.slot_previous,
+                slot_previous: self.request.slot_target,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        flow::{BootFlow, Phase},
+        mock::{
+            bank_swap::{ALPHA, BETA, IMAGE_A, IMAGE_B, MockDevice},
+            run_strategy_to_completion,
+        },
+        runner::StrategyRunner,
+        state::{State, StateStorage, mem::MemStateStorage},
+    };
+
+    #[test]
+    fn test() {
+        let mut device = MockDevice::new();
+        let strategy = SwapBanks::new(&device, Request::new(&device, BETA));
+
+        run_strategy_to_completion(&mut device, &strategy);
+        strategy.activate(&mut device);
+
+        assert_eq!(device.get_primary(), BETA);
+        assert_eq!(device.alpha, IMAGE_A);
+        assert_eq!(device.beta, IMAGE_B);
+        assert!(device.wear.check_slot(ALPHA, 0));
+        assert!(device.wear.check_slot(BETA, 0));
+
+        let strategy = strategy.revert().unwrap();
+
+        run_strategy_to_completion(&mut device, &strategy);
+        strategy.activate(&mut device);
+
+        assert_eq!(device.get_primary(), ALPHA);
+        assert!(device.wear.check_slot(ALPHA, 0));
+        assert!(device.wear.check_slot(BETA, 0));
+    }
+
+    #[test]
+    fn failed_trial() {
+        let device = MockDevice::new();
+        let mut storage = MemStateStorage::new();
+        embassy_futures::block_on(storage.store(&State::new_request(Request::new(&device, BETA))))
+            .unwrap();
+
+        let mut flow = BootFlow::new(StrategyRunner::new(device, &mut storage));
+        let phase = embassy_futures::block_on(flow.resume::<SwapBanks>()).unwrap();
+        assert_eq!(phase, Phase::Trialing);
+        assert_eq!(flow.runner().device().get_primary(), BETA);
+
+        // The new image did not confirm itself before the device was reset, after which the target bank is primary.
+        drop(flow);
+        let mut device = MockDevice::new();
+        device.primary = BETA;
+        let mut flow = BootFlow::new(StrategyRunner::new(device, &mut storage));
+        let phase = embassy_futures::block_on(flow.resume::<SwapBanks>()).unwrap();
+        assert_eq!(phase, Phase::Returned);
+        assert_eq!(flow.runner().device().get_primary(), ALPHA);
+        drop(flow);
+
+        assert!(storage.state().unwrap().request.is_none());
+    }
+}
@@ -8,20 +8,29 @@
 //! Finally the scratch (S) memory page is written to the secondary (B) memory page.
 //!
 //! This results in the primary and secondary slots enduring a single erasure on every page for this strategy, whilst the scratch page endures `N` erasures, where `N` is the number of pages.
+//!
+//! With multiple scratch pages these three phases are executed for a block of pages at a time, taking `3 * ceil(N / K)` steps for `K` scratch pages.
+//! The phases can not be pipelined into fewer steps, for example by refilling the scratch with the next block whilst moving the previous block out of it.
+//! A step interrupted by power loss is executed again from its first operation, which thus must still find its source intact.
+//! Each phase however overwrites the source of the phase before it: B2A overwrites A, S2B overwrites B and the next A2S overwrites S.
+//! Combining any two consecutive phases in a single step would thus lose a page of either image when that step is repeated.
+//! Hence the only way to cut the number of steps is a larger scratch.
+//! For the same reason every step is a [safe point](Strategy::is_safe_point): resuming from the end of an earlier S2B phase
+//! would repeat the A2S phase after B2A already overwrote its source.
 
 use core::num::NonZeroU16;
 
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    CopyOperation, DeviceWithPrimarySlot, DeviceWithScratch, MemoryLocation, Page, Slot, Step,
-    strategies::Strategy,
+    DeviceWithPrimarySlot, DeviceWithScratch, MemoryLocation, Page, Slot, Step,
+    strategies::{BlockPlan, FromRequest, Strategy},
 };
 
 /// Request to boot a secondary image.
 ///
 /// When the secondary image fails to boot, will perform the swap again, restoring the original situation.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Request {
     pub slot_secondary: Slot,
 }
@@ -32,11 +41,12 @@ pub struct SwapSABS {
     scratch_pages: NonZeroU16,
     slot_primary: Slot,
     slot_scratch: Slot,
+    scratch_base: Page,
 }
 
 /// Logical phases for the strategy to execute, to decouple raw steps from behaviour in a logical manner.
-#[derive(Debug)]
-enum Phase {
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Phase {
     A2S,
     B2A,
     S2B,
@@ -52,10 +62,31 @@ impl Phase {
             _ => unreachable!(),
         };
 
-        let start = Page((step.0 / 3) * scratch_pages.get());
+        // Note(as): beyond the last step the start is meaningless, as nothing is planned anyway.
+        let start = Page((step.0 / 3 * scratch_pages.get() as u32) as u16);
 
         (destination, start)
     }
+
+    /// Step in which this phase is executed for the block starting at `start`, being the inverse of [`from_step`](Self::from_step).
+    pub const fn to_step(&self, start: Page, scratch_pages: NonZeroU16) -> Step {
+        let block = start.0 as u32 / scratch_pages.get() as u32;
+        let phase = match self {
+            Phase::A2S => 0,
+            Phase::B2A => 1,
+            Phase::S2B => 2,
+        };
+
+        Step(block * 3 + phase)
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Phase::A2S => "primary to scratch",
+            Phase::B2A => "secondary to primary",
+            Phase::S2B => "scratch to secondary",
+        }
+    }
 }
 
 impl SwapSABS {
@@ -69,21 +100,35 @@ impl SwapSABS {
             scratch_pages: device.scratch_page_count(),
             slot_primary: device.get_primary(),
             slot_scratch: device.get_scratch(),
+            scratch_base: device.scratch_base(),
         }
     }
 }
 
+impl<D: DeviceWithScratch + DeviceWithPrimarySlot> FromRequest<D> for SwapSABS {
+    type Request = Request;
+
+    fn from_request(device: &D, request: Request) -> Self {
+        Self::new(device, request)
+    }
+}
+
+/// Plan of a single step of [`SwapSABS`], copying a single block.
+pub type SwapSABSPlan = BlockPlan;
+
 impl Strategy for SwapSABS {
+    type Plan<'a> = SwapSABSPlan;
+
     fn last_step(&self) -> Step {
         // Note(div_ceil): we might need to partially use the scratch pages for the final segment,
         // if it is not a neat multiple.
         let blocks = self.num_pages.get().div_ceil(self.scratch_pages.get());
 
         // A step for each AS, BA and SB step, where Scratch is fully filled.
-        Step(blocks * 3)
+        Step(blocks as u32 * 3)
     }
 
-    fn plan(&self, step: Step) -> impl Iterator<Item = CopyOperation> {
+    fn plan(&self, step: Step) -> SwapSABSPlan {
         let (phase, start) = Phase::from_step(step, self.scratch_pages);
 
         let (from, to) = match phase {
@@ -94,7 +139,7 @@ impl Strategy for SwapSABS {
                 },
                 MemoryLocation {
                     slot: self.slot_scratch,
-                    page: Page(0),
+                    page: self.scratch_base,
                 },
             ),
             Phase::B2A => (
@@ -110,7 +155,7 @@ impl Strategy for SwapSABS {
             Phase::S2B => (
                 MemoryLocation {
                     slot: self.slot_scratch,
-                    page: Page(0),
+                    page: self.scratch_base,
                 },
                 MemoryLocation {
                     slot: self.request.slot_secondary,
@@ -119,22 +164,37 @@ impl Strategy for SwapSABS {
             ),
         };
 
-        // How many pages do we have left to move in order to finish?
-        let pages_left = self.num_pages.get() - start.0;
+        // How many pages are we doing in this step? Nothing is left to move once the last step has been reached.
+        let pages_now = if step < self.last_step() {
+            // How many pages do we have left to move in order to finish?
+            let pages_left = self.num_pages.get() - start.0;
 
-        // How many pages are we doing in this step?
-        let pages_now = u16::min(pages_left, self.scratch_pages.get());
+            u16::min(pages_left, self.scratch_pages.get())
+        } else {
+            0
+        };
 
-        (0..pages_now).map(move |page| CopyOperation {
-            from: MemoryLocation {
-                slot: from.slot,
-                page: Page(from.page.0 + page),
-            },
-            to: MemoryLocation {
-                slot: to.slot,
-                page: Page(to.page.0 + page),
-            },
-        })
+        BlockPlan::new(from, to, pages_now)
+    }
+
+    fn phase_name(&self, step: Step) -> &'static str {
+        if step >= self.last_step() {
+            return "boot";
+        }
+
+        Phase::from_step(step, self.scratch_pages).0.name()
+    }
+
+    fn requires_scratch(&self) -> bool {
+        true
+    }
+
+    fn image_slot(&self) -> Option<Slot> {
+        Some(self.request.slot_secondary)
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        Some(self.slot_primary)
     }
 
     fn revert(self) -> Option<Self> {
@@ -145,24 +205,13 @@ impl Strategy for SwapSABS {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Device, DeviceWithScratch};
+    use crate::{
+        CopyOperation, Device, DeviceWithScratch,
+        mock::{PowerLoss, run_strategy_to_completion, run_strategy_with_power_loss},
+    };
 
     use super::*;
 
-    fn perform_copy(
-        device: &mut (impl Device + DeviceWithScratch + DeviceWithPrimarySlot),
-        strategy: &SwapSABS,
-    ) {
-        for step_i in 0..strategy.last_step().0 {
-            let step = Step(step_i);
-            for operation in strategy.plan(step) {
-                embassy_futures::block_on(async {
-                    device.copy(operation).await.unwrap();
-                })
-            }
-        }
-    }
-
     #[test]
     fn single_scratch() {
         use crate::mock::single_scratch::{
@@ -180,7 +229,7 @@ mod tests {
         assert_eq!(device.primary, IMAGE_A);
         assert_eq!(device.secondary, IMAGE_B);
 
-        perform_copy(&mut device, &strategy);
+        run_strategy_to_completion(&mut device, &strategy);
 
         assert_eq!(device.primary, IMAGE_B);
         assert_eq!(device.secondary, IMAGE_A);
@@ -195,7 +244,7 @@ mod tests {
 
         let strategy = strategy.revert().unwrap();
 
-        perform_copy(&mut device, &strategy);
+        run_strategy_to_completion(&mut device, &strategy);
 
         assert_eq!(device.primary, IMAGE_A);
         assert_eq!(device.secondary, IMAGE_B);
@@ -218,7 +267,7 @@ mod tests {
         assert_eq!(device.primary, IMAGE_A);
         assert_eq!(device.secondary, IMAGE_B);
 
-        perform_copy(&mut device, &strategy);
+        run_strategy_to_completion(&mut device, &strategy);
 
         assert_eq!(device.primary, IMAGE_B);
         assert_eq!(device.secondary, IMAGE_A);
@@ -237,9 +286,274 @@ mod tests {
 
         let strategy = strategy.revert().unwrap();
 
-        perform_copy(&mut device, &strategy);
+        run_strategy_to_completion(&mut device, &strategy);
 
         assert_eq!(device.primary, IMAGE_A);
         assert_eq!(device.secondary, IMAGE_B);
     }
+
+    #[test]
+    fn scratch_reads() {
+        use crate::mock::multi_scratch::{MockDevice, SCRATCH, SECONDARY};
+
+        let mut device = MockDevice::new();
+        let strategy = SwapSABS::new(
+            &device,
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        let s2b_operations = Step::range(Step::default(), strategy.last_step())
+            .flat_map(|step| strategy.plan(step))
+            .filter(|operation| operation.from.slot == SCRATCH)
+            .count();
+
+        run_strategy_to_completion(&mut device, &strategy);
+
+        let scratch_reads: usize = (0..device.scratch_page_count().get())
+            .map(|page| {
+                device.wear.reads_at(MemoryLocation {
+                    slot: SCRATCH,
+                    page: Page(page),
+                })
+            })
+            .sum();
+
+        assert_eq!(s2b_operations, device.page_count().get() as usize);
+        assert_eq!(scratch_reads, s2b_operations);
+    }
+
+    #[test]
+    fn verify_layout() {
+        use crate::mock::generic::{GenericMock, SECONDARY};
+
+        let request = Request {
+            slot_secondary: SECONDARY,
+        };
+        let strategy = SwapSABS::new(&GenericMock::new(8, 4), request);
+
+        assert_eq!(strategy.verify_layout(&GenericMock::new(8, 4)), Ok(()));
+        assert_eq!(
+            strategy.verify_layout(&GenericMock::new(6, 4)),
+            Err(crate::strategies::StrategyError::PageOverflow)
+        );
+        assert_eq!(
+            strategy.verify_layout(&GenericMock::new(8, 2)),
+            Err(crate::strategies::StrategyError::PageOverflow)
+        );
+    }
+
+    #[test]
+    fn phase_names() {
+        use crate::mock::multi_scratch::{MockDevice, SECONDARY};
+
+        let strategy = SwapSABS::new(
+            &MockDevice::new(),
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        assert_eq!(strategy.phase_name(Step(0)), "primary to scratch");
+        assert_eq!(strategy.phase_name(Step(1)), "secondary to primary");
+        assert_eq!(strategy.phase_name(Step(2)), "scratch to secondary");
+        assert_eq!(strategy.phase_name(Step(3)), "primary to scratch");
+        assert_eq!(strategy.phase_name(strategy.last_step()), "boot");
+    }
+
+    #[test]
+    fn plan_all() {
+        use crate::mock::multi_scratch::{MockDevice, PRIMARY, SCRATCH, SECONDARY};
+
+        let strategy = SwapSABS::new(
+            &MockDevice::new(),
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        assert_eq!(strategy.plan_all().count(), strategy.total_operations());
+
+        assert_eq!(
+            strategy.plan_all().next(),
+            Some((
+                Step(0),
+                CopyOperation {
+                    from: MemoryLocation {
+                        slot: PRIMARY,
+                        page: Page(0),
+                    },
+                    to: MemoryLocation {
+                        slot: SCRATCH,
+                        page: Page(0),
+                    },
+                }
+            ))
+        );
+
+        // The last block of 10 pages with 3 scratch pages only holds a single page.
+        assert_eq!(
+            strategy.plan_all().last(),
+            Some((
+                Step(11),
+                CopyOperation {
+                    from: MemoryLocation {
+                        slot: SCRATCH,
+                        page: Page(0),
+                    },
+                    to: MemoryLocation {
+                        slot: SECONDARY,
+                        page: Page(9),
+                    },
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn power_loss() {
+        use crate::mock::multi_scratch::{IMAGE_A, IMAGE_B, MockDevice, SECONDARY};
+
+        let reference = SwapSABS::new(
+            &MockDevice::new(),
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        for step_i in 0..reference.last_step().0 {
+            let step = Step(step_i);
+            for operations in 0..=reference.plan(step).count() {
+                let mut device = MockDevice::new();
+                let strategy = SwapSABS::new(
+                    &device,
+                    Request {
+                        slot_secondary: SECONDARY,
+                    },
+                );
+
+                run_strategy_with_power_loss(
+                    &mut device,
+                    &strategy,
+                    Some(PowerLoss { step, operations }),
+                );
+
+                assert_eq!(device.primary, IMAGE_B);
+                assert_eq!(device.secondary, IMAGE_A);
+            }
+        }
+    }
+
+    #[test]
+    fn safe_points() {
+        use crate::mock::generic::{GenericMock, SECONDARY};
+
+        let device = GenericMock::new(5, 2);
+        let strategy = SwapSABS::new(
+            &device,
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        // Not only the boundaries after the S2B phases, but also those halfway a block are persisted.
+        for step in Step::range(Step::default(), strategy.last_step().next()) {
+            assert!(strategy.is_safe_point(step), "{step:?} should be safe");
+        }
+    }
+
+    #[test]
+    fn step_count() {
+        use crate::mock::generic::{GenericMock, SECONDARY};
+
+        let request = || Request {
+            slot_secondary: SECONDARY,
+        };
+
+        // Three blocks of three phases, instead of three phases for each of the eight pages.
+        let single = SwapSABS::new(&GenericMock::new(8, 1), request());
+        let strategy = SwapSABS::new(&GenericMock::new(8, 3), request());
+        assert_eq!(single.last_step(), Step(24));
+        assert_eq!(strategy.last_step(), Step(9));
+        assert_eq!(strategy.total_operations(), single.total_operations());
+
+        for step_i in 0..strategy.last_step().0 {
+            let step = Step(step_i);
+            for operations in 0..=strategy.plan(step).count() {
+                let mut device = GenericMock::new(8, 3);
+                run_strategy_with_power_loss(
+                    &mut device,
+                    &strategy,
+                    Some(PowerLoss { step, operations }),
+                );
+
+                assert_eq!(device.primary, GenericMock::image_b(8));
+                assert_eq!(device.secondary, GenericMock::image_a(8));
+            }
+        }
+    }
+
+    #[test]
+    fn scratch_base() {
+        use crate::mock::generic::{GenericMock, SECONDARY};
+
+        let mut device = GenericMock::new(5, 2).with_scratch_base(3);
+        assert_eq!(device.check_scratch_capacity(), Ok(()));
+
+        let strategy = SwapSABS::new(
+            &device,
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        run_strategy_to_completion(&mut device, &strategy);
+
+        assert_eq!(device.primary, GenericMock::image_b(5));
+        assert_eq!(device.secondary, GenericMock::image_a(5));
+
+        // Scratch pages before the base are left untouched.
+        assert!(device.scratch[..3].iter().all(|page| *page == u32::MAX));
+    }
+
+    proptest::proptest! {
+        /// Every step maps to a distinct phase and block, such that resuming from a step executes the phase it was stored for.
+        #[test]
+        fn phase_round_trip(page_count in 1u16..64, scratch_page_count in 1u16..8) {
+            let scratch_pages = NonZeroU16::new(scratch_page_count).unwrap();
+            let blocks = page_count.div_ceil(scratch_page_count) as u32;
+
+            for step in Step::range(Step::default(), Step(blocks * 3)) {
+                let (phase, start) = Phase::from_step(step, scratch_pages);
+                proptest::prop_assert_eq!(phase.to_step(start, scratch_pages), step, "{:?}", phase);
+            }
+        }
+
+        /// Every page of both images must be stored somewhere at all times, i.e. no unread data is clobbered.
+        #[test]
+        fn never_clobbers_unread_pages(page_count in 1u16..64, scratch_page_count in 1u16..8) {
+            use crate::mock::generic::{GenericMock, PRIMARY, SECONDARY};
+
+            let mut device = GenericMock::new(page_count, scratch_page_count);
+            let strategy = SwapSABS::new(
+                &device,
+                Request {
+                    slot_secondary: SECONDARY,
+                },
+            );
+
+            for step in Step::range(Step::default(), strategy.last_step()) {
+                for operation in strategy.plan(step) {
+                    embassy_futures::block_on(device.copy(operation)).unwrap();
+                    proptest::prop_assert!(device.holds_all_images(), "lost data at {:?}", step);
+                }
+            }
+
+            proptest::prop_assert_eq!(&device.primary, &GenericMock::image_b(page_count));
+            proptest::prop_assert_eq!(&device.secondary, &GenericMock::image_a(page_count));
+            proptest::prop_assert!(device.wear.check_slot(PRIMARY, 1));
+            proptest::prop_assert!(device.wear.check_slot(SECONDARY, 1));
+        }
+    }
 }
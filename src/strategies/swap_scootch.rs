@@ -1,29 +1,31 @@
 //! Strategy to swap two slots using 'scootch', leaving both intact after finalizing.
 //!
 //! In other bootloaders also called 'swap move'.
-//! It employs a 'scratch' partition as a temporary buffer for one of the pages.
+//! It employs a 'scratch' partition as a temporary buffer for the first block of pages, a block being as large as the scratch.
 //! In order to limit the wear on this specific partition, the primary slot is first scootched over
-//! by one page, before copying the secondary slot over.
+//! by one block, before copying the secondary slot over.
 //!
 //! This results in the first slot enduring two erasures on every page for this strategy, and the second slot enduring a single erasure.
+//! The scratch endures a single erasure on every page, regardless of its size.
 //!
 //! **TODO** Hence it is beneficial to select the slot with the better wear resistance as the primary slot.
 //!
-//! **TODO** Does it help if the scratch memory spans multiple pages? => number of steps?
+//! A larger scratch reduces the number of steps, as every step moves an entire block.
+//! If the number of pages is not a multiple of the scratch size, the last block is partial.
 
 use core::num::NonZeroU16;
 
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    CopyOperation, DeviceWithPrimarySlot, DeviceWithScratch, MemoryLocation, Page, Slot, Step,
-    strategies::Strategy,
+    DeviceWithPrimarySlot, DeviceWithScratch, MemoryLocation, Page, Slot, Step,
+    strategies::{BlockPlan, FromRequest, Strategy},
 };
 
 /// Request to boot a secondary image.
 ///
 /// When the secondary image fails to boot, will perform the swap again, restoring the original situation.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Request {
     pub slot_secondary: Slot,
 }
@@ -31,37 +33,79 @@ pub struct Request {
 pub struct SwapScootch {
     request: Request,
     num_pages: NonZeroU16,
+    scratch_pages: NonZeroU16,
     slot_primary: Slot,
     slot_scratch: Slot,
+    scratch_base: Page,
 }
 
 /// Logical phases for the strategy to execute, to decouple raw steps from behaviour in a logical manner.
-#[derive(Debug)]
-enum Phase {
-    /// Scootch primary down one page, the first being scootched to the scratch.
+///
+/// Each phase operates on a block of pages, starting at the given page.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Phase {
+    /// Scootch a block of primary down one block, the first block being scootched to the scratch.
     Scootch(Page),
-    /// Copy from secondary to primary.
+    /// Copy a block from secondary to primary.
     ToPrimary(Page),
-    /// Copy to secondary from either primary or scratch, considering that the primary has been scootched.
+    /// Copy a block to secondary from either primary or scratch, considering that the primary has been scootched.
     ToSecondary(Page),
 }
 
+/// First page of `block` of `scratch_pages` pages, saturating like [`MemoryLocation::with_page_offset`].
+const fn block_start(block: u32, scratch_pages: u32) -> Page {
+    let index = block.saturating_mul(scratch_pages);
+    Page::new(if index > u16::MAX as u32 {
+        u16::MAX
+    } else {
+        index as u16
+    })
+}
+
 impl Phase {
-    pub const fn from_step(mut step: Step, num_pages: NonZeroU16) -> Phase {
-        if step.0 < num_pages.get() {
-            return Phase::Scootch(Page(step.0));
+    pub const fn from_step(step: Step, num_pages: NonZeroU16, scratch_pages: NonZeroU16) -> Phase {
+        let blocks = num_pages.get().div_ceil(scratch_pages.get()) as u32;
+        let scratch_pages = scratch_pages.get() as u32;
+
+        if step.0 < blocks {
+            return Phase::Scootch(block_start(step.0, scratch_pages));
         }
 
-        step.0 -= num_pages.get();
+        let step = step.saturating_sub(blocks).0;
 
-        // Copy the other pages in reverse order.
-        let page = Page(num_pages.get() - (step.0 / 2) - 1);
-        if step.0 % 2 == 0 {
+        // Copy the other blocks in reverse order.
+        let page = block_start(blocks.saturating_sub(step / 2 + 1), scratch_pages);
+        if step.is_multiple_of(2) {
             Phase::ToPrimary(page)
         } else {
             Phase::ToSecondary(page)
         }
     }
+
+    /// Step in which this phase is executed, being the inverse of [`from_step`](Self::from_step).
+    pub const fn to_step(&self, num_pages: NonZeroU16, scratch_pages: NonZeroU16) -> Step {
+        let blocks = num_pages.get().div_ceil(scratch_pages.get()) as u32;
+        let scratch_pages = scratch_pages.get() as u32;
+
+        match *self {
+            Phase::Scootch(start) => Step(start.0 as u32 / scratch_pages),
+            // The other blocks are copied in reverse order, two steps per block.
+            Phase::ToPrimary(start) => {
+                Step(blocks + 2 * (blocks - start.0 as u32 / scratch_pages - 1))
+            }
+            Phase::ToSecondary(start) => {
+                Step(blocks + 2 * (blocks - start.0 as u32 / scratch_pages - 1) + 1)
+            }
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Phase::Scootch(_) => "scootch primary",
+            Phase::ToPrimary(_) => "secondary to primary",
+            Phase::ToSecondary(_) => "to secondary",
+        }
+    }
 }
 
 impl SwapScootch {
@@ -71,75 +115,122 @@ impl SwapScootch {
     ) -> Self {
         Self {
             num_pages: device.page_count(),
+            scratch_pages: device.scratch_page_count(),
             request,
             slot_primary: device.get_primary(),
             slot_scratch: device.get_scratch(),
+            scratch_base: device.scratch_base(),
         }
     }
 
-    const fn scratch_location(&self) -> MemoryLocation {
-        // TODO what if scratch is more than one page large?
-        MemoryLocation {
-            slot: self.slot_scratch,
-            page: Page(0),
+    /// Location to which the block of primary starting at `start` has been scootched.
+    const fn scootched_location(&self, start: Page) -> MemoryLocation {
+        match start.0.checked_sub(self.scratch_pages.get()) {
+            // Only the first block starts before the end of the first block.
+            None => MemoryLocation {
+                slot: self.slot_scratch,
+                page: self.scratch_base,
+            },
+            Some(page) => MemoryLocation {
+                slot: self.slot_primary,
+                page: Page(page),
+            },
         }
     }
 }
 
+impl<D: DeviceWithScratch + DeviceWithPrimarySlot> FromRequest<D> for SwapScootch {
+    type Request = Request;
+
+    fn from_request(device: &D, request: Request) -> Self {
+        Self::new(device, request)
+    }
+}
+
+/// Plan of a single step of [`SwapScootch`], copying a single block.
+pub type SwapScootchPlan = BlockPlan;
+
 impl Strategy for SwapScootch {
+    type Plan<'a> = SwapScootchPlan;
+
     fn last_step(&self) -> Step {
-        // A single move for scootch, and two copies for swap, plus a single step for boot.
-        Step(self.num_pages.get() * 3)
-    }
+        // Note(div_ceil): the final block might only partially use the scratch pages.
+        let blocks = self.num_pages.get().div_ceil(self.scratch_pages.get());
 
-    fn plan(&self, step: Step) -> impl Iterator<Item = CopyOperation> {
-        let phase = Phase::from_step(step, self.num_pages);
+        // A single move for scootch, and two copies for swap, for each block.
+        Step(blocks as u32 * 3)
+    }
 
-        // Convert a logical phase into a raw copy operation.
-        let op = match phase {
-            Phase::Scootch(page) => CopyOperation {
-                from: MemoryLocation {
-                    slot: self.slot_primary,
-                    page,
-                },
-                to: if page == Page(0) {
-                    self.scratch_location()
-                } else {
+    fn plan(&self, step: Step) -> SwapScootchPlan {
+        // The last step only boots, and the phases are not defined beyond it.
+        let (from, to, start) = if step < self.last_step() {
+            // Convert a logical phase into the locations of the block to copy.
+            match Phase::from_step(step, self.num_pages, self.scratch_pages) {
+                Phase::Scootch(start) => (
                     MemoryLocation {
                         slot: self.slot_primary,
-                        page: Page(page.0 - 1),
-                    }
-                },
-            },
-            // To primary slot is copied 1:1, meaning the same page is copied from secondary.
-            Phase::ToPrimary(page) => CopyOperation {
-                from: MemoryLocation {
-                    slot: self.request.slot_secondary,
-                    page,
-                },
-                to: MemoryLocation {
-                    slot: self.slot_primary,
-                    page,
-                },
-            },
-            // To secondary the page is located in the N-1 page on the primary, of which page 0 is located in scratch.
-            Phase::ToSecondary(page) => CopyOperation {
-                from: if page == Page(0) {
-                    self.scratch_location()
-                } else {
+                        page: start,
+                    },
+                    self.scootched_location(start),
+                    start,
+                ),
+                // To primary slot is copied 1:1, meaning the same pages are copied from secondary.
+                Phase::ToPrimary(start) => (
+                    MemoryLocation {
+                        slot: self.request.slot_secondary,
+                        page: start,
+                    },
                     MemoryLocation {
                         slot: self.slot_primary,
-                        page: Page(page.0 - 1),
-                    }
-                },
-                to: MemoryLocation {
-                    slot: self.request.slot_secondary,
-                    page,
-                },
-            },
+                        page: start,
+                    },
+                    start,
+                ),
+                // To secondary the block is located one block down on the primary, of which the first block is located in scratch.
+                Phase::ToSecondary(start) => (
+                    self.scootched_location(start),
+                    MemoryLocation {
+                        slot: self.request.slot_secondary,
+                        page: start,
+                    },
+                    start,
+                ),
+            }
+        } else {
+            let nowhere = MemoryLocation {
+                slot: self.slot_primary,
+                page: Page(0),
+            };
+            (nowhere, nowhere, Page(self.num_pages.get()))
         };
 
-        core::iter::once(op)
+        // How many pages are in this block? The final block might be partial.
+        let pages_now = u16::min(
+            self.num_pages.get().saturating_sub(start.0),
+            self.scratch_pages.get(),
+        );
+
+        BlockPlan::new(from, to, pages_now)
+    }
+
+    fn phase_name(&self, step: Step) -> &'static str {
+        if step >= self.last_step() {
+            return "boot";
+        }
+
+        Phase::from_step(step, self.num_pages, self.scratch_pages).name()
+    }
+
+    fn requires_scratch(&self) -> bool {
+        true
+    }
+
+    fn image_slot(&self) -> Option<Slot> {
+        Some(self.request.slot_secondary)
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        Some(self.slot_primary)
     }
 
     fn revert(self) -> Option<Self> {
@@ -150,10 +241,129 @@ impl Strategy for SwapScootch {
 
 #[cfg(test)]
 mod tests {
-    use crate::Device;
+    use crate::{
+        Device,
+        mock::{PowerLoss, run_strategy_to_completion, run_strategy_with_power_loss},
+    };
 
     use super::*;
 
+    #[test]
+    fn golden_operations() {
+        use crate::mock::{
+            generic::{PRIMARY, SCRATCH, SECONDARY},
+            recording::{RecordingDevice, operation},
+        };
+
+        let mut device = RecordingDevice::new(3, 1);
+        let strategy = SwapScootch::new(
+            &device,
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        run_strategy_to_completion(&mut device, &strategy);
+
+        assert_eq!(
+            device.operations,
+            [
+                // Scootch the primary image down, its first page into the scratch.
+                operation(PRIMARY, 0, SCRATCH, 0),
+                operation(PRIMARY, 1, PRIMARY, 0),
+                operation(PRIMARY, 2, PRIMARY, 1),
+                // Swap from the last page onwards.
+                operation(SECONDARY, 2, PRIMARY, 2),
+                operation(PRIMARY, 1, SECONDARY, 2),
+                operation(SECONDARY, 1, PRIMARY, 1),
+                operation(PRIMARY, 0, SECONDARY, 1),
+                operation(SECONDARY, 0, PRIMARY, 0),
+                operation(SCRATCH, 0, SECONDARY, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn nonexistent_secondary() {
+        use crate::mock::single_scratch::MockDevice;
+
+        let device = MockDevice::new();
+        let strategy = |slot| {
+            SwapScootch::new(
+                &device,
+                Request {
+                    slot_secondary: Slot(slot),
+                },
+            )
+        };
+
+        assert_eq!(strategy(1).verify_layout(&device), Ok(()));
+        for slot in [device.slot_count(), u8::MAX] {
+            assert_eq!(
+                strategy(slot).verify_layout(&device),
+                Err(crate::strategies::StrategyError::InvalidSlot)
+            );
+        }
+    }
+
+    #[test]
+    fn physical_pages() {
+        use crate::mock::{
+            generic::{GenericMock, PRIMARY, SCRATCH, SECONDARY},
+            physical_pages::PhysicalPagesDevice,
+        };
+
+        // Pages of 4K, spanning four physical pages of the primary slot and two of the scratch.
+        let physical = [(PRIMARY, 1024), (SECONDARY, 4096), (SCRATCH, 2048)];
+        let mut device = PhysicalPagesDevice::new(GenericMock::new(4, 1), 4096, &physical);
+        let strategy = SwapScootch::new(
+            &device,
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        run_strategy_to_completion(&mut device, &strategy);
+
+        assert_eq!(device.inner.primary, GenericMock::image_b(4));
+        assert_eq!(device.inner.secondary, GenericMock::image_a(4));
+
+        // Every physical page endures as many erases as the page it is part of.
+        for (slot, pages, erases) in [(PRIMARY, 4, 4), (SECONDARY, 4, 1), (SCRATCH, 1, 2)] {
+            for page in 0..pages {
+                let wear = device.inner.wear.wear_at(MemoryLocation {
+                    slot,
+                    page: Page(page),
+                });
+                assert!(wear > 0);
+
+                for physical in page * erases..(page + 1) * erases {
+                    let location = MemoryLocation {
+                        slot,
+                        page: Page(physical),
+                    };
+                    assert_eq!(device.physical_wear.wear_at(location), wear, "{location:?}");
+                }
+            }
+
+            let location = MemoryLocation {
+                slot,
+                page: Page(pages * erases),
+            };
+            assert_eq!(device.physical_wear.wear_at(location), 0);
+        }
+    }
+
+    #[test]
+    fn request_eq() {
+        let request = |slot| Request {
+            slot_secondary: Slot(slot),
+        };
+
+        assert_eq!(request(1), request(1));
+        assert_ne!(request(1), request(2));
+    }
+
     #[test]
     fn single_scratch() {
         use crate::mock::single_scratch::{
@@ -172,20 +382,183 @@ mod tests {
         assert_eq!(device.primary, IMAGE_A);
         assert_eq!(device.secondary, IMAGE_B);
 
-        for step_i in 0..strategy.last_step().0 {
+        run_strategy_to_completion(&mut device, &strategy);
+
+        assert_eq!(device.primary, IMAGE_B);
+        assert_eq!(device.secondary, IMAGE_A);
+
+        assert!(device.wear.check_slot(PRIMARY, 2));
+        assert!(device.wear.check_slot(SECONDARY, 1));
+        assert!(device.wear.check_slot(SCRATCH, 1));
+    }
+
+    #[test]
+    fn offset_slots() {
+        use crate::mock::offset_slots::{
+            IMAGE_A, IMAGE_B, MockDevice, PRIMARY, SCRATCH, SECONDARY,
+        };
+
+        let mut device = MockDevice::new();
+
+        let strategy = SwapScootch::new(
+            &device,
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        assert_eq!(device.slot(PRIMARY), IMAGE_A);
+        assert_eq!(device.slot(SECONDARY), IMAGE_B);
+
+        run_strategy_to_completion(&mut device, &strategy);
+
+        assert_eq!(device.slot(PRIMARY), IMAGE_B);
+        assert_eq!(device.slot(SECONDARY), IMAGE_A);
+        assert_eq!(device.slot(SCRATCH), [0x01]);
+
+        // Memory outside of the slots is left untouched.
+        assert_eq!(device.memory[0], 0xFF);
+        assert_eq!(device.memory[4..6], [0xFF, 0xFF]);
+        assert_eq!(device.memory[9], 0xFF);
+        assert_eq!(device.memory[11], 0xFF);
+    }
+
+    #[test]
+    fn power_loss() {
+        use crate::mock::single_scratch::{IMAGE_A, IMAGE_B, MockDevice, SECONDARY};
+
+        let reference = SwapScootch::new(
+            &MockDevice::new(),
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        for step_i in 0..reference.last_step().0 {
             let step = Step(step_i);
-            for operation in strategy.plan(step) {
-                embassy_futures::block_on(async {
-                    device.copy(operation).await.unwrap();
-                })
+            for operations in 0..=reference.plan(step).count() {
+                let mut device = MockDevice::new();
+                let strategy = SwapScootch::new(
+                    &device,
+                    Request {
+                        slot_secondary: SECONDARY,
+                    },
+                );
+
+                run_strategy_with_power_loss(
+                    &mut device,
+                    &strategy,
+                    Some(PowerLoss { step, operations }),
+                );
+
+                assert_eq!(device.primary, IMAGE_B);
+                assert_eq!(device.secondary, IMAGE_A);
             }
         }
+    }
 
-        assert_eq!(device.primary, IMAGE_B);
-        assert_eq!(device.secondary, IMAGE_A);
+    #[test]
+    fn partial_block() {
+        use crate::mock::generic::{GenericMock, PRIMARY, SCRATCH, SECONDARY};
+
+        let mut device = GenericMock::new(5, 2);
+        let strategy = SwapScootch::new(
+            &device,
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        // Three blocks, of which the last only holds a single page.
+        assert_eq!(strategy.last_step(), Step(9));
+        assert_eq!(strategy.total_operations(), 15);
+
+        run_strategy_to_completion(&mut device, &strategy);
+
+        assert_eq!(device.primary, GenericMock::image_b(5));
+        assert_eq!(device.secondary, GenericMock::image_a(5));
 
         assert!(device.wear.check_slot(PRIMARY, 2));
         assert!(device.wear.check_slot(SECONDARY, 1));
         assert!(device.wear.check_slot(SCRATCH, 1));
+
+        for step in Step::range(Step::default(), strategy.last_step()) {
+            for operations in 0..=strategy.plan(step).count() {
+                let mut device = GenericMock::new(5, 2);
+
+                run_strategy_with_power_loss(
+                    &mut device,
+                    &strategy,
+                    Some(PowerLoss { step, operations }),
+                );
+
+                assert_eq!(device.primary, GenericMock::image_b(5));
+                assert_eq!(device.secondary, GenericMock::image_a(5));
+            }
+        }
+    }
+
+    #[test]
+    fn scratch_base() {
+        use crate::mock::generic::{GenericMock, SECONDARY};
+
+        let mut device = GenericMock::new(5, 2).with_scratch_base(3);
+        assert_eq!(device.check_scratch_capacity(), Ok(()));
+
+        let strategy = SwapScootch::new(
+            &device,
+            Request {
+                slot_secondary: SECONDARY,
+            },
+        );
+
+        run_strategy_to_completion(&mut device, &strategy);
+
+        assert_eq!(device.primary, GenericMock::image_b(5));
+        assert_eq!(device.secondary, GenericMock::image_a(5));
+
+        // Scratch pages before the base are left untouched.
+        assert!(device.scratch[..3].iter().all(|page| *page == u32::MAX));
+    }
+
+    proptest::proptest! {
+        /// Every step maps to a distinct phase, such that resuming from a step executes the phase it was stored for.
+        #[test]
+        fn phase_round_trip(page_count in 1u16..64, scratch_page_count in 1u16..8) {
+            let num_pages = NonZeroU16::new(page_count).unwrap();
+            let scratch_pages = NonZeroU16::new(scratch_page_count).unwrap();
+            let blocks = page_count.div_ceil(scratch_page_count) as u32;
+
+            for step in Step::range(Step::default(), Step(blocks * 3)) {
+                let phase = Phase::from_step(step, num_pages, scratch_pages);
+                proptest::prop_assert_eq!(phase.to_step(num_pages, scratch_pages), step, "{:?}", phase);
+            }
+        }
+
+        /// Every page of both images must be stored somewhere at all times, i.e. no unread data is clobbered.
+        #[test]
+        fn never_clobbers_unread_pages(page_count in 1u16..64, scratch_page_count in 1u16..8) {
+            use crate::mock::generic::{GenericMock, PRIMARY, SECONDARY};
+
+            let mut device = GenericMock::new(page_count, scratch_page_count);
+            let strategy = SwapScootch::new(
+                &device,
+                Request {
+                    slot_secondary: SECONDARY,
+                },
+            );
+
+            for step in Step::range(Step::default(), strategy.last_step()) {
+                for operation in strategy.plan(step) {
+                    embassy_futures::block_on(device.copy(operation)).unwrap();
+                    proptest::prop_assert!(device.holds_all_images(), "lost data at {:?}", step);
+                }
+            }
+
+            proptest::prop_assert_eq!(&device.primary, &GenericMock::image_b(page_count));
+            proptest::prop_assert_eq!(&device.secondary, &GenericMock::image_a(page_count));
+            proptest::prop_assert!(device.wear.check_slot(PRIMARY, 2));
+            proptest::prop_assert!(device.wear.check_slot(SECONDARY, 1));
+        }
     }
 }
@@ -0,0 +1,232 @@
+//! Wrapper to spread the wear of the scratch slot over all of its pages.
+//!
+//! Strategies like [`SwapScootch`](super::swap_scootch::SwapScootch) always use the scratch slot from its first page onwards.
+//! When the scratch is larger than what a strategy needs, all scratch erases are concentrated on its first physical pages. By rotating the scratch pages over consecutive requests,
+//! using the offset persisted in [`State::scratch_offset`](crate::state::State::scratch_offset), the erases are spread
+//! over all scratch pages during the lifetime of the device.
+
+use core::{iter::FusedIterator, num::NonZeroU16};
+
+use crate::{
+    CopyOperation, DeviceWithScratch, MemoryLocation, Page, Slot, Step, strategies::Strategy,
+};
+
+/// Strategy that rotates the scratch pages used by the wrapped strategy.
+pub struct WearLeveling<T> {
+    inner: T,
+    rotation: Rotation,
+}
+
+/// Rotation of the pages of the scratch slot by an offset.
+#[derive(Clone, Copy, Debug)]
+struct Rotation {
+    slot_scratch: Slot,
+    scratch_base: Page,
+    scratch_pages: NonZeroU16,
+    offset: u16,
+}
+
+impl Rotation {
+    fn rotate(&self, location: MemoryLocation) -> MemoryLocation {
+        if location.slot != self.slot_scratch {
+            return location;
+        }
+
+        // Widened, as the sum of the index and offset exceeds the page range for scratches of more than half of it.
+        let index = (location.page.0 - self.scratch_base.0) as u32;
+        let rotated = (index + self.offset as u32) % self.scratch_pages.get() as u32;
+
+        // Note(as): the remainder is smaller than the number of scratch pages.
+        MemoryLocation {
+            slot: location.slot,
+            page: self.scratch_base,
+        }
+        .with_page_offset(rotated as u16)
+    }
+}
+
+/// Plan of a single step of [`WearLeveling`], rotating the scratch pages of the plan `P` of the wrapped strategy.
+#[derive(Clone, Debug)]
+pub struct WearLevelingPlan<P> {
+    inner: P,
+    rotation: Rotation,
+}
+
+impl<P: Iterator<Item = CopyOperation>> Iterator for WearLevelingPlan<P> {
+    type Item = CopyOperation;
+
+    fn next(&mut self) -> Option<CopyOperation> {
+        self.inner.next().map(|operation| CopyOperation {
+            from: self.rotation.rotate(operation.from),
+            to: self.rotation.rotate(operation.to),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<P: FusedIterator<Item = CopyOperation>> FusedIterator for WearLevelingPlan<P> {}
+
+impl<T: Strategy> WearLeveling<T> {
+    /// Wrap `inner`, shifting its scratch pages by `scratch_offset`, typically taken from the state.
+    ///
+    /// The offset must remain the same for the entire request, including its reversal.
+    pub fn new(inner: T, device: &impl DeviceWithScratch, scratch_offset: u16) -> Self {
+        let scratch_pages = device.scratch_page_count();
+
+        Self {
+            inner,
+            rotation: Rotation {
+                slot_scratch: device.get_scratch(),
+                scratch_base: device.scratch_base(),
+                scratch_pages,
+                offset: scratch_offset % scratch_pages.get(),
+            },
+        }
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: Strategy> Strategy for WearLeveling<T> {
+    type Plan<'a>
+        = WearLevelingPlan<T::Plan<'a>>
+    where
+        T: 'a;
+
+    fn last_step(&self) -> Step {
+        self.inner.last_step()
+    }
+
+    fn plan(&self, step: Step) -> WearLevelingPlan<T::Plan<'_>> {
+        WearLevelingPlan {
+            inner: self.inner.plan(step),
+            rotation: self.rotation,
+        }
+    }
+
+    fn image_slot(&self) -> Option<Slot> {
+        self.inner.image_slot()
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        self.inner.boot_slot()
+    }
+
+    fn revert(self) -> Option<Self> {
+        let rotation = self.rotation;
+        self.inner.revert().map(|inner| Self { inner, rotation })
+    }
+
+    fn phase_name(&self, step: Step) -> &'static str {
+        self.inner.phase_name(step)
+    }
+
+    fn requires_scratch(&self) -> bool {
+        self.inner.requires_scratch()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock::{
+            generic::{GenericMock, SCRATCH, SECONDARY},
+            run_strategy_to_completion,
+        },
+        state::State,
+        strategies::{swap_sabs, swap_scootch},
+    };
+
+    #[test]
+    fn spreads_scratch_wear() {
+        // The image is smaller than the scratch, hence a single swap only uses half of the scratch.
+        let mut device = GenericMock::new(2, 4);
+        let mut state = State::<swap_scootch::Request>::default();
+
+        for _ in 0..8 {
+            let strategy = WearLeveling::new(
+                swap_scootch::SwapScootch::new(
+                    &device,
+                    swap_scootch::Request {
+                        slot_secondary: SECONDARY,
+                    },
+                ),
+                &device,
+                state.scratch_offset,
+            );
+
+            run_strategy_to_completion(&mut device, &strategy);
+            state.rotate_scratch(device.scratch_page_count());
+        }
+
+        // An even number of swaps restores the original images.
+        assert_eq!(device.primary, GenericMock::image_a(2));
+        assert_eq!(device.secondary, GenericMock::image_b(2));
+
+        for page in 0..4 {
+            let location = MemoryLocation {
+                slot: SCRATCH,
+                page: Page(page),
+            };
+            assert_eq!(device.wear.wear_at(location), 4);
+        }
+    }
+
+    #[test]
+    fn huge_scratch() {
+        // Both the index and the offset fit the page range, but their sum does not.
+        let device = GenericMock::new(40_000, 40_000);
+        let strategy = WearLeveling::new(
+            swap_scootch::SwapScootch::new(
+                &device,
+                swap_scootch::Request {
+                    slot_secondary: SECONDARY,
+                },
+            ),
+            &device,
+            39_999,
+        );
+
+        let targets: std::vec::Vec<u16> = strategy
+            .plan(Step::default())
+            .map(|operation| operation.to.page.0)
+            .collect();
+        assert_eq!(targets.len(), 40_000);
+        assert_eq!(targets[0], 39_999);
+        assert!(targets[1..].iter().copied().eq(0..39_999));
+    }
+
+    #[test]
+    fn composes_with_partial_blocks() {
+        for offset in 0..3 {
+            let mut device = GenericMock::new(5, 3);
+            let strategy = WearLeveling::new(
+                swap_sabs::SwapSABS::new(
+                    &device,
+                    swap_sabs::Request {
+                        slot_secondary: SECONDARY,
+                    },
+                ),
+                &device,
+                offset,
+            );
+
+            run_strategy_to_completion(&mut device, &strategy);
+
+            assert_eq!(device.primary, GenericMock::image_b(5));
+            assert_eq!(device.secondary, GenericMock::image_a(5));
+
+            let strategy = strategy.revert().unwrap();
+            run_strategy_to_completion(&mut device, &strategy);
+
+            assert_eq!(device.primary, GenericMock::image_a(5));
+            assert_eq!(device.secondary, GenericMock::image_b(5));
+        }
+    }
+}
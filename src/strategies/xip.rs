@@ -2,7 +2,10 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::{Device, Slot, Step, strategies::Strategy};
+use crate::{
+    Device, Slot, Step,
+    strategies::{BlockPlan, FromRequest, Strategy},
+};
 
 /// Request to boot a target image.
 ///
@@ -10,7 +13,7 @@ use crate::{Device, Slot, Step, strategies::Strategy};
 ///
 /// * Note that if the backup is not provided, the device might brick itself.
 /// * Note that the backup should have run successfully previously to ensure successful operation.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Request {
     pub slot_target: Slot,
     pub slot_backup: Option<Slot>,
@@ -34,13 +37,34 @@ impl Xip {
     }
 }
 
+impl<D: Device> FromRequest<D> for Xip {
+    type Request = Request;
+
+    fn from_request(device: &D, request: Request) -> Self {
+        Self::new(device, request)
+    }
+}
+
+/// Plan of a single step of [`Xip`], which never copies anything.
+pub type XipPlan = BlockPlan;
+
 impl Strategy for Xip {
+    type Plan<'a> = XipPlan;
+
     fn last_step(&self) -> Step {
         Step(0)
     }
 
-    fn plan(&self, _step: crate::Step) -> impl Iterator<Item = crate::CopyOperation> {
-        core::iter::empty()
+    fn plan(&self, _step: Step) -> XipPlan {
+        BlockPlan::empty()
+    }
+
+    fn image_slot(&self) -> Option<Slot> {
+        Some(self.request.slot_target)
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        Some(self.request.slot_target)
     }
 
     fn revert(self) -> Option<Self> {
@@ -0,0 +1,178 @@
+//! Strategy to execute an image in place if its slot allows for it, and to copy it into the primary slot otherwise.
+//!
+//! Useful if whether a slot is executable is only known at runtime, for example when the memory mapping of external flash is optional.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DeviceWithPrimarySlot, Slot, Step,
+    strategies::{
+        BlockPlan, FromRequest, Strategy,
+        copy::{self, Copy},
+        xip::{self, Xip},
+    },
+};
+
+/// Request to boot a target image, with an optional backup if the target image is invalid.
+///
+/// * Note that if the backup is not provided, the device might brick itself.
+/// * Note that the backup should have run successfully previously to ensure successful operation.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub slot_target: Slot,
+    pub slot_backup: Option<Slot>,
+}
+
+/// Either [`Xip`] or [`Copy`], depending on whether the target slot is [executable](crate::Device::is_executable).
+///
+/// The backup is handled alike the target, hence should be executable if the target is.
+pub struct XipOrCopy {
+    request: Request,
+    slot_primary: Slot,
+    inner: Inner,
+}
+
+enum Inner {
+    Xip(Xip),
+    Copy(Copy),
+}
+
+impl XipOrCopy {
+    pub fn new(device: &impl DeviceWithPrimarySlot, request: Request) -> Self {
+        let inner = if device.is_executable(request.slot_target) {
+            Inner::Xip(Xip::new(
+                device,
+                xip::Request {
+                    slot_target: request.slot_target,
+                    slot_backup: request.slot_backup,
+                },
+            ))
+        } else {
+            Inner::Copy(Copy::new(
+                device,
+                copy::Request {
+                    slot_secondary: request.slot_target,
+                    slot_backup: request.slot_backup,
+                    image_pages: None,
+                },
+            ))
+        };
+
+        Self {
+            request,
+            slot_primary: device.get_primary(),
+            inner,
+        }
+    }
+
+    /// Whether the target image is executed in place, rather than copied.
+    pub fn is_xip(&self) -> bool {
+        matches!(self.inner, Inner::Xip(_))
+    }
+}
+
+impl<D: DeviceWithPrimarySlot> FromRequest<D> for XipOrCopy {
+    type Request = Request;
+
+    fn from_request(device: &D, request: Request) -> Self {
+        Self::new(device, request)
+    }
+}
+
+/// Plan of a single step of [`XipOrCopy`].
+pub type XipOrCopyPlan = BlockPlan;
+
+impl Strategy for XipOrCopy {
+    type Plan<'a> = XipOrCopyPlan;
+
+    fn last_step(&self) -> Step {
+        match &self.inner {
+            Inner::Xip(strategy) => strategy.last_step(),
+            Inner::Copy(strategy) => strategy.last_step(),
+        }
+    }
+
+    fn plan(&self, step: Step) -> XipOrCopyPlan {
+        match &self.inner {
+            Inner::Xip(strategy) => strategy.plan(step),
+            Inner::Copy(strategy) => strategy.plan(step),
+        }
+    }
+
+    fn phase_name(&self, step: Step) -> &'static str {
+        match &self.inner {
+            Inner::Xip(strategy) => strategy.phase_name(step),
+            Inner::Copy(strategy) => strategy.phase_name(step),
+        }
+    }
+
+    fn image_slot(&self) -> Option<Slot> {
+        Some(self.request.slot_target)
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        Some(match self.inner {
+            Inner::Xip(_) => self.request.slot_target,
+            Inner::Copy(_) => self.slot_primary,
+        })
+    }
+
+    fn revert(self) -> Option<Self> {
+        let inner = match self.inner {
+            Inner::Xip(strategy) => Inner::Xip(strategy.revert()?),
+            Inner::Copy(strategy) => Inner::Copy(strategy.revert()?),
+        };
+
+        Some(Self {
+            request: Request {
+                slot_target: self.request.slot_backup?,
+                slot_backup: None,
+            },
+            slot_primary: self.slot_primary,
+            inner,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{
+        run_strategy_to_completion,
+        tri_slot::{ALPHA, BETA, IMAGE_A, IMAGE_B, MockDevice, PRIMARY},
+    };
+
+    #[test]
+    fn test() {
+        let mut device = MockDevice::new();
+
+        // Only the primary slot is executable, hence the image in beta is copied.
+        let strategy = XipOrCopy::new(
+            &device,
+            Request {
+                slot_target: BETA,
+                slot_backup: Some(ALPHA),
+            },
+        );
+        assert!(!strategy.is_xip());
+        assert_eq!(strategy.boot_slot(), Some(PRIMARY));
+
+        run_strategy_to_completion(&mut device, &strategy);
+        assert_eq!(device.primary, IMAGE_B);
+
+        let strategy = strategy.revert().unwrap();
+        run_strategy_to_completion(&mut device, &strategy);
+        assert_eq!(device.primary, IMAGE_A);
+
+        let strategy = XipOrCopy::new(
+            &device,
+            Request {
+                slot_target: PRIMARY,
+                slot_backup: None,
+            },
+        );
+        assert!(strategy.is_xip());
+        assert_eq!(strategy.boot_slot(), Some(PRIMARY));
+        assert_eq!(strategy.total_operations(), 0);
+    }
+}
@@ -0,0 +1,171 @@
+//! Strategy to run an image in place from the secondary slot straight away, and migrate it into the primary slot once confirmed.
+//!
+//! Useful if the secondary slot resides in a memory the device can execute from, but slower than the primary slot,
+//! for example external flash over QSPI next to internal flash. The new image runs without waiting for a copy,
+//! whereas subsequent boots run from the faster primary slot.
+//!
+//! The update spans two boots, of which each is a separate request:
+//! 1. The image is booted in place from the secondary slot, without copying anything.
+//!    If it fails to boot, the primary slot still holds the previous image.
+//! 2. Once the image is confirmed, the application requests the [`confirmed`](Request::confirmed) request using
+//!    [`State::confirm_and_request`](crate::state::State::confirm_and_request), after which the next boot copies the image into the primary slot.
+
+use core::num::NonZeroU16;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DeviceWithPrimarySlot, MemoryLocation, Page, Slot, Step,
+    strategies::{BlockPlan, FromRequest, Strategy},
+};
+
+/// Request to run the image in a secondary slot, and to migrate it into the primary slot once confirmed.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Request {
+    /// The image to run in place, and to copy to the primary slot.
+    pub slot_secondary: Slot,
+    /// Whether the image has been confirmed, and is to be copied into the primary slot.
+    pub migrate: bool,
+}
+
+impl Request {
+    /// Request to run the image in `slot_secondary` in place.
+    pub const fn new(slot_secondary: Slot) -> Self {
+        Self {
+            slot_secondary,
+            migrate: false,
+        }
+    }
+
+    /// Request to copy the confirmed image into the primary slot.
+    pub const fn confirmed(&self) -> Self {
+        Self {
+            slot_secondary: self.slot_secondary,
+            migrate: true,
+        }
+    }
+}
+
+pub struct XipThenCopy {
+    request: Request,
+    num_pages: NonZeroU16,
+    slot_primary: Slot,
+}
+
+impl XipThenCopy {
+    pub fn new(device: &impl DeviceWithPrimarySlot, request: Request) -> Self {
+        Self {
+            request,
+            num_pages: device.page_count(),
+            slot_primary: device.get_primary(),
+        }
+    }
+}
+
+impl<D: DeviceWithPrimarySlot> FromRequest<D> for XipThenCopy {
+    type Request = Request;
+
+    fn from_request(device: &D, request: Request) -> Self {
+        Self::new(device, request)
+    }
+}
+
+/// Plan of a single step of [`XipThenCopy`].
+pub type XipThenCopyPlan = BlockPlan;
+
+impl Strategy for XipThenCopy {
+    type Plan<'a> = XipThenCopyPlan;
+
+    fn last_step(&self) -> Step {
+        // Running in place boots immediately, whereas migrating copies everything in a single step like `Copy`.
+        Step(self.request.migrate as u32)
+    }
+
+    fn plan(&self, step: Step) -> XipThenCopyPlan {
+        // Nothing is left to copy once the last step has been reached.
+        let num_pages = if step < self.last_step() {
+            self.num_pages.get()
+        } else {
+            0
+        };
+
+        BlockPlan::new(
+            MemoryLocation {
+                slot: self.request.slot_secondary,
+                page: Page(0),
+            },
+            MemoryLocation {
+                slot: self.slot_primary,
+                page: Page(0),
+            },
+            num_pages,
+        )
+    }
+
+    fn phase_name(&self, step: Step) -> &'static str {
+        match (step < self.last_step(), self.request.migrate) {
+            (true, _) => "migrate",
+            (false, false) => "xip",
+            (false, true) => "boot",
+        }
+    }
+
+    fn image_slot(&self) -> Option<Slot> {
+        Some(self.request.slot_secondary)
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        Some(if self.request.migrate {
+            self.slot_primary
+        } else {
+            self.request.slot_secondary
+        })
+    }
+
+    fn revert(self) -> Option<Self> {
+        // Running in place left the primary slot intact, and a migrated image has been confirmed already.
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock::{
+            run_strategy_to_completion,
+            tri_slot::{BETA, IMAGE_A, IMAGE_B, MockDevice, PRIMARY},
+        },
+        state::State,
+    };
+
+    #[test]
+    fn test() {
+        let mut device = MockDevice::new();
+        let mut state = State::new_request(Request::new(BETA));
+
+        // The image runs in place, without touching any memory.
+        let strategy = XipThenCopy::new(&device, state.request.as_ref().unwrap().strategy.clone());
+        assert_eq!(strategy.last_step(), Step(0));
+        run_strategy_to_completion(&mut device, &strategy);
+        assert_eq!(strategy.boot_slot(), Some(BETA));
+        assert_eq!(device.primary, IMAGE_A);
+        assert!(strategy.revert().is_none());
+
+        // Once confirmed, the next boot migrates the image.
+        let request = state.request.as_ref().unwrap().strategy.confirmed();
+        state.confirm_and_request(2, request);
+        assert_eq!(state.min_version, 2);
+        assert!(state.is_update_pending());
+
+        let strategy = XipThenCopy::new(&device, state.request.as_ref().unwrap().strategy.clone());
+        assert_eq!(strategy.phase_name(Step(0)), "migrate");
+        run_strategy_to_completion(&mut device, &strategy);
+        assert_eq!(strategy.boot_slot(), Some(PRIMARY));
+        assert_eq!(device.primary, IMAGE_B);
+        assert_eq!(device.beta, IMAGE_B);
+
+        state.confirm(2);
+        assert!(state.request.is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! Conformance checks for implementers of [`Device`](crate::Device).
+//!
+//! Strategies rely on a few properties of [`Device::copy`](crate::Device::copy) that the type system cannot express:
+//! * the source page is left intact;
+//! * the destination page is erased when necessary, such that it ends up holding exactly the source page;
+//! * copying a page again yields the same result, as a step interrupted by power loss is executed again;
+//! * no other page than the destination page is touched.
+//!
+//! Running [`assert_device_contract`] against your device, for example in a test on the target, checks these properties.
+//! It only requires [`Device::copy`](crate::Device::copy), [`Device::page_count`](crate::Device::page_count),
+//! and [`DeviceWithRead::read`] to observe the effect of each copy.
+
+use crate::{CopyOperation, DeviceWithRead, MemoryLocation, Page, Slot};
+
+/// Number of bytes at the start of each page which are compared to check the contents of a page.
+const SAMPLE_SIZE: usize = 4;
+
+async fn sample(device: &mut impl DeviceWithRead, slot: Slot, page: u16) -> [u8; SAMPLE_SIZE] {
+    let mut buffer = [0u8; SAMPLE_SIZE];
+    device
+        .read(
+            MemoryLocation {
+                slot,
+                page: Page(page),
+            },
+            0,
+            &mut buffer,
+        )
+        .await
+        .expect("read should succeed");
+    buffer
+}
+
+async fn copy(device: &mut impl DeviceWithRead, from: (Slot, u16), to: (Slot, u16)) {
+    device
+        .copy(CopyOperation {
+            from: MemoryLocation {
+                slot: from.0,
+                page: Page(from.1),
+            },
+            to: MemoryLocation {
+                slot: to.0,
+                page: Page(to.1),
+            },
+        })
+        .await
+        .expect("copy should succeed");
+}
+
+/// Check that copying the pages of `slot_from` into `slot_to` honours the contract of [`Device::copy`](crate::Device::copy).
+///
+/// Every page of `slot_from` is copied into `slot_to` twice, after which the last page of `slot_to` is overwritten by the first page of `slot_from`.
+/// The contents of `slot_to` are thus destroyed, whereas `slot_from` is left intact.
+/// Pages are compared by their first few bytes, hence the images in the slots should differ for those bytes.
+///
+/// # Panics
+/// Panics when an operation fails or the contract is violated.
+pub async fn assert_device_contract(
+    device: &mut impl DeviceWithRead,
+    slot_from: Slot,
+    slot_to: Slot,
+) {
+    let page_count = device.page_count().get();
+
+    for page in 0..page_count {
+        let expected = sample(device, slot_from, page).await;
+
+        copy(device, (slot_from, page), (slot_to, page)).await;
+        assert_eq!(
+            sample(device, slot_from, page).await,
+            expected,
+            "copy should leave the source page intact"
+        );
+        assert_eq!(
+            sample(device, slot_to, page).await,
+            expected,
+            "copy should overwrite the destination page"
+        );
+
+        copy(device, (slot_from, page), (slot_to, page)).await;
+        assert_eq!(
+            sample(device, slot_to, page).await,
+            expected,
+            "copying a page again should yield the same page"
+        );
+
+        if let Some(previous) = page.checked_sub(1) {
+            assert_eq!(
+                sample(device, slot_to, previous).await,
+                sample(device, slot_from, previous).await,
+                "copy should not touch the preceding page"
+            );
+        }
+    }
+
+    let last = page_count - 1;
+    if last > 0 {
+        let expected = sample(device, slot_from, 0).await;
+        copy(device, (slot_from, 0), (slot_to, last)).await;
+        assert_eq!(
+            sample(device, slot_to, last).await,
+            expected,
+            "copy should write to the destination page index"
+        );
+        assert_eq!(
+            sample(device, slot_to, 0).await,
+            expected,
+            "copy should not touch other pages"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::generic::{GenericMock, PRIMARY, SCRATCH, SECONDARY};
+
+    #[test]
+    fn generic_mock() {
+        let mut device = GenericMock::new(5, 2);
+        embassy_futures::block_on(assert_device_contract(&mut device, SECONDARY, PRIMARY));
+        assert_eq!(device.primary[..4], GenericMock::image_b(5)[..4]);
+        assert_eq!(device.primary[4], GenericMock::image_b(5)[0]);
+
+        // Copies into the scratch memory, which is only as large as the scratch itself.
+        let mut device = GenericMock::new(1, 1);
+        embassy_futures::block_on(assert_device_contract(&mut device, PRIMARY, SCRATCH));
+    }
+}